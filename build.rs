@@ -0,0 +1,14 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // 用纯 Rust 的 protox 解析 proto，构建时不需要安装 protoc
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/snapshot.proto");
+        let fds = protox::compile(["proto/snapshot.proto"], ["proto"]).expect("valid proto");
+        tonic_prost_build::configure()
+            .build_client(false)
+            .build_transport(false)
+            .compile_fds(fds)
+            .expect("generate gRPC code");
+    }
+}
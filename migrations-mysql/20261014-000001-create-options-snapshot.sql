@@ -0,0 +1,23 @@
+-- MySQL / MariaDB 版本的 t_options_cboe_snapshot 表
+CREATE TABLE IF NOT EXISTS t_options_cboe_snapshot
+(
+    id                BIGINT       NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    symbol            VARCHAR(32)  NOT NULL,
+    call_put          VARCHAR(8)   NOT NULL,
+    expiration        VARCHAR(16)  NOT NULL,
+    strike_price      DOUBLE       NOT NULL,
+    volume            BIGINT       NOT NULL,
+    matched           BIGINT       NOT NULL,
+    routed            BIGINT       NOT NULL,
+    bid_size          BIGINT       NOT NULL,
+    bid_price         DOUBLE       NOT NULL,
+    ask_size          BIGINT       NOT NULL,
+    ask_price         DOUBLE       NOT NULL,
+    last_price        DOUBLE       NOT NULL,
+    last_updated_time DATETIME     NOT NULL,
+    etl_in_dt         DATETIME     NOT NULL,
+    UNIQUE KEY t_options_cboe_snapshot_uk (symbol, call_put, expiration, strike_price, last_updated_time),
+    KEY idx_options_symbol (symbol),
+    KEY idx_options_expiration (expiration),
+    KEY idx_options_last_updated (last_updated_time)
+);
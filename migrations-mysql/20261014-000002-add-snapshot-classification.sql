@@ -0,0 +1,5 @@
+-- 加载时写入的合约分类：剩余天数和到期类型（weekly / monthly / quarterly / leap）
+ALTER TABLE t_options_cboe_snapshot
+    ADD COLUMN days_to_expiration INT,
+    ADD COLUMN expiration_class   VARCHAR(16),
+    ADD KEY idx_options_expiration_class (expiration_class);
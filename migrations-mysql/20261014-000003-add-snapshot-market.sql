@@ -0,0 +1,6 @@
+-- 数据来源市场（cone / opt / ctwo / exo）和可选的请求地址，市场加入唯一键
+ALTER TABLE t_options_cboe_snapshot
+    ADD COLUMN market     VARCHAR(8)   NOT NULL DEFAULT '',
+    ADD COLUMN source_url VARCHAR(255),
+    DROP KEY t_options_cboe_snapshot_uk,
+    ADD UNIQUE KEY t_options_cboe_snapshot_uk (symbol, call_put, expiration, strike_price, last_updated_time, market);
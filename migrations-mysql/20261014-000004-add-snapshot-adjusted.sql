@@ -0,0 +1,3 @@
+-- 调整合约（代码带数字后缀、行权价不是 0.25 的整数倍），加载时写入
+ALTER TABLE t_options_cboe_snapshot
+    ADD COLUMN is_adjusted BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,9 @@
+-- 与 Postgres 的快照表保持相同的列：数据来源（symbol_data 为完整快照）、到期标记和成交量增量
+-- MySQL 后端只做加载，这些列按默认值写入，保证两边的数据可以直接互相导入
+ALTER TABLE t_options_cboe_snapshot
+    ADD COLUMN source        VARCHAR(32) NOT NULL DEFAULT 'symbol_data' AFTER expiration_class,
+    ADD COLUMN is_expired    BOOLEAN     NOT NULL DEFAULT false AFTER source_url,
+    ADD COLUMN volume_delta  BIGINT AFTER is_expired,
+    ADD COLUMN matched_delta BIGINT AFTER volume_delta,
+    ADD COLUMN routed_delta  BIGINT AFTER matched_delta,
+    ADD KEY idx_options_source_last_updated (source, last_updated_time);
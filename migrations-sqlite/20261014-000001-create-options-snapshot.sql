@@ -0,0 +1,34 @@
+-- SQLite 版本的 t_options_cboe_snapshot 表，本地文件第一次打开时自动创建；列与 Postgres 的快照表一致
+CREATE TABLE IF NOT EXISTS t_options_cboe_snapshot
+(
+    id                INTEGER PRIMARY KEY AUTOINCREMENT,
+    symbol            TEXT    NOT NULL,
+    call_put          TEXT    NOT NULL,
+    expiration        TEXT    NOT NULL,
+    strike_price      REAL    NOT NULL,
+    volume            INTEGER NOT NULL,
+    matched           INTEGER NOT NULL,
+    routed            INTEGER NOT NULL,
+    bid_size          INTEGER NOT NULL,
+    bid_price         REAL    NOT NULL,
+    ask_size          INTEGER NOT NULL,
+    ask_price         REAL    NOT NULL,
+    last_price        REAL    NOT NULL,
+    last_updated_time TEXT    NOT NULL,
+    etl_in_dt         TEXT    NOT NULL,
+    days_to_expiration INTEGER,
+    expiration_class  TEXT,
+    source            TEXT    NOT NULL DEFAULT 'symbol_data',
+    market            TEXT    NOT NULL DEFAULT '',
+    source_url        TEXT,
+    is_expired        INTEGER NOT NULL DEFAULT 0,
+    volume_delta      INTEGER,
+    matched_delta     INTEGER,
+    routed_delta      INTEGER,
+    is_adjusted       INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (symbol, call_put, expiration, strike_price, last_updated_time, market)
+);
+
+CREATE INDEX IF NOT EXISTS idx_options_symbol ON t_options_cboe_snapshot (symbol);
+CREATE INDEX IF NOT EXISTS idx_options_expiration ON t_options_cboe_snapshot (expiration);
+CREATE INDEX IF NOT EXISTS idx_options_last_updated ON t_options_cboe_snapshot (last_updated_time);
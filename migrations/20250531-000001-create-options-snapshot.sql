@@ -1,7 +1,8 @@
 -- 创建 t_options_cboe_snapshot 表
 CREATE TABLE IF NOT EXISTS t_options_cboe_snapshot
 (
-    id                SERIAL PRIMARY KEY,
+    -- id 只是自增序号，主键是下面的复合键（一张表只能有一个主键）
+    id                SERIAL,
     symbol            TEXT             NOT NULL,
     call_put          TEXT             NOT NULL,
     expiration        TEXT             NOT NULL,
@@ -0,0 +1,17 @@
+-- 创建 t_cboe_snapshot_etl_log 表，记录每次运行情况
+CREATE TABLE IF NOT EXISTS t_cboe_snapshot_etl_log
+(
+    id            BIGSERIAL PRIMARY KEY,
+    started_at    TIMESTAMP NOT NULL,
+    finished_at   TIMESTAMP,
+    markets       TEXT,
+    snapshot_time TIMESTAMP,
+    rows_fetched  BIGINT,
+    rows_inserted BIGINT,
+    parse_errors  BIGINT,
+    http_bytes    BIGINT,
+    outcome       TEXT      NOT NULL,
+    error_message TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_etl_log_started_at ON t_cboe_snapshot_etl_log (started_at);
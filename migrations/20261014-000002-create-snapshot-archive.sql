@@ -0,0 +1,5 @@
+-- 创建归档表，prune --mode archive 时把过期快照移到这里
+CREATE TABLE IF NOT EXISTS t_options_cboe_snapshot_archive
+(
+    LIKE t_options_cboe_snapshot INCLUDING ALL
+);
@@ -0,0 +1,15 @@
+-- 每次运行中每个市场的加载进度，分批提交时记录已提交的行数
+CREATE TABLE IF NOT EXISTS t_cboe_snapshot_etl_log_market
+(
+    run_id         BIGINT    NOT NULL REFERENCES t_cboe_snapshot_etl_log (id),
+    market         TEXT      NOT NULL,
+    snapshot_time  TIMESTAMP NOT NULL,
+    rows_committed BIGINT    NOT NULL DEFAULT 0,
+    batches        BIGINT    NOT NULL DEFAULT 0,
+    status         TEXT      NOT NULL,
+    updated_at     TIMESTAMP NOT NULL,
+    constraint t_cboe_snapshot_etl_log_market_pk
+        primary key (run_id, market)
+);
+
+CREATE INDEX IF NOT EXISTS idx_etl_log_market_snapshot ON t_cboe_snapshot_etl_log_market (market, snapshot_time);
@@ -0,0 +1,2 @@
+-- 记录本次运行使用的 symbol 过滤条件
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS symbol_filter TEXT;
@@ -0,0 +1,2 @@
+-- --skip-inactive 跳过的无成交、无报价行数
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS rows_skipped BIGINT;
@@ -0,0 +1,17 @@
+-- 数据质量校验发现的问题
+CREATE TABLE IF NOT EXISTS t_cboe_snapshot_dq_issues
+(
+    id            BIGSERIAL PRIMARY KEY,
+    run_id        BIGINT           NOT NULL REFERENCES t_cboe_snapshot_etl_log (id),
+    market        TEXT             NOT NULL,
+    snapshot_time TIMESTAMP        NOT NULL,
+    rule          TEXT             NOT NULL,
+    symbol        TEXT             NOT NULL,
+    call_put      TEXT             NOT NULL,
+    expiration    TEXT             NOT NULL,
+    strike_price  DOUBLE PRECISION NOT NULL,
+    detail        TEXT             NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dq_issues_run_id ON t_cboe_snapshot_dq_issues (run_id);
+CREATE INDEX IF NOT EXISTS idx_dq_issues_snapshot ON t_cboe_snapshot_dq_issues (snapshot_time, rule);
@@ -0,0 +1,9 @@
+-- 加载时写入的合约分类：剩余天数和到期类型（weekly / monthly / quarterly / leap）
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS days_to_expiration INTEGER;
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS expiration_class TEXT;
+
+-- 归档表保持与快照表相同的列顺序，prune --mode archive 按 SELECT * 搬运
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS days_to_expiration INTEGER;
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS expiration_class TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_options_expiration_class ON t_options_cboe_snapshot (expiration_class);
@@ -0,0 +1,17 @@
+-- 异常成交 / 报价提醒
+CREATE TABLE IF NOT EXISTS t_options_cboe_alerts
+(
+    id            BIGSERIAL PRIMARY KEY,
+    run_id        BIGINT           NOT NULL REFERENCES t_cboe_snapshot_etl_log (id),
+    market        TEXT             NOT NULL,
+    snapshot_time TIMESTAMP        NOT NULL,
+    rule          TEXT             NOT NULL,
+    symbol        TEXT             NOT NULL,
+    call_put      TEXT             NOT NULL,
+    expiration    TEXT             NOT NULL,
+    strike_price  DOUBLE PRECISION NOT NULL,
+    detail        TEXT             NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_alerts_snapshot ON t_options_cboe_alerts (snapshot_time, rule);
+CREATE INDEX IF NOT EXISTS idx_alerts_symbol ON t_options_cboe_alerts (symbol, snapshot_time);
@@ -0,0 +1,11 @@
+-- [[datasets]] 配置的 CBOE 市场统计数据，每个数据集每天一行，原始内容存为 JSONB
+CREATE TABLE IF NOT EXISTS t_cboe_market_stats
+(
+    id         BIGSERIAL PRIMARY KEY,
+    dataset    TEXT      NOT NULL,
+    stat_date  DATE      NOT NULL,
+    source_url TEXT      NOT NULL,
+    fetched_at TIMESTAMP NOT NULL,
+    payload    JSONB     NOT NULL,
+    UNIQUE (dataset, stat_date)
+);
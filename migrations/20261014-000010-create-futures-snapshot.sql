@@ -0,0 +1,18 @@
+-- CFE（CBOE 期货交易所）合约快照，和期权快照使用同一个 last_updated_time
+CREATE TABLE IF NOT EXISTS t_futures_cboe_snapshot
+(
+    id                BIGSERIAL PRIMARY KEY,
+    symbol            TEXT             NOT NULL,
+    expiration        TEXT             NOT NULL,
+    volume            BIGINT           NOT NULL,
+    bid_size          BIGINT           NOT NULL,
+    bid_price         DOUBLE PRECISION NOT NULL,
+    ask_size          BIGINT           NOT NULL,
+    ask_price         DOUBLE PRECISION NOT NULL,
+    last_price        DOUBLE PRECISION NOT NULL,
+    last_updated_time TIMESTAMP        NOT NULL,
+    etl_in_dt         TIMESTAMP        NOT NULL,
+    UNIQUE (symbol, expiration, last_updated_time)
+);
+
+CREATE INDEX IF NOT EXISTS idx_futures_last_updated ON t_futures_cboe_snapshot (last_updated_time);
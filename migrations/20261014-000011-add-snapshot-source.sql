@@ -0,0 +1,5 @@
+-- 数据来源：symbol_data 为完整快照，delayed_quotes 为 watchlist 的延迟报价
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS source TEXT NOT NULL DEFAULT 'symbol_data';
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS source TEXT NOT NULL DEFAULT 'symbol_data';
+
+CREATE INDEX IF NOT EXISTS idx_options_source_last_updated ON t_options_cboe_snapshot (source, last_updated_time);
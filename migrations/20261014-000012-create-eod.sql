@@ -0,0 +1,23 @@
+-- consolidate 子命令生成的日终汇总：当天最后报价、累计成交量、last_price 最高 / 最低
+CREATE TABLE IF NOT EXISTS t_options_cboe_eod
+(
+    trade_date     DATE             NOT NULL,
+    symbol         TEXT             NOT NULL,
+    call_put       TEXT             NOT NULL,
+    expiration     TEXT             NOT NULL,
+    strike_price   DOUBLE PRECISION NOT NULL,
+    volume         BIGINT           NOT NULL,
+    bid_size       BIGINT           NOT NULL,
+    bid_price      DOUBLE PRECISION NOT NULL,
+    ask_size       BIGINT           NOT NULL,
+    ask_price      DOUBLE PRECISION NOT NULL,
+    last_price     DOUBLE PRECISION NOT NULL,
+    high_price     DOUBLE PRECISION,
+    low_price      DOUBLE PRECISION,
+    snapshots      BIGINT           NOT NULL,
+    first_snapshot TIMESTAMP        NOT NULL,
+    last_snapshot  TIMESTAMP        NOT NULL,
+    PRIMARY KEY (trade_date, symbol, call_put, expiration, strike_price)
+);
+
+CREATE INDEX IF NOT EXISTS idx_eod_symbol ON t_options_cboe_eod (symbol, trade_date);
@@ -0,0 +1,21 @@
+-- 最新一个完整快照的副本，开启 storage.latest_table 后每次加载结束时刷新
+CREATE TABLE IF NOT EXISTS t_options_cboe_latest
+(
+    symbol             TEXT             NOT NULL,
+    call_put           TEXT             NOT NULL,
+    expiration         TEXT             NOT NULL,
+    strike_price       DOUBLE PRECISION NOT NULL,
+    volume             BIGINT           NOT NULL,
+    matched            BIGINT           NOT NULL,
+    routed             BIGINT           NOT NULL,
+    bid_size           BIGINT           NOT NULL,
+    bid_price          DOUBLE PRECISION NOT NULL,
+    ask_size           BIGINT           NOT NULL,
+    ask_price          DOUBLE PRECISION NOT NULL,
+    last_price         DOUBLE PRECISION NOT NULL,
+    last_updated_time  TIMESTAMP        NOT NULL,
+    etl_in_dt          TIMESTAMP        NOT NULL,
+    days_to_expiration INTEGER,
+    expiration_class   TEXT,
+    PRIMARY KEY (symbol, call_put, expiration, strike_price)
+);
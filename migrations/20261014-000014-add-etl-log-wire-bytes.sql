@@ -0,0 +1,5 @@
+-- 记录压缩传输的字节数，http_bytes 是解压后的大小
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS wire_bytes BIGINT;
+
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS http_bytes BIGINT;
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS wire_bytes BIGINT;
@@ -0,0 +1,2 @@
+-- 加载后维护步骤的耗时，例如 [{"step": "analyze", "secs": 1.2}]
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS maintenance JSONB;
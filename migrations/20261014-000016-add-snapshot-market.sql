@@ -0,0 +1,22 @@
+-- 数据来源市场（cone / opt / ctwo / exo）和可选的请求地址；旧数据和延迟报价的 market 为空字符串
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT '';
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS source_url TEXT;
+
+-- 市场加入主键，同一合约在多个市场挂牌时各自保留一行
+ALTER TABLE t_options_cboe_snapshot DROP CONSTRAINT IF EXISTS t_options_cboe_snapshot_pk;
+ALTER TABLE t_options_cboe_snapshot ADD CONSTRAINT t_options_cboe_snapshot_pk
+    PRIMARY KEY (symbol, call_put, strike_price, expiration, last_updated_time, market);
+
+-- 归档表同样加列并把市场加入主键，prune --mode archive 按 SELECT * 搬运
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT '';
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS source_url TEXT;
+
+ALTER TABLE t_options_cboe_snapshot_archive DROP CONSTRAINT IF EXISTS t_options_cboe_snapshot_archive_pkey;
+ALTER TABLE t_options_cboe_snapshot_archive ADD CONSTRAINT t_options_cboe_snapshot_archive_pkey
+    PRIMARY KEY (symbol, call_put, strike_price, expiration, last_updated_time, market);
+
+-- 最新快照表同样按市场区分，各市场的同一合约各保留一行
+ALTER TABLE t_options_cboe_latest ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT '';
+ALTER TABLE t_options_cboe_latest DROP CONSTRAINT IF EXISTS t_options_cboe_latest_pkey;
+ALTER TABLE t_options_cboe_latest ADD CONSTRAINT t_options_cboe_latest_pkey
+    PRIMARY KEY (symbol, call_put, expiration, strike_price, market);
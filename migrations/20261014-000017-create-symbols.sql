@@ -0,0 +1,30 @@
+-- 标的目录：每个标的第一次 / 最近一次出现的快照、出现过的市场和最近一次的合约数，每次加载后更新
+CREATE TABLE IF NOT EXISTS t_cboe_symbols
+(
+    symbol           TEXT      NOT NULL PRIMARY KEY,
+    first_seen       TIMESTAMP NOT NULL,
+    last_seen        TIMESTAMP NOT NULL,
+    markets          TEXT[]    NOT NULL DEFAULT '{}',
+    active_contracts BIGINT    NOT NULL,
+    updated_at       TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_cboe_symbols_first_seen ON t_cboe_symbols (first_seen);
+CREATE INDEX IF NOT EXISTS idx_cboe_symbols_last_seen ON t_cboe_symbols (last_seen);
+
+-- 用已有的快照回填一次，之后只按每次加载的快照增量更新
+INSERT INTO t_cboe_symbols (symbol, first_seen, last_seen, markets, active_contracts)
+SELECT s.symbol, s.first_seen, s.last_seen, s.markets,
+       (SELECT count(DISTINCT (t.call_put, t.expiration, t.strike_price))
+        FROM t_options_cboe_snapshot t
+        WHERE t.symbol = s.symbol AND t.last_updated_time = s.last_seen AND t.source = 'symbol_data')
+FROM (
+    SELECT symbol,
+           min(last_updated_time) AS first_seen,
+           max(last_updated_time) AS last_seen,
+           array_remove(array_agg(DISTINCT market ORDER BY market), '') AS markets
+    FROM t_options_cboe_snapshot
+    WHERE source = 'symbol_data'
+    GROUP BY symbol
+) s
+ON CONFLICT (symbol) DO NOTHING;
@@ -0,0 +1,56 @@
+-- 变化检测（storage.change_detection）：每个合约最近一次的内容哈希、最近一次变化和最近一次出现的快照时间
+-- 快照表里只写入有变化的合约，没变的合约沿用 changed_at 那一行
+CREATE TABLE IF NOT EXISTS t_cboe_contract_state
+(
+    market       TEXT             NOT NULL,
+    symbol       TEXT             NOT NULL,
+    call_put     TEXT             NOT NULL,
+    expiration   TEXT             NOT NULL,
+    strike_price DOUBLE PRECISION NOT NULL,
+    content_hash BIGINT           NOT NULL,
+    changed_at   TIMESTAMP        NOT NULL,
+    seen_at      TIMESTAMP        NOT NULL,
+    PRIMARY KEY (market, symbol, call_put, expiration, strike_price)
+);
+
+CREATE INDEX IF NOT EXISTS idx_cboe_contract_state_seen_at ON t_cboe_contract_state (seen_at);
+
+-- 最新快照表标记哪些合约是从之前的快照沿用过来的
+ALTER TABLE t_options_cboe_latest ADD COLUMN IF NOT EXISTS carried_forward BOOLEAN NOT NULL DEFAULT false;
+
+-- 还原某个时间点的完整快照：取 lookback 窗口内每个合约最近的一行，且该合约在 ts 之后仍然出现过
+CREATE OR REPLACE FUNCTION cboe_snapshot_at(ts TIMESTAMP, lookback INTERVAL DEFAULT '7 days')
+    RETURNS TABLE
+            (
+                market          TEXT,
+                symbol          TEXT,
+                call_put        TEXT,
+                expiration      TEXT,
+                strike_price    DOUBLE PRECISION,
+                volume          BIGINT,
+                matched         BIGINT,
+                routed          BIGINT,
+                bid_size        BIGINT,
+                bid_price       DOUBLE PRECISION,
+                ask_size        BIGINT,
+                ask_price       DOUBLE PRECISION,
+                last_price      DOUBLE PRECISION,
+                changed_at      TIMESTAMP,
+                carried_forward BOOLEAN
+            )
+    LANGUAGE sql
+    STABLE
+AS
+$$
+SELECT DISTINCT ON (t.market, t.symbol, t.call_put, t.expiration, t.strike_price)
+       t.market, t.symbol, t.call_put, t.expiration, t.strike_price,
+       t.volume, t.matched, t.routed, t.bid_size, t.bid_price, t.ask_size, t.ask_price, t.last_price,
+       t.last_updated_time, t.last_updated_time <> ts
+FROM t_options_cboe_snapshot t
+JOIN t_cboe_contract_state s
+  ON s.market = t.market AND s.symbol = t.symbol AND s.call_put = t.call_put
+ AND s.expiration = t.expiration AND s.strike_price = t.strike_price
+WHERE t.last_updated_time <= ts AND t.last_updated_time > ts - lookback
+  AND t.source = 'symbol_data' AND s.seen_at >= ts
+ORDER BY t.market, t.symbol, t.call_put, t.expiration, t.strike_price, t.last_updated_time DESC
+$$;
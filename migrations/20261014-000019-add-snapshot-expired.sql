@@ -0,0 +1,28 @@
+-- 到期日已过的合约：加载后按纽约日期标记（expiry.flag_after_load），purge-expired 按到期日清理
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS is_expired BOOLEAN NOT NULL DEFAULT false;
+
+-- CSV 里的到期日有 YYYY-MM-DD / MM/DD/YYYY / YYYYMMDD 三种写法，与 cboe::parse_expiration 一致，认不出或日期无效时返回 NULL
+CREATE OR REPLACE FUNCTION cboe_expiration_date(expiration TEXT) RETURNS DATE
+    LANGUAGE plpgsql
+    IMMUTABLE
+AS
+$$
+DECLARE
+    value TEXT := btrim(expiration);
+BEGIN
+    RETURN CASE
+               WHEN value ~ '^\d{4}-\d{2}-\d{2}$' THEN to_date(value, 'YYYY-MM-DD')
+               WHEN value ~ '^\d{1,2}/\d{1,2}/\d{4}$' THEN to_date(value, 'MM/DD/YYYY')
+               WHEN value ~ '^\d{8}$' THEN to_date(value, 'YYYYMMDD')
+               END;
+EXCEPTION
+    WHEN others THEN
+        RETURN NULL;
+END
+$$;
+
+-- 只索引还没标记的行，每次加载后的标记只扫这一小部分
+CREATE INDEX IF NOT EXISTS idx_options_unexpired ON t_options_cboe_snapshot (cboe_expiration_date(expiration)) WHERE NOT is_expired;
+
+-- 归档表保持与快照表相同的列顺序
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS is_expired BOOLEAN NOT NULL DEFAULT false;
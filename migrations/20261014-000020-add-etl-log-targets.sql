@@ -0,0 +1,2 @@
+-- [mirror] 镜像库的写入结果：每个镜像每个市场一项 {target, market, status, rows_inserted, error}
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS targets JSONB;
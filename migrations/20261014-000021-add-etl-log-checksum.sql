@@ -0,0 +1,3 @@
+-- 每个市场写入行的 SHA-256 和去重后的行数，verify 子命令从快照表重新计算比较
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS checksum TEXT;
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS checksum_rows BIGINT;
@@ -0,0 +1,9 @@
+-- 与同一交易日上一次快照相比的成交量增量（storage.volume_deltas），当天第一次出现的合约等于累计值
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS volume_delta BIGINT;
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS matched_delta BIGINT;
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS routed_delta BIGINT;
+
+-- 归档表保持与快照表相同的列顺序
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS volume_delta BIGINT;
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS matched_delta BIGINT;
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS routed_delta BIGINT;
@@ -0,0 +1,15 @@
+-- 波动率曲面（[surface]）：每个标的每次快照每个到期日一条，log_moneyness = ln(K/F) 上的自然三次样条节点
+CREATE TABLE IF NOT EXISTS t_cboe_iv_surface
+(
+    symbol          TEXT               NOT NULL,
+    snapshot_time   TIMESTAMP          NOT NULL,
+    expiration      TEXT               NOT NULL,
+    expiration_date DATE               NOT NULL,
+    years           DOUBLE PRECISION   NOT NULL,
+    forward         DOUBLE PRECISION   NOT NULL,
+    rate            DOUBLE PRECISION   NOT NULL,
+    log_moneyness   DOUBLE PRECISION[] NOT NULL,
+    iv              DOUBLE PRECISION[] NOT NULL,
+    fitted_at       TIMESTAMP          NOT NULL DEFAULT now(),
+    PRIMARY KEY (symbol, snapshot_time, expiration)
+);
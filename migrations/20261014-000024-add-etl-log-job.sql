@@ -0,0 +1,4 @@
+-- [jobs] 的 job 名称，status --job 和每个 job "是否已加载" 的判断按它过滤；没有定义 job 时为空
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS job TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_etl_log_job ON t_cboe_snapshot_etl_log (job, snapshot_time) WHERE job IS NOT NULL;
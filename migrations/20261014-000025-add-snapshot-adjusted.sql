@@ -0,0 +1,12 @@
+-- 调整合约（代码带数字后缀、行权价不是 0.25 的整数倍）：加载时按 classify::is_adjusted 标记，[analytics] exclude_adjusted 时汇总统计跳过这些行
+ALTER TABLE t_options_cboe_snapshot ADD COLUMN IF NOT EXISTS is_adjusted BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE t_options_cboe_snapshot_archive ADD COLUMN IF NOT EXISTS is_adjusted BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE t_options_cboe_latest ADD COLUMN IF NOT EXISTS is_adjusted BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS idx_options_adjusted ON t_options_cboe_snapshot (last_updated_time) WHERE is_adjusted;
+
+-- 标记已有的数据，规则与 classify::is_adjusted 一致
+UPDATE t_options_cboe_snapshot
+SET is_adjusted = true
+WHERE NOT is_adjusted
+  AND (btrim(symbol) ~ '^[A-Za-z].*[0-9]$' OR abs(strike_price * 4 - round(strike_price * 4)) > 1e-6);
@@ -0,0 +1,25 @@
+-- 到期日日历（storage.expiration_calendar）：每个标的出现过的到期日和周期（weekly / monthly / quarterly，与快照日期无关）
+CREATE TABLE IF NOT EXISTS t_option_expirations
+(
+    symbol           TEXT      NOT NULL,
+    expiration_date  DATE      NOT NULL,
+    expiration_class TEXT      NOT NULL,
+    first_seen       TIMESTAMP NOT NULL,
+    last_seen        TIMESTAMP NOT NULL,
+    PRIMARY KEY (symbol, expiration_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_option_expirations_date ON t_option_expirations (expiration_date);
+
+-- 每次快照时每个到期日的剩余天数（按纽约日期）和合约数，时间衰减研究直接按 days_to_expiration 关联
+CREATE TABLE IF NOT EXISTS t_option_expiration_days
+(
+    snapshot_time      TIMESTAMP NOT NULL,
+    symbol             TEXT      NOT NULL,
+    expiration_date    DATE      NOT NULL,
+    days_to_expiration INTEGER   NOT NULL,
+    contracts          INTEGER   NOT NULL,
+    PRIMARY KEY (snapshot_time, symbol, expiration_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_option_expiration_days_symbol ON t_option_expiration_days (symbol, expiration_date);
@@ -0,0 +1,2 @@
+-- 本次运行的 [memory] 限制和进程峰值 RSS，例如 {"max_rss_mb": 512, "in_flight_batches": 1, "batch_size": 50000, "peak_rss_mb": 430}
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS memory_limits JSONB;
@@ -0,0 +1,5 @@
+-- run --resume：每个市场连续提交到的位置（按本次要写入的记录顺序，之前的批次都已提交）和要写入的总行数，
+-- 从中断的运行继续时以此为起点；resumed_from 是被继续的运行
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS committed_offset BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE t_cboe_snapshot_etl_log_market ADD COLUMN IF NOT EXISTS rows_total BIGINT;
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS resumed_from BIGINT;
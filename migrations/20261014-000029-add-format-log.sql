@@ -0,0 +1,12 @@
+-- 格式漂移检测：每次运行观察到的页面签名（source = 'page'）和每个市场的 CSV 表头（source = 市场），和上一次运行比较；
+-- etl_log.format_drift 是本次运行发现的变化
+CREATE TABLE IF NOT EXISTS t_cboe_format_log
+(
+    run_id      BIGINT    NOT NULL REFERENCES t_cboe_snapshot_etl_log (id),
+    source      TEXT      NOT NULL,
+    observed    TEXT[]    NOT NULL,
+    observed_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (run_id, source)
+);
+CREATE INDEX IF NOT EXISTS idx_cboe_format_log_source ON t_cboe_format_log (source, run_id DESC);
+ALTER TABLE t_cboe_snapshot_etl_log ADD COLUMN IF NOT EXISTS format_drift JSONB;
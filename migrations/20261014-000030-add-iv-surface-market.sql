@@ -0,0 +1,2 @@
+-- 拟合波动率曲面所用的市场：每个标的只用该次快照成交量最大的市场；旧数据为空字符串
+ALTER TABLE t_cboe_iv_surface ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use log::info;
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cboe::{ContractKey, OptionRecord};
+use crate::notify::Notification;
+use crate::storage::SnapshotTable;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    // 和最近 N 个快照的平均值比较
+    pub lookback_snapshots: i64,
+    pub volume_factor: f64,
+    pub min_volume: i64,
+    // 价差收窄到历史平均价差的这个比例以下时报警
+    pub spread_collapse_ratio: Option<f64>,
+    // 数据里没有标的价格，用卖价不超过 deep_otm_max_price 近似深度虚值
+    pub deep_otm_max_price: Option<f64>,
+    pub deep_otm_min_volume: i64,
+    pub notify: bool,
+    pub notify_limit: usize,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        AlertConfig {
+            enabled: false,
+            lookback_snapshots: 5,
+            volume_factor: 5.0,
+            min_volume: 500,
+            spread_collapse_ratio: None,
+            deep_otm_max_price: None,
+            deep_otm_min_volume: 1000,
+            notify: false,
+            notify_limit: 20,
+        }
+    }
+}
+
+pub struct Alert {
+    pub rule: &'static str,
+    pub market: String,
+    pub key: ContractKey,
+    pub strike_price: f64,
+    pub detail: String,
+}
+
+struct Trailing {
+    avg_volume: f64,
+    avg_spread: Option<f64>,
+}
+
+pub struct AlertDetector<'a> {
+    config: &'a AlertConfig,
+    trailing: HashMap<ContractKey, Trailing>,
+}
+
+impl<'a> AlertDetector<'a> {
+    pub async fn new(pool: &PgPool, table: &SnapshotTable, config: &'a AlertConfig, snapshot_time: NaiveDateTime) -> Result<AlertDetector<'a>> {
+        let trailing = load_trailing(pool, table, snapshot_time, config.lookback_snapshots).await?;
+        Ok(AlertDetector { config, trailing })
+    }
+
+    pub fn detect(&self, market: &str, records: &[OptionRecord]) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for rec in records {
+            let key = rec.key();
+            // 没有历史的新合约不报警
+            let Some(trailing) = self.trailing.get(&key) else {
+                continue;
+            };
+            let mut flag = |rule: &'static str, detail: String| {
+                alerts.push(Alert { rule, market: market.to_string(), key: key.clone(), strike_price: rec.strike_price, detail });
+            };
+            let spike = rec.volume as f64 > trailing.avg_volume * self.config.volume_factor;
+            if spike && rec.volume >= self.config.min_volume {
+                flag("volume_spike", format!("volume {} vs trailing avg {:.0}", rec.volume, trailing.avg_volume));
+            }
+            if let Some(ratio) = self.config.spread_collapse_ratio
+                && let Some(avg_spread) = trailing.avg_spread
+                && rec.bid_price > 0.0
+                && rec.ask_price > 0.0
+                && avg_spread > 0.0
+                && rec.ask_price - rec.bid_price < avg_spread * ratio
+            {
+                flag("spread_collapse", format!("spread {:.2} vs trailing avg {:.2}", rec.ask_price - rec.bid_price, avg_spread));
+            }
+            if let Some(max_price) = self.config.deep_otm_max_price
+                && spike
+                && rec.ask_price > 0.0
+                && rec.ask_price <= max_price
+                && rec.volume >= self.config.deep_otm_min_volume
+            {
+                flag("deep_otm_volume", format!("volume {} at ask {} vs trailing avg {:.0}", rec.volume, rec.ask_price, trailing.avg_volume));
+            }
+        }
+        if !alerts.is_empty() {
+            info!("Found {} unusual-activity alerts in {}.", alerts.len(), market);
+        }
+        alerts
+    }
+}
+
+// 开启变化检测时快照表里只有变化的行，每个快照用 cboe_snapshot_at 还原后再平均，否则没变化的合约会被漏掉
+async fn load_trailing(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime, lookback: i64) -> Result<HashMap<ContractKey, Trailing>> {
+    let times = format!(r#"
+        SELECT DISTINCT last_updated_time FROM {table}
+        WHERE last_updated_time < $1 AND source = 'symbol_data'
+        ORDER BY last_updated_time DESC
+        LIMIT $2
+    "#);
+    let source = if table.change_detection {
+        format!("SELECT a.* FROM ({times}) AS p CROSS JOIN LATERAL cboe_snapshot_at(p.last_updated_time) a")
+    } else {
+        format!("SELECT * FROM {table} WHERE last_updated_time IN ({times})")
+    };
+    let rows = sqlx::query(&format!(r#"
+        SELECT symbol, call_put, expiration, strike_price,
+               avg(volume)::float8 AS avg_volume,
+               avg(ask_price - bid_price) FILTER (WHERE bid_price > 0 AND ask_price > 0)::float8 AS avg_spread
+        FROM ({source}) AS snapshots
+        GROUP BY symbol, call_put, expiration, strike_price
+    "#))
+        .bind(snapshot_time)
+        .bind(lookback)
+        .fetch_all(pool)
+        .await?;
+    let mut trailing = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let key = ContractKey::new(
+            row.try_get("symbol")?,
+            row.try_get("call_put")?,
+            row.try_get("expiration")?,
+            row.try_get("strike_price")?,
+        );
+        trailing.insert(key, Trailing { avg_volume: row.try_get("avg_volume")?, avg_spread: row.try_get("avg_spread")? });
+    }
+    Ok(trailing)
+}
+
+pub async fn store_alerts(pool: &PgPool, run_id: i64, snapshot_time: NaiveDateTime, alerts: &[Alert]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for alert in alerts {
+        sqlx::query(r#"
+            INSERT INTO t_options_cboe_alerts
+            (run_id, market, snapshot_time, rule, symbol, call_put, expiration, strike_price, detail)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#)
+            .bind(run_id)
+            .bind(&alert.market)
+            .bind(snapshot_time)
+            .bind(alert.rule)
+            .bind(&alert.key.symbol)
+            .bind(&alert.key.call_put)
+            .bind(&alert.key.expiration)
+            .bind(alert.strike_price)
+            .bind(&alert.detail)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub fn notification(snapshot_time: NaiveDateTime, alerts: &[Alert], limit: usize) -> Notification {
+    let title = format!("CBOE unusual activity: {} alerts at {}", alerts.len(), snapshot_time.format("%Y-%m-%d %H:%M:%S"));
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for alert in alerts {
+        *counts.entry(alert.rule).or_default() += 1;
+    }
+    let mut text = title.clone();
+    for (rule, n) in &counts {
+        text.push_str(&format!("\n  {}: {}", rule, n));
+    }
+    for alert in alerts.iter().take(limit) {
+        text.push_str(&format!(
+            "\n{} {} {} {} {}: {}",
+            alert.rule, alert.key.symbol, alert.key.expiration, alert.strike_price, alert.key.call_put, alert.detail
+        ));
+    }
+    if alerts.len() > limit {
+        text.push_str(&format!("\n... and {} more", alerts.len() - limit));
+    }
+    let json = serde_json::json!({
+        "snapshot_time": snapshot_time,
+        "alerts": alerts.len(),
+        "rules": counts,
+        "top": alerts.iter().take(limit).map(|a| serde_json::json!({
+            "rule": a.rule,
+            "market": a.market,
+            "symbol": a.key.symbol,
+            "call_put": a.key.call_put,
+            "expiration": a.key.expiration,
+            "strike_price": a.strike_price,
+            "detail": a.detail,
+        })).collect::<Vec<_>>(),
+    });
+    Notification { title, text, json, html: None }
+}
@@ -0,0 +1,98 @@
+use anyhow::{Result, bail};
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BaselineAction {
+    Warn,
+    #[default]
+    Abort,
+}
+
+// 行数和最近几次成功加载的中位数相差超过 max_deviation_pct 时告警或中止，防止截断的下载覆盖正常数据
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BaselineConfig {
+    pub enabled: bool,
+    pub window: i64,
+    pub min_runs: usize,
+    pub max_deviation_pct: f64,
+    pub action: BaselineAction,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        BaselineConfig {
+            enabled: false,
+            window: 10,
+            min_runs: 3,
+            max_deviation_pct: 50.0,
+            action: BaselineAction::Abort,
+        }
+    }
+}
+
+pub struct BaselineGuard<'a> {
+    pool: &'a PgPool,
+    config: &'a BaselineConfig,
+    job: Option<&'a str>,
+}
+
+impl<'a> BaselineGuard<'a> {
+    // 基线来自 Postgres 运行日志，其它后端没有历史可比，直接关闭
+    pub fn new(pool: Option<&'a PgPool>, config: &'a BaselineConfig, job: Option<&'a str>) -> Option<BaselineGuard<'a>> {
+        if !config.enabled {
+            return None;
+        }
+        match pool {
+            Some(pool) => Some(BaselineGuard { pool, config, job }),
+            None => {
+                warn!("Row count baseline needs the Postgres run log, skipping the check.");
+                None
+            }
+        }
+    }
+
+    // 只和同一个 job 的历史比较，各 job 的过滤条件不同；--resume 接着写完的市场只提交了剩下的部分，不算在内
+    pub async fn check(&self, market: &str, rows: u64) -> Result<()> {
+        let mut history: Vec<i64> = sqlx::query_scalar(r#"
+            SELECT m.rows_committed FROM t_cboe_snapshot_etl_log_market m
+            JOIN t_cboe_snapshot_etl_log l ON l.id = m.run_id
+            WHERE m.market = $1 AND m.status = 'done' AND l.job IS NOT DISTINCT FROM $3
+              AND (l.resumed_from IS NULL OR m.rows_committed = m.rows_total)
+            ORDER BY m.snapshot_time DESC
+            LIMIT $2
+        "#)
+            .bind(market)
+            .bind(self.config.window)
+            .bind(self.job)
+            .fetch_all(self.pool)
+            .await?;
+        if history.len() < self.config.min_runs.max(1) {
+            info!("{} has {} previous loads, not enough for a row count baseline.", market, history.len());
+            return Ok(());
+        }
+        history.sort_unstable();
+        let baseline = history[history.len() / 2];
+        if baseline == 0 {
+            return Ok(());
+        }
+        let deviation = (rows as f64 - baseline as f64).abs() / baseline as f64 * 100.0;
+        if deviation <= self.config.max_deviation_pct {
+            return Ok(());
+        }
+        let message = format!(
+            "{} has {} rows, {:.0}% off the baseline of {} from the last {} loads (max {}%)",
+            market, rows, deviation, baseline, history.len(), self.config.max_deviation_pct
+        );
+        match self.config.action {
+            BaselineAction::Warn => {
+                warn!("{}", message);
+                Ok(())
+            }
+            BaselineAction::Abort => bail!(message),
+        }
+    }
+}
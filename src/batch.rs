@@ -0,0 +1,106 @@
+use anyhow::{Result, anyhow};
+use arrow::array::{Array, ArrayRef, Float64Array, Int64Array, StringArray, TimestampSecondArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use std::sync::{Arc, LazyLock};
+
+use crate::{MarketSnapshot, OptionRecord};
+
+// 列名和数据库快照表一致，last_updated_time 是不带时区的纽约时间
+static SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        Field::new("market", DataType::Utf8, false),
+        Field::new("last_updated_time", DataType::Timestamp(TimeUnit::Second, None), false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("call_put", DataType::Utf8, false),
+        Field::new("expiration", DataType::Utf8, false),
+        Field::new("strike_price", DataType::Float64, false),
+        Field::new("volume", DataType::Int64, false),
+        Field::new("matched", DataType::Int64, false),
+        Field::new("routed", DataType::Int64, false),
+        Field::new("bid_size", DataType::Int64, false),
+        Field::new("bid_price", DataType::Float64, false),
+        Field::new("ask_size", DataType::Int64, false),
+        Field::new("ask_price", DataType::Float64, false),
+        Field::new("last_price", DataType::Float64, false),
+    ]))
+});
+
+pub fn schema() -> SchemaRef {
+    SCHEMA.clone()
+}
+
+impl MarketSnapshot {
+    pub fn to_arrow(&self) -> Result<RecordBatch> {
+        let records = &self.records;
+        let strings = |f: fn(&OptionRecord) -> &str| -> ArrayRef { Arc::new(StringArray::from_iter_values(records.iter().map(f))) };
+        let ints = |f: fn(&OptionRecord) -> i64| -> ArrayRef { Arc::new(Int64Array::from_iter_values(records.iter().map(f))) };
+        let floats = |f: fn(&OptionRecord) -> f64| -> ArrayRef { Arc::new(Float64Array::from_iter_values(records.iter().map(f))) };
+        let snapshot_time = self.snapshot_time.and_utc().timestamp();
+
+        let columns = vec![
+            Arc::new(StringArray::from_iter_values(records.iter().map(|_| self.market.as_str()))) as ArrayRef,
+            Arc::new(TimestampSecondArray::from_iter_values(records.iter().map(|_| snapshot_time))),
+            strings(|r| &r.symbol),
+            strings(|r| &r.call_put),
+            strings(|r| &r.expiration),
+            floats(|r| r.strike_price),
+            ints(|r| r.volume),
+            ints(|r| r.matched),
+            ints(|r| r.routed),
+            ints(|r| r.bid_size),
+            floats(|r| r.bid_price),
+            ints(|r| r.ask_size),
+            floats(|r| r.ask_price),
+            floats(|r| r.last_price),
+        ];
+        Ok(RecordBatch::try_new(schema(), columns)?)
+    }
+}
+
+fn column<'a, T: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a T> {
+    batch.column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<T>())
+        .ok_or_else(|| anyhow!("Arrow batch has no {} column of the expected type", name))
+}
+
+// to_arrow 的逆操作，market 和 last_updated_time 列不还原
+pub fn records_from_arrow(batch: &RecordBatch) -> Result<Vec<OptionRecord>> {
+    let (symbol, call_put, expiration) = (
+        column::<StringArray>(batch, "symbol")?,
+        column::<StringArray>(batch, "call_put")?,
+        column::<StringArray>(batch, "expiration")?,
+    );
+    let ints = |name| column::<Int64Array>(batch, name);
+    let floats = |name| column::<Float64Array>(batch, name);
+    let (volume, matched, routed, bid_size, ask_size) = (ints("volume")?, ints("matched")?, ints("routed")?, ints("bid_size")?, ints("ask_size")?);
+    let (strike_price, bid_price, ask_price, last_price) = (floats("strike_price")?, floats("bid_price")?, floats("ask_price")?, floats("last_price")?);
+    Ok((0..batch.num_rows())
+        .map(|i| OptionRecord {
+            symbol: symbol.value(i).to_string(),
+            call_put: call_put.value(i).to_string(),
+            expiration: expiration.value(i).to_string(),
+            strike_price: strike_price.value(i),
+            volume: volume.value(i),
+            matched: matched.value(i),
+            routed: routed.value(i),
+            bid_size: bid_size.value(i),
+            bid_price: bid_price.value(i),
+            ask_size: ask_size.value(i),
+            ask_price: ask_price.value(i),
+            last_price: last_price.value(i),
+        })
+        .collect())
+}
+
+// 每个市场一个 RecordBatch，schema 相同，可以直接拼接
+pub fn to_arrow(snapshots: &[MarketSnapshot]) -> Result<Vec<RecordBatch>> {
+    snapshots.iter().map(|s| s.to_arrow()).collect()
+}
+
+// 注册到 DataFusion SessionContext 里即可用 SQL 查询，例如 ctx.register_table("options", table_provider(&snapshots)?)
+#[cfg(feature = "datafusion")]
+pub fn table_provider(snapshots: &[MarketSnapshot]) -> Result<Arc<datafusion::datasource::MemTable>> {
+    let table = datafusion::datasource::MemTable::try_new(schema(), vec![to_arrow(snapshots)?])?;
+    Ok(Arc::new(table))
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::Schema;
+use arrow::ipc::CompressionType;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::{FileWriter, IpcWriteOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::batch::{records_from_arrow, schema};
+use crate::{MarketSnapshot, OptionRecord};
+
+// 本地 CSV 解析结果的二进制缓存：同目录下的 <文件名>.arrow（Arrow IPC 文件，LZ4 压缩，列同 batch::schema），
+// pandas / polars 也能直接读取。缓存里是过滤之前的全部记录，CSV 被修改或解析设置不同时作废
+const VERSION: &str = "1";
+
+pub struct CachedSnapshot {
+    pub headers: Vec<String>,
+    pub records: Vec<OptionRecord>,
+    pub parse_errors: u64,
+    // 原 CSV 的字节数
+    pub source_bytes: u64,
+}
+
+pub fn cache_path(csv: &Path) -> PathBuf {
+    let mut name = csv.as_os_str().to_os_string();
+    name.push(".arrow");
+    PathBuf::from(name)
+}
+
+// CSV 的大小和修改时间，记录在缓存里用于判断缓存是否过期
+fn source_stamp(csv: &Path) -> Result<(u64, u128)> {
+    let meta = std::fs::metadata(csv).with_context(|| format!("Failed to read {}", csv.display()))?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    Ok((meta.len(), modified))
+}
+
+// fingerprint 描述解析设置（列映射、分隔符等），由调用方给出；没有缓存或缓存已过期时为 None
+pub fn read(csv: &Path, fingerprint: &str) -> Result<Option<CachedSnapshot>> {
+    let path = cache_path(csv);
+    let Ok(file) = File::open(&path) else {
+        return Ok(None);
+    };
+    let (bytes, modified) = source_stamp(csv)?;
+    let reader = FileReader::try_new(file, None).with_context(|| format!("Failed to open cache {}", path.display()))?;
+    let meta = reader.schema().metadata().clone();
+    let get = |key: &str| meta.get(key).map(|v| v.as_str());
+    if get("cboe.version") != Some(VERSION)
+        || get("cboe.fingerprint") != Some(fingerprint)
+        || get("cboe.source_bytes") != Some(bytes.to_string().as_str())
+        || get("cboe.source_modified") != Some(modified.to_string().as_str())
+    {
+        return Ok(None);
+    }
+    let mut records = Vec::new();
+    for batch in reader {
+        records.extend(records_from_arrow(&batch?)?);
+    }
+    Ok(Some(CachedSnapshot {
+        headers: serde_json::from_str(get("cboe.headers").unwrap_or("[]"))?,
+        records,
+        parse_errors: get("cboe.parse_errors").and_then(|v| v.parse().ok()).unwrap_or_default(),
+        source_bytes: bytes,
+    }))
+}
+
+// 先写临时文件再改名，中断时不会留下半个缓存
+pub fn write(csv: &Path, fingerprint: &str, headers: &[String], snapshot: &MarketSnapshot) -> Result<PathBuf> {
+    let (bytes, modified) = source_stamp(csv)?;
+    let metadata = HashMap::from([
+        ("cboe.version".to_string(), VERSION.to_string()),
+        ("cboe.fingerprint".to_string(), fingerprint.to_string()),
+        ("cboe.source_bytes".to_string(), bytes.to_string()),
+        ("cboe.source_modified".to_string(), modified.to_string()),
+        ("cboe.headers".to_string(), serde_json::to_string(headers)?),
+        ("cboe.parse_errors".to_string(), snapshot.parse_errors.to_string()),
+    ]);
+    let schema = Schema::new_with_metadata(schema().fields().clone(), metadata);
+    let batch = snapshot.to_arrow()?.with_schema(schema.clone().into())?;
+
+    let path = cache_path(csv);
+    let tmp = path.with_extension("arrow.tmp");
+    let options = IpcWriteOptions::default().try_with_compression(Some(CompressionType::LZ4_FRAME))?;
+    let mut writer = FileWriter::try_new_with_options(File::create(&tmp)?, &schema, options)?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("Failed to write cache {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::Deserialize;
+
+use crate::clock::{Clock, SystemClock};
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    pub open: String,
+    pub close: String,
+    pub early_close: String,
+    // 收盘后 CBOE 还会再更新一次快照，收盘后这段时间内仍视为开市
+    pub grace_minutes: i64,
+    pub extra_holidays: Vec<NaiveDate>,
+    pub extra_early_closes: Vec<NaiveDate>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        CalendarConfig {
+            open: "09:30".to_string(),
+            close: "16:15".to_string(),
+            early_close: "13:15".to_string(),
+            grace_minutes: 30,
+            extra_holidays: Vec::new(),
+            extra_early_closes: Vec::new(),
+        }
+    }
+}
+
+pub struct TradingCalendar {
+    open: NaiveTime,
+    close: NaiveTime,
+    early_close: NaiveTime,
+    grace: Duration,
+    extra_holidays: Vec<NaiveDate>,
+    extra_early_closes: Vec<NaiveDate>,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").with_context(|| format!("Invalid time {}, expected HH:MM", value))
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n).expect("valid nth weekday")
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, 5)
+        .unwrap_or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+// 周六的节假日提前到周五，周日的顺延到周一
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid easter date")
+}
+
+// NYSE/CBOE 固定规则的休市日
+pub fn exchange_holidays(year: i32) -> Vec<NaiveDate> {
+    let ymd = |m, d| NaiveDate::from_ymd_opt(year, m, d).expect("valid date");
+    let mut holidays = Vec::new();
+    // 元旦落在周六时不在上一年的 12 月 31 日补休
+    let new_year = ymd(1, 1);
+    if new_year.weekday() != Weekday::Sat {
+        holidays.push(observed(new_year));
+    }
+    holidays.push(nth_weekday(year, 1, Weekday::Mon, 3));
+    holidays.push(nth_weekday(year, 2, Weekday::Mon, 3));
+    holidays.push(easter(year) - Duration::days(2));
+    holidays.push(last_weekday(year, 5, Weekday::Mon));
+    if year >= 2022 {
+        holidays.push(observed(ymd(6, 19)));
+    }
+    holidays.push(observed(ymd(7, 4)));
+    holidays.push(nth_weekday(year, 9, Weekday::Mon, 1));
+    holidays.push(nth_weekday(year, 11, Weekday::Thu, 4));
+    holidays.push(observed(ymd(12, 25)));
+    holidays
+}
+
+pub fn exchange_early_closes(year: i32) -> Vec<NaiveDate> {
+    let ymd = |m, d| NaiveDate::from_ymd_opt(year, m, d).expect("valid date");
+    let mut days = Vec::new();
+    let july_3 = ymd(7, 3);
+    if matches!(july_3.weekday(), Weekday::Mon | Weekday::Tue | Weekday::Wed | Weekday::Thu) {
+        days.push(july_3);
+    }
+    days.push(nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1));
+    let christmas_eve = ymd(12, 24);
+    if !matches!(christmas_eve.weekday(), Weekday::Sat | Weekday::Sun) {
+        days.push(christmas_eve);
+    }
+    days
+}
+
+pub fn now_new_york() -> NaiveDateTime {
+    SystemClock.now_new_york()
+}
+
+impl TradingCalendar {
+    pub fn new(config: &CalendarConfig) -> Result<TradingCalendar> {
+        Ok(TradingCalendar {
+            open: parse_time(&config.open)?,
+            close: parse_time(&config.close)?,
+            early_close: parse_time(&config.early_close)?,
+            grace: Duration::minutes(config.grace_minutes),
+            extra_holidays: config.extra_holidays.clone(),
+            extra_early_closes: config.extra_early_closes.clone(),
+        })
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+            && !exchange_holidays(date.year()).contains(&date)
+            && !self.extra_holidays.contains(&date)
+    }
+
+    // 返回当天的开盘、收盘时间（纽约时间），休市日返回 None
+    pub fn session(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let early = exchange_early_closes(date.year()).contains(&date) || self.extra_early_closes.contains(&date);
+        let close = if early { self.early_close } else { self.close };
+        Some((date.and_time(self.open), date.and_time(close)))
+    }
+
+    pub fn is_open(&self, now: NaiveDateTime) -> bool {
+        self.session(now.date())
+            .is_some_and(|(open, close)| now >= open && now <= close + self.grace)
+    }
+
+    pub fn next_open(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let mut date = now.date();
+        loop {
+            if let Some((open, close)) = self.session(date)
+                && now <= close + self.grace
+            {
+                return open.max(now);
+            }
+            date += Duration::days(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn calendar() -> TradingCalendar {
+        TradingCalendar::new(&CalendarConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn holidays_2025_and_2026() {
+        let holidays_2025 = exchange_holidays(2025);
+        for day in ["2025-01-01", "2025-01-20", "2025-02-17", "2025-04-18", "2025-05-26", "2025-06-19", "2025-07-04", "2025-09-01", "2025-11-27", "2025-12-25"] {
+            assert!(holidays_2025.contains(&date(day)), "{}", day);
+        }
+        assert_eq!(holidays_2025.len(), 10);
+        let holidays_2026 = exchange_holidays(2026);
+        for day in ["2026-01-01", "2026-01-19", "2026-02-16", "2026-04-03", "2026-05-25", "2026-06-19", "2026-09-07", "2026-11-26", "2026-12-25"] {
+            assert!(holidays_2026.contains(&date(day)), "{}", day);
+        }
+    }
+
+    #[test]
+    fn weekend_holidays_are_observed() {
+        // 2026-07-04 是周六，提前到周五；2022-06-19 是周日，顺延到周一
+        assert!(exchange_holidays(2026).contains(&date("2026-07-03")));
+        assert!(exchange_holidays(2022).contains(&date("2022-06-20")));
+        // 2022-01-01 是周六，2021-12-31 照常开市
+        assert!(calendar().is_trading_day(date("2021-12-31")));
+    }
+
+    #[test]
+    fn early_closes() {
+        assert_eq!(exchange_early_closes(2025), vec![date("2025-07-03"), date("2025-11-28"), date("2025-12-24")]);
+        // 2026-07-03 是补休日，不算提前收盘
+        assert_eq!(exchange_early_closes(2026), vec![date("2026-11-27"), date("2026-12-24")]);
+        let calendar = calendar();
+        assert_eq!(calendar.session(date("2025-11-28")), Some((at("2025-11-28 09:30"), at("2025-11-28 13:15"))));
+        assert!(calendar.is_open(at("2025-12-24 13:40")));
+        assert!(!calendar.is_open(at("2025-12-24 13:50")));
+        assert!(calendar.is_open(at("2025-12-23 16:40")));
+    }
+
+    #[test]
+    fn skips_holidays_to_the_next_open() {
+        let calendar = calendar();
+        assert_eq!(calendar.session(date("2026-04-03")), None);
+        assert!(!calendar.is_open(at("2026-04-03 10:00")));
+        assert_eq!(calendar.next_open(at("2026-04-03 10:00")), at("2026-04-06 09:30"));
+        // 收盘加宽限期之后，下一次开盘是下一个交易日
+        assert_eq!(calendar.next_open(at("2025-12-31 17:00")), at("2026-01-02 09:30"));
+        assert_eq!(calendar.next_open(at("2025-12-31 12:00")), at("2025-12-31 12:00"));
+    }
+
+    #[test]
+    fn extra_holidays_from_config() {
+        // 2025-01-09 卡特国葬临时休市，不在固定规则里
+        let config = CalendarConfig { extra_holidays: vec![date("2025-01-09")], ..Default::default() };
+        let calendar = TradingCalendar::new(&config).unwrap();
+        assert!(!calendar.is_trading_day(date("2025-01-09")));
+        assert!(calendar.is_trading_day(date("2025-01-10")));
+    }
+}
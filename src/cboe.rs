@@ -0,0 +1,290 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::ReaderBuilder;
+use log::{error, info, warn};
+use regex::Regex;
+use serde::Serialize;
+use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+
+use crate::classify::{Classification, classify, is_adjusted, normalize_strike};
+use crate::filter::SymbolFilter;
+use crate::http::HttpFetcher;
+use crate::source::{MarketData, SnapshotSource};
+
+pub const PAGE_URL: &str = "https://www.cboe.com/us/options/market_statistics/symbol_data/?mkt=cone";
+const CSV_URL: &str = "https://www.cboe.com/us/options/market_statistics/symbol_data/csv/";
+pub const MARKETS: [&str; 4] = ["cone", "opt", "ctwo", "exo"];
+pub const EXPECTED_HEADERS: [&str; 12] = [
+    "Symbol", "Call/Put", "Expiration", "Strike Price", "Volume", "Matched", "Routed",
+    "Bid Size", "Bid Price", "Ask Size", "Ask Price", "Last Price",
+];
+
+#[derive(Serialize, Clone)]
+pub struct OptionRecord {
+    pub symbol: String,
+    pub call_put: String,
+    pub expiration: String,
+    pub strike_price: f64,
+    pub volume: i64,
+    pub matched: i64,
+    pub routed: i64,
+    pub bid_size: i64,
+    pub bid_price: f64,
+    pub ask_size: i64,
+    pub ask_price: f64,
+    pub last_price: f64,
+}
+
+#[derive(Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
+pub struct ContractKey {
+    pub symbol: String,
+    pub call_put: String,
+    pub expiration: String,
+    pub strike_millis: i64,
+}
+
+impl ContractKey {
+    pub fn new(symbol: &str, call_put: &str, expiration: &str, strike_price: f64) -> ContractKey {
+        ContractKey {
+            symbol: symbol.to_string(),
+            call_put: call_put.to_string(),
+            expiration: expiration.to_string(),
+            strike_millis: (strike_price * 1000.0).round() as i64,
+        }
+    }
+}
+
+impl OptionRecord {
+    pub fn key(&self) -> ContractKey {
+        ContractKey::new(&self.symbol, &self.call_put, &self.expiration, self.strike_price)
+    }
+
+    pub fn is_inactive(&self) -> bool {
+        self.volume == 0 && self.bid_size == 0 && self.ask_size == 0 && self.last_price == 0.0
+    }
+
+    pub fn expiration_date(&self) -> Option<NaiveDate> {
+        parse_expiration(&self.expiration)
+    }
+
+    pub fn classify(&self, snapshot_date: NaiveDate) -> Option<Classification> {
+        self.expiration_date().map(|expiration| classify(expiration, snapshot_date))
+    }
+
+    pub fn is_adjusted(&self) -> bool {
+        is_adjusted(&self.symbol, self.strike_price)
+    }
+
+    // OCC 21 位合约代码：root 补足 6 位 + YYMMDD + C/P + 行权价 * 1000（8 位）
+    pub fn occ_symbol(&self) -> String {
+        let expiration = self.expiration_date()
+            .map(|d| d.format("%y%m%d").to_string())
+            .unwrap_or_else(|| self.expiration.clone());
+        let call_put = self.call_put.trim().chars().next().unwrap_or(' ').to_ascii_uppercase();
+        format!("{:<6}{}{}{:08}", self.symbol.trim(), expiration, call_put, (self.strike_price * 1000.0).round() as i64)
+    }
+}
+
+pub fn parse_expiration(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    ["%Y-%m-%d", "%m/%d/%Y", "%Y%m%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(value, fmt).ok())
+}
+
+pub struct CsvContent {
+    pub headers: Vec<String>,
+    pub records: Vec<OptionRecord>,
+    pub http_bytes: u64,
+    pub wire_bytes: u64,
+    pub parse_errors: u64,
+    pub filtered: u64,
+    // 下载地址，本地文件导入时为空
+    pub source_url: Option<String>,
+}
+
+// CSV 的表头就是来源的字段名，格式检查由调用方（CboeSource）补上
+impl From<CsvContent> for MarketData {
+    fn from(content: CsvContent) -> MarketData {
+        MarketData {
+            records: content.records,
+            fields: content.headers,
+            format_problems: Vec::new(),
+            http_bytes: content.http_bytes,
+            wire_bytes: content.wire_bytes,
+            parse_errors: content.parse_errors,
+            filtered: content.filtered,
+            source_url: content.source_url,
+        }
+    }
+}
+
+pub fn csv_url(market: &str) -> String {
+    format!("{}?mkt={}", CSV_URL, market)
+}
+
+pub async fn get_page_content_last_update_time(fetcher: &dyn HttpFetcher) -> Result<NaiveDateTime> {
+    let resp = fetcher.get_text(PAGE_URL).await?;
+    parse_last_update_time(&resp)
+}
+
+pub fn parse_last_update_time(page: &str) -> Result<NaiveDateTime> {
+    let re = Regex::new(r"last updated (\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2})")?;
+    if let Some(caps) = re.captures(page) {
+        let dt = NaiveDateTime::parse_from_str(&caps[1], "%Y-%m-%d %H:%M:%S")?;
+        info!("Last update time: {}", dt);
+        Ok(dt)
+    } else {
+        error!("Failed to parse last update time.");
+        Err(anyhow::anyhow!("Parse error"))
+    }
+}
+
+pub async fn get_csv_content(fetcher: &dyn HttpFetcher, market: &str, filter: &SymbolFilter) -> Result<CsvContent> {
+    let url = csv_url(market);
+    info!("Fetching CSV from {}", url);
+    let download = fetcher.get_download(&url).await?;
+    let http_bytes = download.body.len() as u64;
+    let wire_bytes = download.wire_bytes;
+    let cursor = Cursor::new(download.body);
+
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(cursor);
+
+    let headers = rdr.headers()?.iter().map(|h| h.trim().to_string()).collect();
+    let mut records = Vec::new();
+    let mut parse_errors = 0;
+    let mut filtered = 0;
+    for result in rdr.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Skipping malformed CSV row in {}: {}", market, e);
+                parse_errors += 1;
+                continue;
+            }
+        };
+        if record.len() < 12 {
+            parse_errors += 1;
+            continue;
+        }
+        if !filter.matches(&record[0]) {
+            filtered += 1;
+            continue;
+        }
+
+        let option_record = OptionRecord {
+            symbol: record[0].to_string(),
+            call_put: record[1].to_string(),
+            expiration: record[2].to_string(),
+            strike_price: normalize_strike(parse_field(&record[3], &mut parse_errors)),
+            volume: parse_field(&record[4], &mut parse_errors),
+            matched: parse_field(&record[5], &mut parse_errors),
+            routed: parse_field(&record[6], &mut parse_errors),
+            bid_size: parse_field(&record[7], &mut parse_errors),
+            bid_price: parse_field(&record[8], &mut parse_errors),
+            ask_size: parse_field(&record[9], &mut parse_errors),
+            ask_price: parse_field(&record[10], &mut parse_errors),
+            last_price: parse_field(&record[11], &mut parse_errors),
+        };
+
+        records.push(option_record);
+    }
+
+    if filtered > 0 {
+        info!("Filtered out {} rows from {}.", filtered, market);
+    }
+
+    Ok(CsvContent { headers, records, http_bytes, wire_bytes, parse_errors, filtered, source_url: Some(url) })
+}
+
+// 空字段按 0 处理，不算解析错误
+pub fn parse_field<T: FromStr + Default>(value: &str, parse_errors: &mut u64) -> T {
+    let value = value.trim();
+    if value.is_empty() {
+        return T::default();
+    }
+    value.parse().unwrap_or_else(|_| {
+        *parse_errors += 1;
+        T::default()
+    })
+}
+
+// 返回与预期表头不一致的描述，空表示兼容
+pub fn check_headers(headers: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, expected) in EXPECTED_HEADERS.iter().enumerate() {
+        match headers.get(i) {
+            Some(actual) if actual.eq_ignore_ascii_case(expected) => {}
+            Some(actual) => problems.push(format!("column {} is \"{}\", expected \"{}\"", i + 1, actual, expected)),
+            None => problems.push(format!("missing column {} \"{}\"", i + 1, expected)),
+        }
+    }
+    problems
+}
+
+// 页面上快照时间附近的文字，数字替换成 9，去掉 HTML 标签；找不到时间时取 "updated" 附近的文字。
+// 页面措辞变化（比如 "last updated" 改成 "As of"）时签名随之变化，用于格式漂移检测
+pub fn page_signature(page: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").expect("valid regex");
+    let text = tags.replace_all(page, " ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let timestamp = Regex::new(r"\d{4}-\d{2}-\d{2}[ T]\d{1,2}:\d{2}(:\d{2})?").expect("valid regex");
+    let (start, end) = match timestamp.find(&text) {
+        Some(m) => (m.start(), m.end()),
+        None => match text.to_ascii_lowercase().find("updated") {
+            Some(i) => (i, i + "updated".len()),
+            None => return "(no timestamp on page)".to_string(),
+        },
+    };
+    // 取前面最多 40 个字符，从词的边界开始
+    let mut from = start.saturating_sub(40);
+    while !text.is_char_boundary(from) {
+        from += 1;
+    }
+    let before = &text[from..start];
+    let before = if from > 0 { before.split_once(' ').map_or(before, |(_, rest)| rest) } else { before };
+    format!("{}{}", before, &text[start..end]).trim().chars().map(|c| if c.is_ascii_digit() { '9' } else { c }).collect()
+}
+
+pub struct CboeSource<'a> {
+    fetcher: &'a dyn HttpFetcher,
+    page_signature: Mutex<Option<String>>,
+}
+
+impl<'a> CboeSource<'a> {
+    pub fn new(fetcher: &'a dyn HttpFetcher) -> CboeSource<'a> {
+        CboeSource { fetcher, page_signature: Mutex::new(None) }
+    }
+}
+
+#[async_trait]
+impl SnapshotSource for CboeSource<'_> {
+    fn name(&self) -> &'static str {
+        "cboe"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        MARKETS.iter().map(|m| m.to_string()).collect()
+    }
+
+    async fn snapshot_time(&self) -> Result<NaiveDateTime> {
+        let page = self.fetcher.get_text(PAGE_URL).await?;
+        *self.page_signature.lock().unwrap_or_else(PoisonError::into_inner) = Some(page_signature(&page));
+        parse_last_update_time(&page)
+    }
+
+    fn format_signature(&self) -> Option<String> {
+        self.page_signature.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    async fn fetch(&self, market: &str, filter: &SymbolFilter) -> Result<MarketData> {
+        let content = get_csv_content(self.fetcher, market, filter).await?;
+        let format_problems = check_headers(&content.headers);
+        Ok(MarketData { format_problems, ..content.into() })
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use csv::ReaderBuilder;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+use crate::calendar;
+use crate::cboe::parse_field;
+use crate::filter::SymbolFilter;
+use crate::http::HttpFetcher;
+use crate::shutdown::Cancelled;
+
+const CSV_URL: &str = "https://www.cboe.com/us/futures/market_statistics/symbol_data/csv/?mkt=cfe";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FuturesConfig {
+    pub enabled: bool,
+    pub url: String,
+}
+
+impl Default for FuturesConfig {
+    fn default() -> Self {
+        FuturesConfig { enabled: false, url: CSV_URL.to_string() }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct FuturesRecord {
+    pub symbol: String,
+    pub expiration: String,
+    pub volume: i64,
+    pub bid_size: i64,
+    pub bid_price: f64,
+    pub ask_size: i64,
+    pub ask_price: f64,
+    pub last_price: f64,
+}
+
+pub struct FuturesContent {
+    pub records: Vec<FuturesRecord>,
+    pub http_bytes: u64,
+    pub wire_bytes: u64,
+    pub parse_errors: u64,
+    pub filtered: u64,
+}
+
+// CFE 的列顺序和期权不同，按表头名取值
+fn column(headers: &csv::StringRecord, name: &str) -> Result<usize> {
+    match headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name)) {
+        Some(i) => Ok(i),
+        None => bail!("CFE CSV is missing column \"{}\"", name),
+    }
+}
+
+pub async fn get_csv_content(fetcher: &dyn HttpFetcher, url: &str, filter: &SymbolFilter) -> Result<FuturesContent> {
+    info!("Fetching CFE CSV from {}", url);
+    let download = fetcher.get_download(url).await?;
+    let http_bytes = download.body.len() as u64;
+    let wire_bytes = download.wire_bytes;
+    let mut rdr = ReaderBuilder::new().has_headers(true).flexible(true).from_reader(download.body.as_ref());
+
+    let headers = rdr.headers()?.clone();
+    let symbol = column(&headers, "Symbol")?;
+    let expiration = column(&headers, "Expiration")?;
+    let volume = column(&headers, "Volume")?;
+    let bid_size = column(&headers, "Bid Size")?;
+    let bid_price = column(&headers, "Bid Price")?;
+    let ask_size = column(&headers, "Ask Size")?;
+    let ask_price = column(&headers, "Ask Price")?;
+    let last_price = column(&headers, "Last Price")?;
+
+    let mut records = Vec::new();
+    let mut parse_errors = 0;
+    let mut filtered = 0;
+    for result in rdr.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Skipping malformed CFE CSV row: {}", e);
+                parse_errors += 1;
+                continue;
+            }
+        };
+        let field = |i: usize| record.get(i).unwrap_or_default();
+        if !filter.matches(field(symbol)) {
+            filtered += 1;
+            continue;
+        }
+        records.push(FuturesRecord {
+            symbol: field(symbol).trim().to_string(),
+            expiration: field(expiration).trim().to_string(),
+            volume: parse_field(field(volume), &mut parse_errors),
+            bid_size: parse_field(field(bid_size), &mut parse_errors),
+            bid_price: parse_field(field(bid_price), &mut parse_errors),
+            ask_size: parse_field(field(ask_size), &mut parse_errors),
+            ask_price: parse_field(field(ask_price), &mut parse_errors),
+            last_price: parse_field(field(last_price), &mut parse_errors),
+        });
+    }
+
+    Ok(FuturesContent { records, http_bytes, wire_bytes, parse_errors, filtered })
+}
+
+// 快照时间沿用期权页面的更新时间，便于和同一时刻的期权快照对齐
+pub async fn insert_records(
+    pool: &PgPool,
+    records: &[FuturesRecord],
+    last_updated_time: NaiveDateTime,
+    token: &CancellationToken,
+) -> Result<u64> {
+    let etl_in_dt = calendar::now_new_york();
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+    for rec in records {
+        if token.is_cancelled() {
+            tx.rollback().await?;
+            return Err(Cancelled.into());
+        }
+        let result = sqlx::query(r#"
+            INSERT INTO t_futures_cboe_snapshot
+            (symbol, expiration, volume, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (symbol, expiration, last_updated_time)
+            DO UPDATE SET
+                volume = EXCLUDED.volume,
+                bid_size = EXCLUDED.bid_size,
+                bid_price = EXCLUDED.bid_price,
+                ask_size = EXCLUDED.ask_size,
+                ask_price = EXCLUDED.ask_price,
+                last_price = EXCLUDED.last_price,
+                etl_in_dt = EXCLUDED.etl_in_dt
+        "#)
+            .bind(&rec.symbol)
+            .bind(&rec.expiration)
+            .bind(rec.volume)
+            .bind(rec.bid_size)
+            .bind(rec.bid_price)
+            .bind(rec.ask_size)
+            .bind(rec.ask_price)
+            .bind(rec.last_price)
+            .bind(last_updated_time)
+            .bind(etl_in_dt)
+            .execute(&mut *tx)
+            .await?;
+        inserted += result.rows_affected();
+    }
+    tx.commit().await?;
+    info!("Inserted {} CFE futures records.", records.len());
+    Ok(inserted)
+}
@@ -0,0 +1,220 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+
+use crate::MarketSnapshot;
+use crate::cboe::{OptionRecord, parse_expiration};
+
+// 同一行权价的看涨 / 看跌合约
+pub struct StrikeRow {
+    pub strike: f64,
+    pub call: Option<OptionRecord>,
+    pub put: Option<OptionRecord>,
+}
+
+// 一个到期日下按行权价升序排列的合约
+pub struct Expiry {
+    pub expiration: String,
+    pub expiration_date: Option<NaiveDate>,
+    pub strikes: Vec<StrikeRow>,
+}
+
+// 一个标的的完整期权链，到期日升序
+pub struct OptionChain {
+    pub underlying: String,
+    pub snapshot_time: NaiveDateTime,
+    pub expirations: Vec<Expiry>,
+}
+
+// 可以解析的到期日和原字符串（解析成功时为空）
+type ExpiryKey = (Option<NaiveDate>, String);
+
+fn volumes<'a>(rows: impl Iterator<Item = &'a StrikeRow>) -> (i64, i64) {
+    rows.fold((0, 0), |(calls, puts), row| {
+        (calls + row.call.as_ref().map_or(0, |r| r.volume), puts + row.put.as_ref().map_or(0, |r| r.volume))
+    })
+}
+
+// 看跌成交量 / 看涨成交量，看涨没有成交时为 None
+fn ratio((calls, puts): (i64, i64)) -> Option<f64> {
+    (calls > 0).then(|| puts as f64 / calls as f64)
+}
+
+fn nearest<'a>(strikes: impl Iterator<Item = &'a f64>, spot: f64) -> Option<f64> {
+    strikes.copied().min_by(|a, b| (a - spot).abs().total_cmp(&(b - spot).abs()))
+}
+
+impl Expiry {
+    pub fn total_volume(&self) -> i64 {
+        let (calls, puts) = volumes(self.strikes.iter());
+        calls + puts
+    }
+
+    pub fn put_call_ratio(&self) -> Option<f64> {
+        ratio(volumes(self.strikes.iter()))
+    }
+
+    pub fn atm_strike(&self, spot: f64) -> Option<f64> {
+        nearest(self.strikes.iter().map(|s| &s.strike), spot)
+    }
+
+    pub fn atm(&self, spot: f64) -> Option<&StrikeRow> {
+        let strike = self.atm_strike(spot)?;
+        self.strikes.iter().find(|s| s.strike == strike)
+    }
+}
+
+impl OptionChain {
+    // records 里其它标的的合约会被忽略
+    pub fn from_records(underlying: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> OptionChain {
+        // 同一到期日可能有不同的日期写法，能解析的按日期归并，解析不了的按原字符串
+        let mut grouped: BTreeMap<ExpiryKey, (String, BTreeMap<i64, StrikeRow>)> = BTreeMap::new();
+        for rec in records.iter().filter(|r| r.symbol.trim().eq_ignore_ascii_case(underlying.trim())) {
+            let expiration_date = parse_expiration(&rec.expiration);
+            let group = (expiration_date, if expiration_date.is_some() { String::new() } else { rec.expiration.clone() });
+            let (_, strikes) = grouped.entry(group).or_insert_with(|| (rec.expiration.clone(), BTreeMap::new()));
+            let row = strikes
+                .entry(rec.key().strike_millis)
+                .or_insert_with(|| StrikeRow { strike: rec.strike_price, call: None, put: None });
+            match rec.call_put.trim().to_ascii_uppercase().as_str() {
+                "C" => row.call = Some(rec.clone()),
+                "P" => row.put = Some(rec.clone()),
+                _ => {}
+            }
+        }
+        let expirations = grouped
+            .into_iter()
+            .map(|((expiration_date, _), (expiration, strikes))| Expiry { expiration, expiration_date, strikes: strikes.into_values().collect() })
+            .collect();
+        OptionChain { underlying: underlying.to_string(), snapshot_time, expirations }
+    }
+
+    pub fn expiration(&self, expiration: &str) -> Option<&Expiry> {
+        self.expirations.iter().find(|e| e.expiration == expiration)
+    }
+
+    pub fn total_volume(&self) -> i64 {
+        self.expirations.iter().map(|e| e.total_volume()).sum()
+    }
+
+    pub fn put_call_ratio(&self) -> Option<f64> {
+        ratio(volumes(self.expirations.iter().flat_map(|e| e.strikes.iter())))
+    }
+
+    // 所有到期日里离现价最近的行权价
+    pub fn atm_strike(&self, spot: f64) -> Option<f64> {
+        nearest(self.expirations.iter().flat_map(|e| e.strikes.iter().map(|s| &s.strike)), spot)
+    }
+}
+
+impl MarketSnapshot {
+    pub fn chain(&self, underlying: &str) -> OptionChain {
+        OptionChain::from_records(underlying, self.snapshot_time, &self.records)
+    }
+
+    // 按标的分组的全部期权链
+    pub fn chains(&self) -> BTreeMap<String, OptionChain> {
+        let mut by_symbol: BTreeMap<String, Vec<OptionRecord>> = BTreeMap::new();
+        for rec in &self.records {
+            by_symbol.entry(rec.symbol.trim().to_ascii_uppercase()).or_default().push(rec.clone());
+        }
+        by_symbol
+            .into_iter()
+            .map(|(symbol, records)| {
+                let chain = OptionChain::from_records(&symbol, self.snapshot_time, &records);
+                (symbol, chain)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(symbol: &str, call_put: &str, expiration: &str, strike_price: f64, volume: i64) -> OptionRecord {
+        OptionRecord {
+            symbol: symbol.to_string(),
+            call_put: call_put.to_string(),
+            expiration: expiration.to_string(),
+            strike_price,
+            volume,
+            matched: 0,
+            routed: 0,
+            bid_size: 0,
+            bid_price: 0.0,
+            ask_size: 0,
+            ask_price: 0.0,
+            last_price: 0.0,
+        }
+    }
+
+    fn chain_of(records: &[OptionRecord]) -> OptionChain {
+        let snapshot_time = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap().and_hms_opt(15, 0, 0).unwrap();
+        OptionChain::from_records("SPY", snapshot_time, records)
+    }
+
+    #[test]
+    fn pairs_calls_and_puts_by_strike() {
+        let chain = chain_of(&[
+            record("SPY", "P", "2026-10-16", 500.0, 30),
+            record("SPY", "C", "2026-10-16", 500.0, 10),
+            record("spy ", "c", "2026-10-16", 495.0, 20),
+            record("QQQ", "C", "2026-10-16", 500.0, 99),
+        ]);
+        assert_eq!(chain.expirations.len(), 1);
+        let expiry = &chain.expirations[0];
+        assert_eq!(expiry.strikes.iter().map(|s| s.strike).collect::<Vec<_>>(), [495.0, 500.0]);
+        let row = &expiry.strikes[1];
+        assert_eq!(row.call.as_ref().map(|r| r.volume), Some(10));
+        assert_eq!(row.put.as_ref().map(|r| r.volume), Some(30));
+        assert_eq!(chain.total_volume(), 60);
+        assert_eq!(chain.put_call_ratio(), Some(1.0));
+    }
+
+    #[test]
+    fn missing_legs_stay_empty() {
+        let chain = chain_of(&[
+            record("SPY", "C", "2026-10-16", 500.0, 10),
+            record("SPY", "P", "2026-10-16", 490.0, 5),
+            record("SPY", "X", "2026-10-16", 480.0, 7),
+        ]);
+        let strikes = &chain.expirations[0].strikes;
+        assert_eq!(strikes.len(), 3);
+        // 无法识别的看涨 / 看跌标记只留下空的行权价
+        assert!(strikes[0].call.is_none() && strikes[0].put.is_none());
+        assert!(strikes[1].call.is_none() && strikes[1].put.is_some());
+        assert!(strikes[2].call.is_some() && strikes[2].put.is_none());
+        assert_eq!(chain.total_volume(), 15);
+
+        let puts_only = chain_of(&[record("SPY", "P", "2026-10-16", 500.0, 5)]);
+        assert_eq!(puts_only.put_call_ratio(), None);
+    }
+
+    #[test]
+    fn duplicate_strikes_keep_the_last_row() {
+        let chain = chain_of(&[
+            record("SPY", "C", "2026-10-16", 500.0, 10),
+            record("SPY", "C", "2026-10-16", 500.0004, 25),
+            record("SPY", "C", "10/16/2026", 500.0, 40),
+        ]);
+        // 同一到期日的不同写法归并到一起，行权价按千分之一取整比较
+        assert_eq!(chain.expirations.len(), 1);
+        let strikes = &chain.expirations[0].strikes;
+        assert_eq!(strikes.len(), 1);
+        assert_eq!(strikes[0].call.as_ref().map(|r| r.volume), Some(40));
+        assert_eq!(chain.total_volume(), 40);
+    }
+
+    #[test]
+    fn unparsed_expirations_are_grouped_by_text() {
+        let chain = chain_of(&[
+            record("SPY", "C", "2026-11-20", 500.0, 1),
+            record("SPY", "C", "Nov 2026", 500.0, 2),
+            record("SPY", "C", "2026-10-16", 500.0, 3),
+        ]);
+        let expirations: Vec<_> = chain.expirations.iter().map(|e| e.expiration.as_str()).collect();
+        assert_eq!(expirations, ["Nov 2026", "2026-10-16", "2026-11-20"]);
+        assert_eq!(chain.expiration("2026-10-16").map(|e| e.total_volume()), Some(3));
+        assert_eq!(chain.atm_strike(503.0), Some(500.0));
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use log::info;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+
+use crate::cboe::{ContractKey, OptionRecord};
+use crate::storage::SnapshotTable;
+
+const STATE_CHUNK: usize = 10_000;
+
+// 哈希要写进数据库跨版本比较，不能用 DefaultHasher，这里用固定的 FNV-1a
+fn content_hash(rec: &OptionRecord) -> i64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let fields = [
+        rec.volume as u64, rec.matched as u64, rec.routed as u64, rec.bid_size as u64, rec.ask_size as u64,
+        rec.bid_price.to_bits(), rec.ask_price.to_bits(), rec.last_price.to_bits(),
+    ];
+    for byte in fields.iter().flat_map(|f| f.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash as i64
+}
+
+// 一个市场本次快照的变化：需要写入的合约，以及所有合约的哈希（写入成功后更新状态表）
+pub struct Changes {
+    pub changed: Vec<OptionRecord>,
+    pub unchanged: u64,
+    hashes: HashMap<ContractKey, (f64, i64)>,
+}
+
+pub async fn detect(pool: &PgPool, market: &str, records: &[OptionRecord]) -> Result<Changes> {
+    let rows = sqlx::query("SELECT symbol, call_put, expiration, strike_price, content_hash FROM t_cboe_contract_state WHERE market = $1")
+        .bind(market)
+        .fetch_all(pool)
+        .await?;
+    let mut previous = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let key = ContractKey::new(row.try_get("symbol")?, row.try_get("call_put")?, row.try_get("expiration")?, row.try_get("strike_price")?);
+        previous.insert(key, row.try_get::<i64, _>("content_hash")?);
+    }
+    let mut changes = Changes { changed: Vec::new(), unchanged: 0, hashes: HashMap::with_capacity(records.len()) };
+    // CSV 里同一合约出现多次时以最后一行为准，与写入时的 upsert 和 verify::checksum 一致
+    let mut last = HashMap::with_capacity(records.len());
+    for (i, rec) in records.iter().enumerate() {
+        last.insert(rec.key(), i);
+    }
+    for (i, rec) in records.iter().enumerate() {
+        let key = rec.key();
+        if last.get(&key) != Some(&i) {
+            continue;
+        }
+        let hash = content_hash(rec);
+        if previous.get(&key) == Some(&hash) {
+            changes.unchanged += 1;
+        } else {
+            changes.changed.push(rec.clone());
+        }
+        changes.hashes.insert(key, (rec.strike_price, hash));
+    }
+    info!("{}: {} contracts changed, {} unchanged since the previous snapshot.", market, changes.changed.len(), changes.unchanged);
+    Ok(changes)
+}
+
+pub async fn last_seen(pool: &PgPool) -> Result<Option<NaiveDateTime>> {
+    Ok(sqlx::query_scalar("SELECT max(seen_at) FROM t_cboe_contract_state").fetch_one(pool).await?)
+}
+
+// 只在快照写入成功之后调用，否则下次会把没写进去的合约当成没变化
+pub async fn record(pool: &PgPool, market: &str, snapshot_time: NaiveDateTime, changes: &Changes) -> Result<()> {
+    let entries: Vec<_> = changes.hashes.iter().collect();
+    for chunk in entries.chunks(STATE_CHUNK) {
+        let mut symbols = Vec::with_capacity(chunk.len());
+        let mut call_puts = Vec::with_capacity(chunk.len());
+        let mut expirations = Vec::with_capacity(chunk.len());
+        let mut strikes = Vec::with_capacity(chunk.len());
+        let mut hashes = Vec::with_capacity(chunk.len());
+        for (key, (strike, hash)) in chunk {
+            symbols.push(key.symbol.as_str());
+            call_puts.push(key.call_put.as_str());
+            expirations.push(key.expiration.as_str());
+            strikes.push(*strike);
+            hashes.push(*hash);
+        }
+        sqlx::query(r#"
+            INSERT INTO t_cboe_contract_state AS s (market, symbol, call_put, expiration, strike_price, content_hash, changed_at, seen_at)
+            SELECT $1, u.symbol, u.call_put, u.expiration, u.strike_price, u.content_hash, $2, $2
+            FROM UNNEST($3::TEXT[], $4::TEXT[], $5::TEXT[], $6::DOUBLE PRECISION[], $7::BIGINT[])
+                AS u(symbol, call_put, expiration, strike_price, content_hash)
+            ON CONFLICT (market, symbol, call_put, expiration, strike_price) DO UPDATE SET
+                changed_at = CASE WHEN s.content_hash = EXCLUDED.content_hash THEN s.changed_at ELSE EXCLUDED.changed_at END,
+                content_hash = EXCLUDED.content_hash,
+                seen_at = EXCLUDED.seen_at
+        "#)
+            .bind(market)
+            .bind(snapshot_time)
+            .bind(&symbols)
+            .bind(&call_puts)
+            .bind(&expirations)
+            .bind(&strikes)
+            .bind(&hashes)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+// 最新一次快照的完整合约列表：状态表里本次出现过的合约，连接到它们最近一次变化时写入的那一行
+pub fn latest_snapshot_sql(table: &SnapshotTable) -> String {
+    format!(r#"
+        SELECT t.*, t.last_updated_time <> s.seen_at AS carried_forward
+        FROM t_cboe_contract_state s
+        JOIN {table} t
+          ON t.market = s.market AND t.symbol = s.symbol AND t.call_put = s.call_put
+         AND t.expiration = s.expiration AND t.strike_price = s.strike_price AND t.last_updated_time = s.changed_at
+        WHERE s.seen_at = $1 AND t.source = 'symbol_data'
+    "#)
+}
+
+// 某次快照的完整合约列表，快照时间绑定在 $1，返回快照表的所有列加 carried_forward。
+// 不开变化检测时就是这次写入的行；开启后最新快照走状态表，历史快照用 cboe_snapshot_at 还原后连回快照表取其余列
+pub fn snapshot_sql(table: &SnapshotTable, latest: bool) -> String {
+    if !table.change_detection {
+        return format!("SELECT *, false AS carried_forward FROM {table} WHERE last_updated_time = $1 AND source = 'symbol_data'");
+    }
+    if latest {
+        return latest_snapshot_sql(table);
+    }
+    format!(r#"
+        SELECT t.*, a.carried_forward
+        FROM cboe_snapshot_at($1) a
+        JOIN {table} t
+          ON t.market = a.market AND t.symbol = a.symbol AND t.call_put = a.call_put
+         AND t.expiration = a.expiration AND t.strike_price = a.strike_price AND t.last_updated_time = a.changed_at
+        WHERE t.source = 'symbol_data'
+    "#)
+}
+
+// 读者要的快照：没指定时间时取最新一次快照，返回快照时间和 snapshot_sql；一次快照都没有时返回 None。
+// 开启变化检测时没有变化的快照不会写进快照表，最新时间以状态表的 seen_at 为准
+pub async fn resolve(pool: &PgPool, table: &SnapshotTable, snapshot: Option<NaiveDateTime>) -> Result<Option<(NaiveDateTime, String)>> {
+    let latest = match table.change_detection {
+        true => last_seen(pool).await?,
+        false => None,
+    };
+    let latest = match latest {
+        Some(time) => Some(time),
+        None => crate::db::get_max_updated_date(pool, table).await?,
+    };
+    let Some(time) = snapshot.or(latest) else {
+        return Ok(None);
+    };
+    Ok(Some((time, snapshot_sql(table, Some(time) == latest))))
+}
+
+// [$1, $2) 内每天的快照行，多一列 day（纽约日期）。开启变化检测时当天没变化的合约不会写入，
+// 用当天第一次快照（cboe_snapshot_at）把之前沿用过来的行补上，补上的行 day 是这一天
+pub fn daily_rows_sql(table: &SnapshotTable) -> String {
+    let rows = format!(r#"
+        SELECT t.*, t.last_updated_time::date AS day FROM {table} t
+        WHERE t.last_updated_time >= $1 AND t.last_updated_time < $2 AND t.source = 'symbol_data'
+    "#);
+    if !table.change_detection {
+        return rows;
+    }
+    format!(r#"
+        {rows}
+        UNION ALL
+        SELECT t.*, f.day
+        FROM (
+            SELECT last_updated_time::date AS day, min(last_updated_time) AS first_snapshot FROM {table}
+            WHERE last_updated_time >= $1 AND last_updated_time < $2 AND source = 'symbol_data'
+            GROUP BY 1
+        ) f
+        CROSS JOIN LATERAL cboe_snapshot_at(f.first_snapshot) a
+        JOIN {table} t
+          ON t.market = a.market AND t.symbol = a.symbol AND t.call_put = a.call_put
+         AND t.expiration = a.expiration AND t.strike_price = a.strike_price AND t.last_updated_time = a.changed_at
+        WHERE a.carried_forward AND t.source = 'symbol_data'
+    "#)
+}
+
+// prune 删掉的行可能正被沿用，先清掉对应的状态，下次加载时这些合约会重新完整写入
+pub async fn forget_before(pool: &PgPool, cutoff: NaiveDateTime) -> Result<u64> {
+    let removed = sqlx::query("DELETE FROM t_cboe_contract_state WHERE changed_at < $1")
+        .bind(cutoff)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if removed > 0 {
+        info!("Reset change-detection state for {} contracts whose last change is being pruned.", removed);
+    }
+    Ok(removed)
+}
@@ -0,0 +1,124 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::calendar::exchange_holidays;
+
+// 距到期超过一年的合约视为 LEAP
+const LEAP_DAYS: i64 = 365;
+
+pub struct Classification {
+    pub days_to_expiration: i32,
+    pub expiration_class: &'static str,
+}
+
+// 按快照日期给合约分类：剩余天数和到期类型（weekly / monthly / quarterly / leap）
+pub fn classify(expiration: NaiveDate, snapshot_date: NaiveDate) -> Classification {
+    let days = (expiration - snapshot_date).num_days();
+    let expiration_class = if days > LEAP_DAYS { "leap" } else { expiration_cycle(expiration) };
+    Classification { days_to_expiration: days as i32, expiration_class }
+}
+
+// 只看到期日本身的周期（quarterly / monthly / weekly），与快照日期无关
+pub fn expiration_cycle(expiration: NaiveDate) -> &'static str {
+    if is_quarterly(expiration) {
+        "quarterly"
+    } else if is_monthly(expiration) {
+        "monthly"
+    } else {
+        "weekly"
+    }
+}
+
+// 月度合约在第三个周五到期，周五休市时提前到周四
+fn is_monthly(date: NaiveDate) -> bool {
+    let Some(third_friday) = NaiveDate::from_weekday_of_month_opt(date.year(), date.month(), Weekday::Fri, 3) else {
+        return false;
+    };
+    if date == third_friday {
+        return true;
+    }
+    date == third_friday - Duration::days(1) && exchange_holidays(date.year()).contains(&third_friday)
+}
+
+// 季度合约在 3/6/9/12 月最后一个交易日到期
+fn is_quarterly(date: NaiveDate) -> bool {
+    if !date.month().is_multiple_of(3) {
+        return false;
+    }
+    let holidays = exchange_holidays(date.year());
+    let first_of_next = NaiveDate::from_ymd_opt(date.year(), date.month(), 1)
+        .and_then(|d| d.checked_add_months(chrono::Months::new(1)));
+    let Some(mut last) = first_of_next.map(|d| d - Duration::days(1)) else {
+        return false;
+    };
+    while matches!(last.weekday(), Weekday::Sat | Weekday::Sun) || holidays.contains(&last) {
+        last -= Duration::days(1);
+    }
+    date == last
+}
+
+// 行权价按 OCC 精度（千分之一）取整，去掉 CSV 里 33.330000001 这类浮点噪声，冲突键才能对上
+pub fn normalize_strike(strike: f64) -> f64 {
+    (strike * 1000.0).round() / 1000.0
+}
+
+// 调整合约（拆股、特别派息、并购等公司行动后的非标准合约）有两个特征：
+// OCC 代码在原代码后加数字（AAPL1、TSLA2），或行权价不是 0.25 的整数倍（如 3 拆 1 后的 33.33）
+pub fn is_adjusted(symbol: &str, strike: f64) -> bool {
+    let symbol = symbol.trim();
+    let numbered = symbol.len() > 1
+        && symbol.starts_with(|c: char| c.is_ascii_alphabetic())
+        && symbol.ends_with(|c: char| c.is_ascii_digit());
+    let quarters = normalize_strike(strike) * 4.0;
+    numbered || (quarters - quarters.round()).abs() > 1e-6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn class(expiration: &str, snapshot: &str) -> (i32, &'static str) {
+        let c = classify(date(expiration), date(snapshot));
+        (c.days_to_expiration, c.expiration_class)
+    }
+
+    #[test]
+    fn same_day_expiration_has_zero_days() {
+        assert_eq!(class("2026-10-14", "2026-10-14"), (0, "weekly"));
+        assert_eq!(class("2026-10-16", "2026-10-16"), (0, "monthly"));
+    }
+
+    #[test]
+    fn monthly_is_the_third_friday() {
+        assert_eq!(expiration_cycle(date("2026-10-16")), "monthly");
+        assert_eq!(expiration_cycle(date("2026-10-09")), "weekly");
+        assert_eq!(expiration_cycle(date("2026-10-23")), "weekly");
+        // 第三个周五休市（2025 耶稣受难日、2026 六月节）时提前到周四
+        assert_eq!(expiration_cycle(date("2025-04-17")), "monthly");
+        assert_eq!(expiration_cycle(date("2026-06-18")), "monthly");
+        assert_eq!(expiration_cycle(date("2025-10-16")), "weekly");
+    }
+
+    #[test]
+    fn quarterly_is_the_last_trading_day_of_the_quarter() {
+        assert_eq!(expiration_cycle(date("2026-03-31")), "quarterly");
+        assert_eq!(expiration_cycle(date("2025-06-30")), "quarterly");
+        assert_eq!(expiration_cycle(date("2026-12-31")), "quarterly");
+        assert_eq!(expiration_cycle(date("2026-12-30")), "weekly");
+        // 不在季末月份
+        assert_eq!(expiration_cycle(date("2026-10-30")), "weekly");
+        // 2023-09-30 是周六，取周五
+        assert_eq!(expiration_cycle(date("2023-09-29")), "quarterly");
+        // 2024-03-31 是周日，周五 03-29 是耶稣受难日，取周四
+        assert_eq!(expiration_cycle(date("2024-03-28")), "quarterly");
+    }
+
+    #[test]
+    fn leap_after_one_year() {
+        assert_eq!(class("2027-10-14", "2026-10-14"), (365, "weekly"));
+        assert_eq!(class("2027-10-15", "2026-10-14"), (366, "leap"));
+    }
+}
@@ -0,0 +1,373 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::cboe::MARKETS;
+use crate::export::{ExportFormat, ExportLayout, SnapshotSelector, parse_snapshot};
+use crate::filter::FilterConfig;
+use crate::memory::MemoryConfig;
+use crate::output::Format;
+use crate::report::ReportFormat;
+use crate::retention::PruneMode;
+use crate::storage::InsertOptions;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Load CBOE option symbol data snapshots into Postgres",
+    after_help = "Exit codes: 0 loaded, 10 already up to date (skipped), 20 partial load, 30 fetch failure, 40 database failure, 130 interrupted"
+)]
+pub struct Cli {
+    /// Path to the TOML config file (defaults to ./cboe.toml when present)
+    #[arg(short, long, global = true, env = "CBOE_CONFIG")]
+    pub config: Option<String>,
+
+    /// Only use these [jobs.<name>] from the config; run and daemon use every job when omitted
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub job: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Fetch the latest snapshot and load it (default)
+    Run(RunArgs),
+    /// Keep running and load new snapshots during trading hours
+    Daemon(DaemonArgs),
+    /// Show the most recent runs from the ETL log
+    Status(StatusArgs),
+    /// Remove snapshots older than the retention window
+    Prune(PruneArgs),
+    /// Delete or archive rows of contracts that expired more than N days ago
+    PurgeExpired(PurgeExpiredArgs),
+    /// Convert the snapshot table into a TimescaleDB hypertable and apply policies (requires --features timescale)
+    TimescaleSetup,
+    /// Quick lookups against the stored snapshots
+    Query(QueryArgs),
+    /// Dump one snapshot to CSV or JSON Lines
+    Export(ExportArgs),
+    /// Serve a read-only HTTP API over the stored snapshots
+    Serve(ServeArgs),
+    /// Load CBOE delayed quotes for watchlist symbols between full snapshots
+    Quotes(QuotesArgs),
+    /// Load local CSV files using the [import] column mapping
+    Import(ImportArgs),
+    /// Roll one day of intraday snapshots into the end-of-day table
+    Consolidate(ConsolidateArgs),
+    /// Compare two snapshots, or a snapshot against a local CSV
+    Diff(DiffArgs),
+    /// Summarize the past week of snapshots as text, Markdown or HTML
+    Report(ReportArgs),
+    /// Recompute snapshot checksums from the database and compare them with the run log
+    Verify(VerifyArgs),
+    /// Regenerate the expiration calendar tables from the stored snapshots
+    RebuildExpirations(RebuildExpirationsArgs),
+}
+
+pub fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| format!("expected YYYY-MM-DD HH:MM:SS, got {}", value))
+}
+
+#[derive(Args, Default)]
+pub struct RunArgs {
+    /// Commit inserts every N rows instead of one transaction per market
+    #[arg(long, env = "CBOE_COMMIT_BATCH_SIZE")]
+    pub commit_batch_size: Option<usize>,
+    /// Insert batches over this many parallel connections (Postgres only)
+    #[arg(long, env = "CBOE_INSERT_WORKERS", default_value_t = 1)]
+    pub insert_workers: usize,
+    /// Scan the whole table for duplicate rows after loading
+    #[arg(long)]
+    pub deep_clean: bool,
+    /// Skip rows with no volume, no quotes and no last price
+    #[arg(long)]
+    pub skip_inactive: bool,
+    /// Fetch and parse everything and print a load preview without writing to the database
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Exit quietly when the exchange is closed (weekends, holidays, outside trading hours)
+    #[arg(long)]
+    pub skip_if_closed: bool,
+    /// Continue an interrupted run of the same snapshot from its last committed batch (Postgres only)
+    #[arg(long)]
+    pub resume: bool,
+    /// Only load these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+    /// Only load symbols starting with one of these prefixes
+    #[arg(long, value_delimiter = ',')]
+    pub include_prefix: Vec<String>,
+    /// Only load symbols matching this regex (case-insensitive)
+    #[arg(long)]
+    pub include_regex: Option<String>,
+    /// Skip these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    /// Skip symbols starting with one of these prefixes
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_prefix: Vec<String>,
+    /// Skip symbols matching this regex (case-insensitive)
+    #[arg(long)]
+    pub exclude_regex: Option<String>,
+}
+
+impl RunArgs {
+    pub fn insert_options(&self, memory: &MemoryConfig) -> InsertOptions {
+        InsertOptions { batch_size: self.commit_batch_size, workers: self.insert_workers, memory: *memory }
+    }
+
+    // 命令行给了任意过滤参数时整体覆盖配置文件里的 filter
+    pub fn filter(&self, config: &FilterConfig) -> FilterConfig {
+        let from_args = FilterConfig {
+            include: self.include.clone(),
+            include_prefix: self.include_prefix.clone(),
+            include_regex: self.include_regex.clone(),
+            exclude: self.exclude.clone(),
+            exclude_prefix: self.exclude_prefix.clone(),
+            exclude_regex: self.exclude_regex.clone(),
+        };
+        if from_args.is_empty() { config.clone() } else { from_args }
+    }
+}
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Seconds between checks for a new snapshot
+    #[arg(long, env = "CBOE_DAEMON_INTERVAL", default_value_t = 900)]
+    pub interval_secs: u64,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Number of runs to show
+    #[arg(short = 'n', long, default_value_t = 10)]
+    pub limit: i64,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Only verify the markets loaded by this run id
+    #[arg(long)]
+    pub run: Option<i64>,
+    /// Only verify snapshots taken at or after this time
+    #[arg(long, value_parser = parse_timestamp)]
+    pub since: Option<NaiveDateTime>,
+    /// Number of market snapshots to verify, newest first
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: i64,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+}
+
+#[derive(Args)]
+pub struct PruneArgs {
+    /// Retention window in days (defaults to retention.days from the config)
+    #[arg(long)]
+    pub days: Option<u32>,
+    /// Delete old rows or move them to the archive table
+    #[arg(long, value_enum)]
+    pub mode: Option<PruneMode>,
+    /// Only report how many rows would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct PurgeExpiredArgs {
+    /// Keep contracts for this many days after expiration (defaults to expiry.keep_days from the config)
+    #[arg(long)]
+    pub days: Option<u32>,
+    /// Delete the rows or move them to the archive table
+    #[arg(long, value_enum)]
+    pub mode: Option<PruneMode>,
+    /// Only report how many rows would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    #[command(subcommand)]
+    pub command: QueryCommand,
+    /// Snapshot timestamp to query (defaults to the latest)
+    #[arg(long, global = true, value_parser = parse_timestamp)]
+    pub snapshot: Option<NaiveDateTime>,
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+}
+
+#[derive(Subcommand)]
+pub enum QueryCommand {
+    /// Option chain for one underlying
+    Chain {
+        symbol: String,
+        /// Only show this expiration
+        #[arg(long)]
+        expiry: Option<String>,
+    },
+    /// Contracts with the highest volume
+    TopVolume {
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Per-expiration volume summary for one underlying
+    SymbolSummary {
+        symbol: String,
+    },
+    /// Underlyings first seen, or last seen, within the past N days
+    Listings {
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Expiration calendar for one underlying (unexpired dates unless --all)
+    Expirations {
+        symbol: String,
+        #[arg(long)]
+        all: bool,
+    },
+    /// Fitted IV smiles for one underlying, or the interpolated IV at one strike and expiration
+    Iv {
+        symbol: String,
+        #[arg(long, requires = "expiry")]
+        strike: Option<f64>,
+        /// Expiration date (YYYY-MM-DD)
+        #[arg(long, requires = "strike")]
+        expiry: Option<NaiveDate>,
+    },
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Snapshot timestamp or "latest"
+    #[arg(long, value_parser = parse_snapshot, default_value = "latest")]
+    pub snapshot: SnapshotSelector,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// Only export these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub symbol: Vec<String>,
+    /// Only export this expiration
+    #[arg(long)]
+    pub expiry: Option<String>,
+    /// Output file (defaults to stdout)
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Gzip the output (implied when the output file ends with .gz)
+    #[arg(long)]
+    pub gzip: bool,
+    /// "file" writes one snapshot to --output; "hive" writes underlying=SYM/date=YYYY-MM-DD/part-N files under --output (a directory or s3://bucket/prefix)
+    #[arg(long, value_enum, default_value_t = ExportLayout::File)]
+    pub layout: ExportLayout,
+    /// Trading dates to export with --layout hive, every snapshot of each day (defaults to the day of --snapshot)
+    #[arg(long, value_delimiter = ',')]
+    pub date: Vec<NaiveDate>,
+    /// Start a new part file after this many rows with --layout hive
+    #[arg(long, default_value_t = 500_000)]
+    pub max_rows_per_file: usize,
+}
+
+#[derive(Args)]
+pub struct QuotesArgs {
+    /// Symbols to load (defaults to delayed_quotes.watchlist), indexes need a leading underscore like _SPX
+    pub symbols: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// CSV files to load
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Snapshot timestamp for every file (otherwise parsed from the file name via import.filename_format)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub snapshot: Option<NaiveDateTime>,
+    /// CBOE market the files were exported from (cone, opt, ctwo or exo), stored in the market column
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(MARKETS))]
+    pub market: Option<String>,
+    /// Commit inserts every N rows instead of one transaction per file
+    #[arg(long, env = "CBOE_COMMIT_BATCH_SIZE")]
+    pub commit_batch_size: Option<usize>,
+    /// Insert batches over this many parallel connections (Postgres only)
+    #[arg(long, env = "CBOE_INSERT_WORKERS", default_value_t = 1)]
+    pub insert_workers: usize,
+}
+
+impl ImportArgs {
+    pub fn insert_options(&self, memory: &MemoryConfig) -> InsertOptions {
+        InsertOptions { batch_size: self.commit_batch_size, workers: self.insert_workers, memory: *memory }
+    }
+}
+
+#[derive(Args)]
+pub struct ConsolidateArgs {
+    /// Trading date to consolidate (defaults to today in New York)
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+    /// Delete the day's intraday rows afterwards, keeping only its final snapshot
+    #[arg(long)]
+    pub prune_intraday: bool,
+}
+
+#[derive(Args)]
+pub struct RebuildExpirationsArgs {
+    /// Only snapshots on or after this date
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Base snapshot timestamp or "latest"
+    #[arg(long, value_parser = parse_snapshot)]
+    pub from: SnapshotSelector,
+    /// Snapshot to compare against
+    #[arg(long, value_parser = parse_snapshot, conflicts_with = "csv", required_unless_present = "csv")]
+    pub to: Option<SnapshotSelector>,
+    /// Local CSV to compare against, read with the [import] column mapping
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Only compare these symbols (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub symbol: Vec<String>,
+    /// Ignore numeric differences up to this amount
+    #[arg(long, default_value_t = 1e-9)]
+    pub tolerance: f64,
+    /// Write every difference to this CSV file
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, env = "CBOE_SERVE_BIND", default_value = "0.0.0.0:8080")]
+    pub bind: String,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Last trading date covered by the report (defaults to today in New York)
+    #[arg(long)]
+    pub end: Option<NaiveDate>,
+    /// Number of calendar days to cover, ending at --end
+    #[arg(long, default_value_t = 7)]
+    pub days: i64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+    /// Rows in the top underlyings and busiest expirations tables
+    #[arg(long, default_value_t = 10)]
+    pub top: i64,
+    /// Output file (defaults to stdout)
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Also send the report through the configured [notify] channels
+    #[arg(long)]
+    pub notify: bool,
+}
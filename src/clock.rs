@@ -0,0 +1,26 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::America;
+use std::sync::Arc;
+
+// 当前时间的来源，测试时用 testing::FixedClock 代替
+pub trait Clock: Send + Sync {
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    fn now_new_york(&self) -> NaiveDateTime {
+        self.now_utc().with_timezone(&America::New_York).naive_local()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.as_ref().now_utc()
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::alerts::AlertConfig;
+use crate::baseline::BaselineConfig;
+use crate::calendar::CalendarConfig;
+use crate::cfe::FuturesConfig;
+use crate::datasets::DatasetConfig;
+use crate::delayed::DelayedQuotesConfig;
+use crate::drift::FormatDriftConfig;
+use crate::events::EventsConfig;
+use crate::expiry::ExpiryConfig;
+use crate::filter::FilterConfig;
+use crate::healthcheck::HealthcheckConfig;
+use crate::http::HttpConfig;
+use crate::import::ImportConfig;
+use crate::maintenance::MaintenanceConfig;
+use crate::memory::MemoryConfig;
+use crate::mirror::MirrorConfig;
+use crate::notify::NotifyConfig;
+use crate::retention::RetentionConfig;
+use crate::serve::ApiConfig;
+use crate::sink::SinksConfig;
+use crate::surfaces::SurfaceConfig;
+use crate::timescale::TimescaleConfig;
+use crate::validate::ValidationConfig;
+
+pub const DEFAULT_CONFIG_PATH: &str = "cboe.toml";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub alerts: AlertConfig,
+    pub analytics: AnalyticsConfig,
+    pub api: ApiConfig,
+    pub baseline: BaselineConfig,
+    pub calendar: CalendarConfig,
+    pub database: DatabaseConfig,
+    pub datasets: Vec<DatasetConfig>,
+    pub delayed_quotes: DelayedQuotesConfig,
+    pub events: EventsConfig,
+    pub expiry: ExpiryConfig,
+    pub filter: FilterConfig,
+    pub format_drift: FormatDriftConfig,
+    pub futures: FuturesConfig,
+    pub healthcheck: HealthcheckConfig,
+    pub http: HttpConfig,
+    pub import: ImportConfig,
+    // 命名的 job：每个 [jobs.<name>] 按段覆盖这里的配置，run / daemon 依次运行
+    pub jobs: BTreeMap<String, toml::Table>,
+    pub maintenance: MaintenanceConfig,
+    pub memory: MemoryConfig,
+    pub mirror: MirrorConfig,
+    pub notify: NotifyConfig,
+    pub retention: RetentionConfig,
+    pub storage: StorageConfig,
+    pub sinks: SinksConfig,
+    pub surface: SurfaceConfig,
+    pub timescale: TimescaleConfig,
+    pub validation: ValidationConfig,
+    // 当前 job 的名称，运行日志按它区分
+    #[serde(skip)]
+    pub job: Option<String>,
+    // 合并 job 时使用的原始配置
+    #[serde(skip)]
+    raw: toml::Table,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub partitioned: bool,
+    pub latest_table: bool,
+    // 快照表所在的 schema 和表名，默认是 search_path 下的 t_options_cboe_snapshot
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    // 每行写入请求的 CSV 地址，默认关闭以节省空间
+    pub source_url: bool,
+    // 只写入和上一次快照相比有变化的合约（仅 Postgres），没变的沿用之前的行
+    pub change_detection: bool,
+    // 写入 volume_delta / matched_delta / routed_delta（仅 Postgres）
+    pub volume_deltas: bool,
+    // 每次加载后更新到期日日历 t_option_expirations / t_option_expiration_days（仅 Postgres）
+    pub expiration_calendar: bool,
+}
+
+// 汇总统计（report、query top-volume / symbol-summary、加载事件的 put/call ratio、波动率曲面）共用的选项
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    // 跳过调整合约（is_adjusted，需要 migration 000025）
+    pub exclude_adjusted: bool,
+}
+
+impl AnalyticsConfig {
+    // 拼进 WHERE 子句的条件
+    pub fn adjusted_filter(&self) -> &'static str {
+        if self.exclude_adjusted { "AND NOT is_adjusted" } else { "" }
+    }
+}
+
+// 连接池和会话参数；超时为 0 表示不限制，默认值与 sqlx 一致
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+    // 经过 pgbouncer transaction 模式时设为 0，不使用预编译语句缓存
+    pub statement_cache_capacity: usize,
+    pub application_name: Option<String>,
+    // 每个 Postgres 连接建立后执行 set_config，如 synchronous_commit = "off"
+    pub session: BTreeMap<String, String>,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+            max_lifetime_secs: 1800,
+            statement_cache_capacity: 100,
+            application_name: None,
+            session: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: Option<&str>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => DEFAULT_CONFIG_PATH,
+            None => return Ok(Config::default()),
+        };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path))?;
+        let raw: toml::Table = toml::from_str(&content).with_context(|| format!("Failed to parse config file {}", path))?;
+        let mut config: Config = raw.clone().try_into().with_context(|| format!("Failed to parse config file {}", path))?;
+        config.raw = raw;
+        Ok(config)
+    }
+
+    // job 里的表与顶层配置逐层合并，其它值（包括数组）整体替换；数据库连接所有 job 共用，不能覆盖
+    pub fn job(&self, name: &str) -> Result<Config> {
+        let overrides = self.jobs.get(name).ok_or_else(|| {
+            anyhow!("Unknown job {:?}, defined jobs: {}", name, self.jobs.keys().cloned().collect::<Vec<_>>().join(", "))
+        })?;
+        if let Some(key) = ["database", "jobs"].iter().find(|k| overrides.contains_key(**k)) {
+            bail!("[jobs.{}] cannot override [{}], it is shared by all jobs", name, key);
+        }
+        let mut raw = self.raw.clone();
+        raw.remove("jobs");
+        merge(&mut raw, overrides);
+        let mut config: Config = raw.clone().try_into().with_context(|| format!("Invalid config for job {}", name))?;
+        config.job = Some(name.to_string());
+        config.raw = raw;
+        Ok(config)
+    }
+
+    // 指定的 job，没有指定时为全部 job；没有定义 job 时为空
+    pub fn select_jobs(&self, names: &[String]) -> Result<Vec<Config>> {
+        if names.is_empty() {
+            self.jobs.keys().map(|name| self.job(name)).collect()
+        } else {
+            names.iter().map(|name| self.job(name)).collect()
+        }
+    }
+}
+
+fn merge(base: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => merge(existing, table),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
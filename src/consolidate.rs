@@ -0,0 +1,99 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveTime};
+use log::info;
+use sqlx::PgPool;
+
+use crate::changes;
+use crate::storage::SnapshotTable;
+
+// 把一天的盘中快照汇总成 t_options_cboe_eod，重复执行会覆盖当天结果
+pub async fn consolidate(pool: &PgPool, table: &SnapshotTable, date: NaiveDate, prune_intraday: bool) -> Result<u64> {
+    let day_start = date.and_time(NaiveTime::MIN);
+    let day_end = day_start + Duration::days(1);
+    let mut tx = pool.begin().await?;
+
+    // CBOE 的 volume 是当天累计值，取最大值而不是求和；开启变化检测时当天没变化的合约用沿用过来的那一行
+    let result = sqlx::query(&format!(r#"
+        WITH day AS ({rows}),
+        agg AS (
+            SELECT symbol, call_put, expiration, strike_price,
+                   max(volume) AS volume,
+                   max(last_price) FILTER (WHERE last_price > 0) AS high_price,
+                   min(last_price) FILTER (WHERE last_price > 0) AS low_price,
+                   count(*) AS snapshots,
+                   min(last_updated_time) AS first_snapshot,
+                   max(last_updated_time) AS last_snapshot
+            FROM day
+            GROUP BY symbol, call_put, expiration, strike_price
+        ),
+        last AS (
+            SELECT DISTINCT ON (symbol, call_put, expiration, strike_price)
+                   symbol, call_put, expiration, strike_price, bid_size, bid_price, ask_size, ask_price, last_price
+            FROM day
+            ORDER BY symbol, call_put, expiration, strike_price, last_updated_time DESC
+        )
+        INSERT INTO t_options_cboe_eod
+        (trade_date, symbol, call_put, expiration, strike_price, volume, bid_size, bid_price, ask_size, ask_price, last_price,
+         high_price, low_price, snapshots, first_snapshot, last_snapshot)
+        SELECT $3, a.symbol, a.call_put, a.expiration, a.strike_price, a.volume,
+               l.bid_size, l.bid_price, l.ask_size, l.ask_price, l.last_price,
+               a.high_price, a.low_price, a.snapshots, a.first_snapshot, a.last_snapshot
+        FROM agg a
+        JOIN last l USING (symbol, call_put, expiration, strike_price)
+        ON CONFLICT (trade_date, symbol, call_put, expiration, strike_price)
+        DO UPDATE SET
+            volume = EXCLUDED.volume,
+            bid_size = EXCLUDED.bid_size,
+            bid_price = EXCLUDED.bid_price,
+            ask_size = EXCLUDED.ask_size,
+            ask_price = EXCLUDED.ask_price,
+            last_price = EXCLUDED.last_price,
+            high_price = EXCLUDED.high_price,
+            low_price = EXCLUDED.low_price,
+            snapshots = EXCLUDED.snapshots,
+            first_snapshot = EXCLUDED.first_snapshot,
+            last_snapshot = EXCLUDED.last_snapshot
+    "#, rows = changes::daily_rows_sql(table)))
+        .bind(day_start)
+        .bind(day_end)
+        .bind(date)
+        .execute(&mut *tx)
+        .await?;
+    let contracts = result.rows_affected();
+    info!("Consolidated {} contracts for {}.", contracts, date);
+
+    // 保留当天最后一个快照，下次加载时仍能判断快照是否已更新；
+    // 开启变化检测时最后一个快照只有变化的行，改为保留每个合约当天最后一行，没变化的合约还要沿用它
+    if prune_intraday {
+        let superseded = if table.change_detection {
+            format!(r#"
+                EXISTS (
+                    SELECT 1 FROM {table} n
+                    WHERE n.market = t.market AND n.symbol = t.symbol AND n.call_put = t.call_put
+                      AND n.expiration = t.expiration AND n.strike_price = t.strike_price AND n.source = t.source
+                      AND n.last_updated_time > t.last_updated_time AND n.last_updated_time < $2
+                )
+            "#)
+        } else {
+            format!(r#"
+                last_updated_time < (
+                    SELECT max(last_updated_time) FROM {table}
+                    WHERE last_updated_time >= $1 AND last_updated_time < $2
+                )
+            "#)
+        };
+        let result = sqlx::query(&format!(r#"
+            DELETE FROM {table} t
+            WHERE last_updated_time >= $1 AND last_updated_time < $2
+              AND {superseded}
+        "#))
+            .bind(day_start)
+            .bind(day_end)
+            .execute(&mut *tx)
+            .await?;
+        info!("Pruned {} intraday rows for {}.", result.rows_affected(), date);
+    }
+
+    tx.commit().await?;
+    Ok(contracts)
+}
@@ -0,0 +1,465 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::America;
+use futures::channel::mpsc;
+use futures::{FutureExt, SinkExt, StreamExt};
+use log::{error, info, warn};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_util::sync::CancellationToken;
+
+use crate::cboe::OptionRecord;
+use crate::run_log::MarketProgress;
+use crate::shutdown::{self, Cancelled};
+use crate::storage::{InsertOptions, Provenance, SnapshotStore, SnapshotTable};
+
+#[derive(sqlx::FromRow, Serialize)]
+pub struct SnapshotRow {
+    pub symbol: String,
+    pub call_put: String,
+    pub expiration: String,
+    pub strike_price: f64,
+    pub volume: i64,
+    pub matched: i64,
+    pub routed: i64,
+    pub bid_size: i64,
+    pub bid_price: f64,
+    pub ask_size: i64,
+    pub ask_price: f64,
+    pub last_price: f64,
+    pub last_updated_time: NaiveDateTime,
+    pub days_to_expiration: Option<i32>,
+    pub expiration_class: Option<String>,
+    pub is_adjusted: bool,
+    pub source: String,
+    pub market: String,
+}
+
+pub const SNAPSHOT_COLUMNS: [&str; 20] = [
+    "symbol", "call_put", "expiration", "strike_price", "volume", "matched", "routed",
+    "bid_size", "bid_price", "ask_size", "ask_price", "last_price", "last_updated_time", "etl_in_dt",
+    "days_to_expiration", "expiration_class", "is_adjusted", "source", "market", "source_url",
+];
+
+pub async fn missing_snapshot_columns(pool: &PgPool, table: &SnapshotTable) -> Result<Vec<String>> {
+    let rows = sqlx::query(r#"
+        SELECT column_name::text FROM information_schema.columns
+        WHERE table_name = $1 AND table_schema = COALESCE($2, current_schema())
+    "#)
+        .bind(&table.name)
+        .bind(&table.schema)
+        .fetch_all(pool)
+        .await?;
+    let existing: Vec<String> = rows.iter().map(|r| r.try_get(0)).collect::<Result<_, _>>()?;
+    Ok(SNAPSHOT_COLUMNS.iter()
+        .filter(|c| !existing.iter().any(|e| e == *c))
+        .map(|c| c.to_string())
+        .collect())
+}
+
+// 只看完整快照，watchlist 的延迟报价不影响是否需要重新加载
+pub async fn get_max_updated_date(pool: &PgPool, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+    let row = sqlx::query(&format!("SELECT max(last_updated_time) FROM {table} WHERE source = 'symbol_data'"))
+        .fetch_one(pool)
+        .await?;
+    let max_time: Option<NaiveDateTime> = row.try_get(0)?;
+    Ok(max_time)
+}
+
+// records 已经整体在内存里，有界队列和 BatchSizer 只限制同时在切分、等待和写入的批次
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_records(
+    pool: &PgPool,
+    table: &SnapshotTable,
+    records: &[OptionRecord],
+    last_updated_time: NaiveDateTime,
+    provenance: &Provenance<'_>,
+    options: &InsertOptions,
+    progress: &MarketProgress,
+    token: &CancellationToken,
+) -> Result<u64> {
+    let utc_now = chrono::Utc::now();
+    let etl_in_dt = utc_now.with_timezone(&America::New_York).naive_local();
+    let workers = options.in_flight();
+    let sizer = options.batch_sizer(records.len().div_ceil(workers));
+    let counters = BatchCounters::default();
+
+    // 切分和写入之间是有界队列：写入跟不上时不再继续切分，批次大小按切分时的 RSS 决定
+    let (mut sender, receiver) = mpsc::channel(options.memory.queue_batches.max(1));
+    let produce = async {
+        for (batch, chunk) in sizer.chunks(records) {
+            let batch = insert_batch(pool, table, batch, chunk, last_updated_time, etl_in_dt, provenance, &counters, progress, token).boxed();
+            if sender.send(batch).await.is_err() {
+                break;
+            }
+        }
+        drop(sender);
+    };
+    // 每个 worker 从连接池里拿自己的连接；批次之间互不重叠，upsert 与提交顺序无关
+    let consume = async {
+        let mut results = receiver.buffer_unordered(workers);
+        let mut inserted = 0;
+        let mut errors = Vec::new();
+        while let Some(result) = results.next().await {
+            match result {
+                Ok(rows) => inserted += rows,
+                // 单连接时保持原来的行为，遇错即停
+                Err(e) if shutdown::is_cancelled(&e) || workers == 1 => return Err(e),
+                Err(e) => errors.push(e),
+            }
+        }
+        Ok((inserted, errors))
+    };
+    let ((), result) = futures::join!(produce, consume);
+    let (inserted, mut errors) = result?;
+    let batches = counters.batches.load(Ordering::Relaxed);
+    if !errors.is_empty() {
+        for e in &errors {
+            error!("Insert batch failed: {:#}", e);
+        }
+        let summary = format!("{} of {} batches failed", errors.len(), batches + errors.len() as u64);
+        return Err(errors.swap_remove(0).context(summary));
+    }
+
+    info!("Inserted {} records in {} batches with {} workers.", records.len(), batches, workers);
+    Ok(inserted)
+}
+
+#[derive(Default)]
+struct BatchCounters {
+    committed: AtomicU64,
+    batches: AtomicU64,
+}
+
+// 死锁（40P01）和序列化失败（40001）时整个事务已经回滚，重做该批次是安全的
+const WRITE_RETRIES: u32 = 3;
+const RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(200);
+
+fn is_retryable(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| match cause.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(db)) => matches!(db.code().as_deref(), Some("40001" | "40P01")),
+        _ => false,
+    })
+}
+
+fn retry_delay(attempt: u32) -> std::time::Duration {
+    RETRY_BACKOFF * 2u32.pow(attempt - 1)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_batch(
+    pool: &PgPool,
+    table: &SnapshotTable,
+    batch: Range<u64>,
+    chunk: &[OptionRecord],
+    last_updated_time: NaiveDateTime,
+    etl_in_dt: NaiveDateTime,
+    provenance: &Provenance<'_>,
+    counters: &BatchCounters,
+    progress: &MarketProgress,
+    token: &CancellationToken,
+) -> Result<u64> {
+    let mut attempt = 0;
+    loop {
+        let mut ticked = 0;
+        let result = try_insert_batch(pool, table, &batch, chunk, last_updated_time, etl_in_dt, provenance, counters, progress, token, &mut ticked).await;
+        match result {
+            Err(e) if attempt < WRITE_RETRIES && is_retryable(&e) => {
+                attempt += 1;
+                progress.rewind(ticked);
+                let delay = retry_delay(attempt);
+                warn!("Insert batch failed with {:#}, retrying in {:?} ({}/{}).", e, delay, attempt, WRITE_RETRIES);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn try_insert_batch(
+    pool: &PgPool,
+    table: &SnapshotTable,
+    batch: &Range<u64>,
+    chunk: &[OptionRecord],
+    last_updated_time: NaiveDateTime,
+    etl_in_dt: NaiveDateTime,
+    provenance: &Provenance<'_>,
+    counters: &BatchCounters,
+    progress: &MarketProgress,
+    token: &CancellationToken,
+    ticked: &mut u64,
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+    let insert = format!(r#"
+        INSERT INTO {table}
+        (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, market, source_url)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+        ON CONFLICT (symbol, call_put, expiration, strike_price, last_updated_time, market)
+        DO UPDATE SET
+            volume = EXCLUDED.volume,
+            matched = EXCLUDED.matched,
+            routed = EXCLUDED.routed,
+            bid_size = EXCLUDED.bid_size,
+            bid_price = EXCLUDED.bid_price,
+            ask_size = EXCLUDED.ask_size,
+            ask_price = EXCLUDED.ask_price,
+            last_price = EXCLUDED.last_price,
+            etl_in_dt = EXCLUDED.etl_in_dt,
+            days_to_expiration = EXCLUDED.days_to_expiration,
+            expiration_class = EXCLUDED.expiration_class,
+            is_adjusted = EXCLUDED.is_adjusted,
+            source_url = EXCLUDED.source_url
+    "#);
+    for rec in chunk {
+        // 收到停止信号时回滚当前批次，之前已提交的批次保留
+        if token.is_cancelled() {
+            tx.rollback().await?;
+            info!("Rolled back a batch after shutdown signal, {} rows committed.", counters.committed.load(Ordering::Relaxed));
+            return Err(Cancelled.into());
+        }
+        let class = rec.classify(last_updated_time.date());
+        let result = sqlx::query(&insert)
+            .bind(&rec.symbol)
+            .bind(&rec.call_put)
+            .bind(&rec.expiration)
+            .bind(rec.strike_price)
+            .bind(rec.volume)
+            .bind(rec.matched)
+            .bind(rec.routed)
+            .bind(rec.bid_size)
+            .bind(rec.bid_price)
+            .bind(rec.ask_size)
+            .bind(rec.ask_price)
+            .bind(rec.last_price)
+            .bind(last_updated_time)
+            .bind(etl_in_dt)
+            .bind(class.as_ref().map(|c| c.days_to_expiration))
+            .bind(class.as_ref().map(|c| c.expiration_class))
+            .bind(rec.is_adjusted())
+            .bind(provenance.market)
+            .bind(provenance.source_url)
+            .execute(&mut *tx)
+            .await?;
+        inserted += result.rows_affected();
+        progress.tick();
+        *ticked += 1;
+    }
+
+    let rows = chunk.len() as u64;
+    let committed = counters.committed.fetch_add(rows, Ordering::Relaxed) + rows;
+    let batches = counters.batches.fetch_add(1, Ordering::Relaxed) + 1;
+    // 进度和数据在同一个事务里提交，重试时可以从这里继续
+    let finished = async {
+        progress.record(&mut tx, committed, batches, batch).await?;
+        tx.commit().await?;
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = finished {
+        // 提交失败时撤回计数，避免重试后重复累加
+        counters.committed.fetch_sub(rows, Ordering::Relaxed);
+        counters.batches.fetch_sub(1, Ordering::Relaxed);
+        return Err(e);
+    }
+    progress.committed(batch.clone());
+    Ok(inserted)
+}
+
+pub async fn ensure_partition(pool: &PgPool, table: &SnapshotTable, date: NaiveDate) -> Result<()> {
+    let next = date + Duration::days(1);
+    let name = table.qualify(&format!("{}_p{}", table.name, date.format("%Y%m%d")), '"');
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ('{}') TO ('{}')",
+        name,
+        table,
+        date.format("%Y-%m-%d"),
+        next.format("%Y-%m-%d"),
+    );
+    sqlx::query(&sql).execute(pool).await?;
+    info!("Ensured partition {} exists.", name);
+    Ok(())
+}
+
+// DELETE 而不是 TRUNCATE：提交前读者仍能看到上一份数据，不会被锁住
+// 开启变化检测时从状态表还原完整快照，沿用的合约 carried_forward 为 true，last_updated_time 是它最近一次变化的时间
+// 主键包含市场，快照里不应有重复的行，真有重复时报错而不是悄悄丢掉
+pub async fn refresh_latest(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime) -> Result<u64> {
+    let source = if table.change_detection {
+        crate::changes::latest_snapshot_sql(table)
+    } else {
+        format!("SELECT *, false AS carried_forward FROM {table} WHERE last_updated_time = $1 AND source = 'symbol_data'")
+    };
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM t_options_cboe_latest").execute(&mut *tx).await?;
+    let result = sqlx::query(&format!(r#"
+        INSERT INTO t_options_cboe_latest
+        (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price,
+         last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, market, carried_forward)
+        SELECT symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price,
+               last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, market, carried_forward
+        FROM ({source}) AS snapshot
+    "#))
+        .bind(snapshot_time)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!("Refreshed t_options_cboe_latest with {} rows.", result.rows_affected());
+    Ok(result.rows_affected())
+}
+
+// 成交量是当天累计值，增量只和同一交易日（纽约日期）的上一行比较；变化检测下上一行是状态表里该合约最近一次变化的行
+pub async fn update_volume_deltas(pool: &PgPool, table: &SnapshotTable, market: &str, snapshot_time: NaiveDateTime) -> Result<i64> {
+    let day_start = snapshot_time.date().and_time(NaiveTime::MIN);
+    let same_contract = "p.market = t.market AND p.symbol = t.symbol AND p.call_put = t.call_put \
+        AND p.expiration = t.expiration AND p.strike_price = t.strike_price AND p.source = 'symbol_data'";
+    let mut tx = pool.begin().await?;
+    if table.change_detection {
+        sqlx::query(&format!(r#"
+            UPDATE {table} t SET
+                volume_delta = t.volume - p.volume,
+                matched_delta = t.matched - p.matched,
+                routed_delta = t.routed - p.routed
+            FROM t_cboe_contract_state s
+            JOIN {table} p
+              ON p.market = s.market AND p.symbol = s.symbol AND p.call_put = s.call_put
+             AND p.expiration = s.expiration AND p.strike_price = s.strike_price AND p.last_updated_time = s.changed_at
+            WHERE t.last_updated_time = $1 AND t.market = $2 AND t.source = 'symbol_data'
+              AND s.market = t.market AND s.symbol = t.symbol AND s.call_put = t.call_put
+              AND s.expiration = t.expiration AND s.strike_price = t.strike_price
+              AND s.changed_at >= $3 AND s.changed_at < $1 AND {same_contract}
+        "#))
+            .bind(snapshot_time)
+            .bind(market)
+            .bind(day_start)
+            .execute(&mut *tx)
+            .await?;
+    } else {
+        let previous: Option<NaiveDateTime> = sqlx::query_scalar(&format!(r#"
+            SELECT max(last_updated_time) FROM {table}
+            WHERE market = $2 AND source = 'symbol_data' AND last_updated_time < $1 AND last_updated_time >= $3
+        "#))
+            .bind(snapshot_time)
+            .bind(market)
+            .bind(day_start)
+            .fetch_one(&mut *tx)
+            .await?;
+        if let Some(previous) = previous {
+            sqlx::query(&format!(r#"
+                UPDATE {table} t SET
+                    volume_delta = t.volume - p.volume,
+                    matched_delta = t.matched - p.matched,
+                    routed_delta = t.routed - p.routed
+                FROM {table} p
+                WHERE t.last_updated_time = $1 AND t.market = $2 AND t.source = 'symbol_data'
+                  AND p.last_updated_time = $3 AND {same_contract}
+            "#))
+                .bind(snapshot_time)
+                .bind(market)
+                .bind(previous)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    // 当天第一次出现的合约：开盘以来的累计值就是增量
+    sqlx::query(&format!(r#"
+        UPDATE {table} SET volume_delta = volume, matched_delta = matched, routed_delta = routed
+        WHERE last_updated_time = $1 AND market = $2 AND source = 'symbol_data' AND volume_delta IS NULL
+    "#))
+        .bind(snapshot_time)
+        .bind(market)
+        .execute(&mut *tx)
+        .await?;
+    let total: i64 = sqlx::query_scalar(&format!(r#"
+        SELECT COALESCE(sum(volume_delta), 0)::BIGINT FROM {table}
+        WHERE last_updated_time = $1 AND market = $2 AND source = 'symbol_data'
+    "#))
+        .bind(snapshot_time)
+        .bind(market)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!("{}: {} contracts traded since the previous snapshot.", market, total);
+    Ok(total)
+}
+
+// 正常加载依赖 upsert 的冲突键避免重复，这里只用于 --deep-clean 全表清理历史遗留的重复行
+pub async fn clean_duplicate_data(pool: &PgPool, table: &SnapshotTable) -> Result<u64> {
+    let mut attempt = 0;
+    loop {
+        match try_clean_duplicate_data(pool, table).await {
+            Err(e) if attempt < WRITE_RETRIES && is_retryable(&e) => {
+                attempt += 1;
+                let delay = retry_delay(attempt);
+                warn!("Duplicate cleanup failed with {:#}, retrying in {:?} ({}/{}).", e, delay, attempt, WRITE_RETRIES);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn try_clean_duplicate_data(pool: &PgPool, table: &SnapshotTable) -> Result<u64> {
+    let result = sqlx::query(&format!(r#"
+        DELETE FROM {table} t
+        USING (
+            SELECT id,
+                   row_number() OVER (
+                       PARTITION BY symbol, call_put, expiration, strike_price, last_updated_time, market
+                       ORDER BY etl_in_dt DESC, id DESC
+                   ) AS rn
+            FROM {table}
+        ) d
+        WHERE t.id = d.id AND d.rn > 1
+    "#))
+        .execute(pool)
+        .await?;
+    info!("Removed {} duplicate rows.", result.rows_affected());
+    Ok(result.rows_affected())
+}
+
+pub struct PgStore {
+    pool: PgPool,
+}
+
+impl PgStore {
+    pub fn new(pool: PgPool) -> PgStore {
+        PgStore { pool }
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for PgStore {
+    fn backend(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+        get_max_updated_date(&self.pool, table).await
+    }
+
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64> {
+        insert_records(&self.pool, table, records, snapshot_time, provenance, options, progress, token).await
+    }
+
+    async fn clean_duplicate_data(&self, table: &SnapshotTable) -> Result<u64> {
+        clean_duplicate_data(&self.pool, table).await
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+}
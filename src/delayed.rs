@@ -0,0 +1,185 @@
+use anyhow::{Context, Result, bail};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+use crate::calendar;
+use crate::cboe::OptionRecord;
+use crate::cli::parse_timestamp;
+use crate::http::Fetcher;
+use crate::shutdown::Cancelled;
+use crate::storage::SnapshotTable;
+
+pub const SOURCE: &str = "delayed_quotes";
+const QUOTES_URL: &str = "https://cdn.cboe.com/api/global/delayed_quotes/options/{symbol}.json";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DelayedQuotesConfig {
+    // 指数需要加下划线前缀，例如 _SPX、_VIX
+    pub watchlist: Vec<String>,
+    pub url: String,
+}
+
+impl Default for DelayedQuotesConfig {
+    fn default() -> Self {
+        DelayedQuotesConfig { watchlist: Vec::new(), url: QUOTES_URL.to_string() }
+    }
+}
+
+#[derive(Deserialize)]
+struct QuotesResponse {
+    timestamp: Option<String>,
+    data: QuotesData,
+}
+
+#[derive(Deserialize)]
+struct QuotesData {
+    #[serde(default)]
+    options: Vec<Quote>,
+}
+
+// 接口里的数量有时是小数形式，统一按 f64 读取
+#[derive(Deserialize)]
+struct Quote {
+    option: String,
+    #[serde(default)]
+    bid: f64,
+    #[serde(default)]
+    bid_size: f64,
+    #[serde(default)]
+    ask: f64,
+    #[serde(default)]
+    ask_size: f64,
+    #[serde(default)]
+    volume: f64,
+    #[serde(default)]
+    last_trade_price: f64,
+}
+
+// OCC 代码：root + YYMMDD + C/P + 行权价 * 1000（8 位），root 长度不固定
+fn parse_occ(option: &str) -> Option<(String, String, String, f64)> {
+    let option = option.trim();
+    if option.len() < 16 || !option.is_ascii() {
+        return None;
+    }
+    let (root, rest) = option.split_at(option.len() - 15);
+    let expiration = NaiveDate::parse_from_str(&rest[..6], "%y%m%d").ok()?;
+    let call_put = &rest[6..7];
+    if call_put != "C" && call_put != "P" {
+        return None;
+    }
+    let strike: i64 = rest[7..].parse().ok()?;
+    Some((root.trim().to_string(), call_put.to_string(), expiration.format("%Y-%m-%d").to_string(), strike as f64 / 1000.0))
+}
+
+pub async fn fetch(fetcher: &Fetcher, config: &DelayedQuotesConfig, symbol: &str) -> Result<(NaiveDateTime, Vec<OptionRecord>)> {
+    let url = config.url.replace("{symbol}", symbol);
+    info!("Fetching delayed quotes from {}", url);
+    let body = fetcher.get_bytes(&url).await?;
+    let resp: QuotesResponse = serde_json::from_slice(&body).with_context(|| format!("Failed to parse delayed quotes for {}", symbol))?;
+    let quote_time = resp.timestamp.as_deref()
+        .and_then(|t| parse_timestamp(t).ok())
+        .unwrap_or_else(calendar::now_new_york);
+
+    let mut records = Vec::with_capacity(resp.data.options.len());
+    for quote in resp.data.options {
+        let Some((root, call_put, expiration, strike_price)) = parse_occ(&quote.option) else {
+            warn!("Skipping unparseable option code {}", quote.option);
+            continue;
+        };
+        records.push(OptionRecord {
+            symbol: root,
+            call_put,
+            expiration,
+            strike_price,
+            volume: quote.volume as i64,
+            matched: 0,
+            routed: 0,
+            bid_size: quote.bid_size as i64,
+            bid_price: quote.bid,
+            ask_size: quote.ask_size as i64,
+            ask_price: quote.ask,
+            last_price: quote.last_trade_price,
+        });
+    }
+    Ok((quote_time, records))
+}
+
+pub async fn insert_records(pool: &PgPool, table: &SnapshotTable, records: &[OptionRecord], quote_time: NaiveDateTime, token: &CancellationToken) -> Result<u64> {
+    let etl_in_dt = calendar::now_new_york();
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+    // 延迟报价是各交易所合并后的结果，不属于某个市场，market 留空
+    let insert = format!(r#"
+        INSERT INTO {table}
+        (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, source)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+        ON CONFLICT (symbol, call_put, expiration, strike_price, last_updated_time, market)
+        DO UPDATE SET
+            volume = EXCLUDED.volume,
+            bid_size = EXCLUDED.bid_size,
+            bid_price = EXCLUDED.bid_price,
+            ask_size = EXCLUDED.ask_size,
+            ask_price = EXCLUDED.ask_price,
+            last_price = EXCLUDED.last_price,
+            etl_in_dt = EXCLUDED.etl_in_dt,
+            source = EXCLUDED.source
+    "#);
+    for rec in records {
+        if token.is_cancelled() {
+            tx.rollback().await?;
+            return Err(Cancelled.into());
+        }
+        let class = rec.classify(quote_time.date());
+        let result = sqlx::query(&insert)
+            .bind(&rec.symbol)
+            .bind(&rec.call_put)
+            .bind(&rec.expiration)
+            .bind(rec.strike_price)
+            .bind(rec.volume)
+            .bind(rec.matched)
+            .bind(rec.routed)
+            .bind(rec.bid_size)
+            .bind(rec.bid_price)
+            .bind(rec.ask_size)
+            .bind(rec.ask_price)
+            .bind(rec.last_price)
+            .bind(quote_time)
+            .bind(etl_in_dt)
+            .bind(class.as_ref().map(|c| c.days_to_expiration))
+            .bind(class.as_ref().map(|c| c.expiration_class))
+            .bind(rec.is_adjusted())
+            .bind(SOURCE)
+            .execute(&mut *tx)
+            .await?;
+        inserted += result.rows_affected();
+    }
+    tx.commit().await?;
+    Ok(inserted)
+}
+
+// 单个标的失败只记录日志，继续处理 watchlist 里的其它标的
+pub async fn run(pool: &PgPool, table: &SnapshotTable, fetcher: &Fetcher, config: &DelayedQuotesConfig, symbols: &[String], token: &CancellationToken) -> Result<()> {
+    let symbols = if symbols.is_empty() { &config.watchlist } else { symbols };
+    if symbols.is_empty() {
+        bail!("No symbols: pass them on the command line or set delayed_quotes.watchlist");
+    }
+    for symbol in symbols {
+        if token.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        let (quote_time, records) = match token.run_until_cancelled(fetch(fetcher, config, symbol)).await.ok_or(Cancelled)? {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Failed to fetch delayed quotes for {}: {:#}", symbol, e);
+                continue;
+            }
+        };
+        let inserted = insert_records(pool, table, &records, quote_time, token).await?;
+        info!("Loaded {} delayed quotes for {} at {}.", inserted, symbol, quote_time);
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use log::info;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+
+use crate::cboe::{ContractKey, OptionRecord};
+use crate::cli::DiffArgs;
+use crate::config::Config;
+use crate::filter::{FilterConfig, SymbolFilter};
+use crate::import::FileSource;
+use crate::output::{Format, Table};
+use crate::source::SnapshotSource;
+use crate::storage::SnapshotTable;
+
+const FIELDS: [&str; 8] = ["volume", "matched", "routed", "bid_size", "bid_price", "ask_size", "ask_price", "last_price"];
+
+fn field(rec: &OptionRecord, name: &str) -> f64 {
+    match name {
+        "volume" => rec.volume as f64,
+        "matched" => rec.matched as f64,
+        "routed" => rec.routed as f64,
+        "bid_size" => rec.bid_size as f64,
+        "bid_price" => rec.bid_price,
+        "ask_size" => rec.ask_size as f64,
+        "ask_price" => rec.ask_price,
+        "last_price" => rec.last_price,
+        _ => unreachable!("unknown field {}", name),
+    }
+}
+
+struct Change {
+    key: ContractKey,
+    strike_price: f64,
+    kind: &'static str,
+    field: Option<&'static str>,
+    old: Option<f64>,
+    new: Option<f64>,
+}
+
+// source 来自 SnapshotSelector::resolve，开启变化检测时包括沿用的合约
+async fn load_snapshot(pool: &PgPool, snapshot_time: NaiveDateTime, source: &str, filter: &SymbolFilter) -> Result<BTreeMap<ContractKey, OptionRecord>> {
+    let rows = sqlx::query(&format!(r#"
+        SELECT symbol, call_put, expiration, strike_price, volume, matched, routed,
+               bid_size, bid_price, ask_size, ask_price, last_price
+        FROM ({source}) AS snapshot
+    "#))
+        .bind(snapshot_time)
+        .fetch_all(pool)
+        .await?;
+    let mut records = BTreeMap::new();
+    for row in rows {
+        let rec = OptionRecord {
+            symbol: row.try_get("symbol")?,
+            call_put: row.try_get("call_put")?,
+            expiration: row.try_get("expiration")?,
+            strike_price: row.try_get("strike_price")?,
+            volume: row.try_get("volume")?,
+            matched: row.try_get("matched")?,
+            routed: row.try_get("routed")?,
+            bid_size: row.try_get("bid_size")?,
+            bid_price: row.try_get("bid_price")?,
+            ask_size: row.try_get("ask_size")?,
+            ask_price: row.try_get("ask_price")?,
+            last_price: row.try_get("last_price")?,
+        };
+        if filter.matches(&rec.symbol) {
+            records.insert(rec.key(), rec);
+        }
+    }
+    Ok(records)
+}
+
+fn compare(from: &BTreeMap<ContractKey, OptionRecord>, to: &BTreeMap<ContractKey, OptionRecord>, tolerance: f64) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (key, old) in from {
+        let Some(new) = to.get(key) else {
+            changes.push(Change { key: key.clone(), strike_price: old.strike_price, kind: "removed", field: None, old: None, new: None });
+            continue;
+        };
+        for name in FIELDS {
+            let (a, b) = (field(old, name), field(new, name));
+            if (a - b).abs() > tolerance {
+                changes.push(Change { key: key.clone(), strike_price: old.strike_price, kind: "changed", field: Some(name), old: Some(a), new: Some(b) });
+            }
+        }
+    }
+    for (key, new) in to {
+        if !from.contains_key(key) {
+            changes.push(Change { key: key.clone(), strike_price: new.strike_price, kind: "added", field: None, old: None, new: None });
+        }
+    }
+    changes
+}
+
+fn write_changes(path: &str, changes: &[Change]) -> Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["symbol", "call_put", "expiration", "strike_price", "change", "field", "old", "new"])?;
+    for c in changes {
+        wtr.write_record([
+            c.key.symbol.clone(),
+            c.key.call_put.clone(),
+            c.key.expiration.clone(),
+            c.strike_price.to_string(),
+            c.kind.to_string(),
+            c.field.unwrap_or_default().to_string(),
+            c.old.map(|v| v.to_string()).unwrap_or_default(),
+            c.new.map(|v| v.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    wtr.flush()?;
+    info!("Wrote {} differences to {}.", changes.len(), path);
+    Ok(())
+}
+
+// 比较两次快照，或者一次快照和本地 CSV（按 [import] 的列映射读取），用于核对 CBOE 重发的数据和回填结果
+pub async fn run(pool: &PgPool, table: &SnapshotTable, config: &Config, args: &DiffArgs) -> Result<()> {
+    let filter = SymbolFilter::new(&FilterConfig { include: args.symbol.clone(), ..Default::default() })?;
+    let (from_time, from_source) = args.from.resolve(pool, table).await?;
+    let from = load_snapshot(pool, from_time, &from_source, &filter).await?;
+
+    let (to_label, to) = match (&args.to, &args.csv) {
+        (_, Some(path)) => {
+            let source = FileSource::new(path, &config.import, Some(from_time))?;
+            let market = source.markets().remove(0);
+            let content = source.fetch(&market, &filter).await?;
+            let records = content.records.into_iter().map(|r| (r.key(), r)).collect();
+            (path.display().to_string(), records)
+        }
+        (Some(to), None) => {
+            let (to_time, to_source) = to.resolve(pool, table).await?;
+            (to_time.format("%Y-%m-%d %H:%M:%S").to_string(), load_snapshot(pool, to_time, &to_source, &filter).await?)
+        }
+        (None, None) => bail!("Pass --to or --csv"),
+    };
+    if from.is_empty() {
+        bail!("Snapshot {} has no rows", from_time);
+    }
+
+    let changes = compare(&from, &to, args.tolerance);
+    println!("{} ({} contracts) -> {} ({} contracts)", from_time.format("%Y-%m-%d %H:%M:%S"), from.len(), to_label, to.len());
+
+    let count = |kind: &str| changes.iter().filter(|c| c.kind == kind).count();
+    let mut changed_contracts: Vec<&ContractKey> = changes.iter().filter(|c| c.kind == "changed").map(|c| &c.key).collect();
+    changed_contracts.dedup();
+    let mut table = Table::new(vec!["change", "contracts"]);
+    table.push(vec![json!("added"), json!(count("added"))]);
+    table.push(vec![json!("removed"), json!(count("removed"))]);
+    table.push(vec![json!("changed"), json!(changed_contracts.len())]);
+    table.push(vec![json!("unchanged"), json!(from.len() - count("removed") - changed_contracts.len())]);
+    table.print(Format::Table)?;
+
+    if !changed_contracts.is_empty() {
+        let mut fields = Table::new(vec!["field", "contracts"]);
+        for name in FIELDS {
+            let n = changes.iter().filter(|c| c.field == Some(name)).count();
+            if n > 0 {
+                fields.push(vec![json!(name), json!(n)]);
+            }
+        }
+        fields.print(Format::Table)?;
+    }
+
+    if let Some(path) = &args.output {
+        write_changes(path, &changes)?;
+    }
+    Ok(())
+}
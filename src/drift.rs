@@ -0,0 +1,144 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use chrono_tz::America;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+use crate::notify::Notification;
+use crate::run_log::RunLog;
+
+// 页面签名在 t_cboe_format_log 里的 source
+pub const PAGE: &str = "page";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FormatDriftConfig {
+    pub enabled: bool,
+    // 发现变化时通过 [notify] 中配置的渠道发送，不受 notify.on 限制
+    pub notify: bool,
+}
+
+impl Default for FormatDriftConfig {
+    fn default() -> Self {
+        FormatDriftConfig { enabled: true, notify: true }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct FormatDrift {
+    // "page" 或市场
+    pub source: String,
+    pub previous_run: i64,
+    pub previous: Vec<String>,
+    pub observed: Vec<String>,
+    pub changes: Vec<String>,
+}
+
+// 记录本次运行观察到的页面签名和 CSV 表头，和同一个 source 上一次的记录比较（仅 Postgres）
+pub struct DriftDetector<'a> {
+    pool: &'a PgPool,
+    run_id: i64,
+}
+
+impl<'a> DriftDetector<'a> {
+    pub fn new(pool: &'a PgPool, run_id: i64) -> DriftDetector<'a> {
+        DriftDetector { pool, run_id }
+    }
+
+    // 没有上一次记录（第一次运行）或没有变化时返回 None
+    pub async fn observe(&self, source: &str, observed: &[String]) -> Result<Option<FormatDrift>> {
+        let previous: Option<(i64, Vec<String>)> = sqlx::query_as(
+            "SELECT run_id, observed FROM t_cboe_format_log WHERE source = $1 AND run_id < $2 ORDER BY run_id DESC LIMIT 1",
+        )
+            .bind(source)
+            .bind(self.run_id)
+            .fetch_optional(self.pool)
+            .await?;
+        sqlx::query(r#"
+            INSERT INTO t_cboe_format_log (run_id, source, observed, observed_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (run_id, source) DO UPDATE SET observed = EXCLUDED.observed, observed_at = EXCLUDED.observed_at
+        "#)
+            .bind(self.run_id)
+            .bind(source)
+            .bind(observed)
+            .bind(now())
+            .execute(self.pool)
+            .await?;
+
+        let Some((previous_run, previous)) = previous else {
+            info!("Recorded the {} format for drift detection.", source);
+            return Ok(None);
+        };
+        let changes = if source == PAGE { describe_page(&previous, observed) } else { describe_headers(&previous, observed) };
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        warn!("!!! CBOE FORMAT DRIFT in {} since run {} !!!", source, previous_run);
+        for change in &changes {
+            warn!("!!!   {}", change);
+        }
+        Ok(Some(FormatDrift { source: source.to_string(), previous_run, previous, observed: observed.to_vec(), changes }))
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().with_timezone(&America::New_York).naive_local()
+}
+
+fn describe_page(previous: &[String], observed: &[String]) -> Vec<String> {
+    if previous == observed {
+        return Vec::new();
+    }
+    vec![format!("\"last updated\" wording changed from \"{}\" to \"{}\"", previous.join(" "), observed.join(" "))]
+}
+
+// 同一位置一删一增算作改名，列集合相同但顺序不同算作重新排序
+fn describe_headers(previous: &[String], observed: &[String]) -> Vec<String> {
+    if previous == observed {
+        return Vec::new();
+    }
+    let before: HashSet<&str> = previous.iter().map(String::as_str).collect();
+    let after: HashSet<&str> = observed.iter().map(String::as_str).collect();
+    let mut removed: Vec<(usize, &str)> = previous.iter().enumerate().filter(|(_, h)| !after.contains(h.as_str())).map(|(i, h)| (i, h.as_str())).collect();
+    let mut added: Vec<(usize, &str)> = observed.iter().enumerate().filter(|(_, h)| !before.contains(h.as_str())).map(|(i, h)| (i, h.as_str())).collect();
+
+    let mut changes = Vec::new();
+    removed.retain(|&(i, old)| match added.iter().position(|&(j, _)| j == i) {
+        Some(k) => {
+            changes.push(format!("column {} renamed \"{}\" -> \"{}\"", i + 1, old, added.remove(k).1));
+            false
+        }
+        None => true,
+    });
+    for (i, name) in removed {
+        changes.push(format!("removed column {} \"{}\"", i + 1, name));
+    }
+    for (i, name) in added {
+        changes.push(format!("added column {} \"{}\"", i + 1, name));
+    }
+    if changes.is_empty() {
+        changes.push(format!("columns reordered: {} (was {})", observed.join(", "), previous.join(", ")));
+    }
+    changes
+}
+
+pub fn notification(run: &RunLog) -> Notification {
+    let title = format!("CBOE format drift: {} changed (run {})", run.format_drift.iter().map(|d| d.source.as_str()).collect::<Vec<_>>().join(", "), run.id());
+    let mut text = title.clone();
+    for drift in &run.format_drift {
+        text.push_str(&format!("\n{} (compared with run {}):", drift.source, drift.previous_run));
+        for change in &drift.changes {
+            text.push_str(&format!("\n  {}", change));
+        }
+    }
+    let json = serde_json::json!({
+        "title": title,
+        "run_id": run.id(),
+        "job": run.job,
+        "snapshot_time": run.snapshot_time,
+        "format_drift": run.format_drift,
+    });
+    Notification { title, text, json, html: None }
+}
@@ -0,0 +1,182 @@
+use anyhow::Result;
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use chrono::NaiveDateTime;
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sqlx::postgres::PgListener;
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::changes;
+use crate::config::AnalyticsConfig;
+use crate::run_log::{Outcome, RunLog};
+use crate::storage::SnapshotTable;
+
+// 每次加载结束时通过 Postgres NOTIFY 发出一条事件，serve 进程 LISTEN 后转发给 WebSocket 客户端
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    pub enabled: bool,
+    pub channel: String,
+    // 事件里附带这些标的的 put/call ratio
+    pub symbols: Vec<String>,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig { enabled: false, channel: "cboe_load_events".to_string(), symbols: vec!["SPX".to_string()] }
+    }
+}
+
+// 开启变化检测时快照表只有变化的合约，从状态表还原完整快照再算
+async fn put_call_ratios(
+    pool: &PgPool,
+    table: &SnapshotTable,
+    analytics: &AnalyticsConfig,
+    snapshot_time: NaiveDateTime,
+    symbols: &[String],
+) -> Result<BTreeMap<String, Option<f64>>> {
+    let source = if table.change_detection {
+        changes::latest_snapshot_sql(table)
+    } else {
+        format!("SELECT * FROM {table} WHERE last_updated_time = $1 AND source = 'symbol_data'")
+    };
+    let rows = sqlx::query(&format!(r#"
+        SELECT symbol,
+               COALESCE(sum(volume) FILTER (WHERE upper(left(call_put, 1)) = 'C'), 0)::BIGINT AS call_volume,
+               COALESCE(sum(volume) FILTER (WHERE upper(left(call_put, 1)) = 'P'), 0)::BIGINT AS put_volume
+        FROM ({source}) AS snapshot
+        WHERE symbol = ANY($2) {adjusted}
+        GROUP BY symbol
+    "#, adjusted = analytics.adjusted_filter()))
+        .bind(snapshot_time)
+        .bind(symbols.iter().map(|s| s.to_uppercase()).collect::<Vec<_>>())
+        .fetch_all(pool)
+        .await?;
+    let mut ratios = BTreeMap::new();
+    for row in rows {
+        let calls: i64 = row.try_get("call_volume")?;
+        let puts: i64 = row.try_get("put_volume")?;
+        let ratio = (calls > 0).then(|| (puts as f64 / calls as f64 * 100.0).round() / 100.0);
+        ratios.insert(row.try_get("symbol")?, ratio);
+    }
+    Ok(ratios)
+}
+
+fn rows_label(rows: u64) -> String {
+    match rows {
+        0..1_000 => rows.to_string(),
+        1_000..1_000_000 => format!("{:.0}k", rows as f64 / 1e3),
+        _ => format!("{:.1}M", rows as f64 / 1e6),
+    }
+}
+
+async fn event(pool: &PgPool, table: &SnapshotTable, config: &EventsConfig, analytics: &AnalyticsConfig, run: &RunLog, outcome: Outcome) -> Result<Value> {
+    let snapshot = run.snapshot_time.map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "-".to_string());
+    let markets: Vec<&str> = run.markets.iter().map(|m| m.market.as_str()).collect();
+    if outcome == Outcome::Failed {
+        let message = format!("snapshot {} failed: {}", snapshot, run.error_message.as_deref().unwrap_or("unknown error"));
+        return Ok(json!({
+            "event": "load_failed",
+            "run_id": run.id(),
+            "snapshot_time": run.snapshot_time,
+            "markets": markets,
+            "error": run.error_message,
+            "message": message,
+        }));
+    }
+    let ratios = match run.snapshot_time {
+        Some(t) if !config.symbols.is_empty() => put_call_ratios(pool, table, analytics, t, &config.symbols).await?,
+        _ => BTreeMap::new(),
+    };
+    let mut message = format!("snapshot {} loaded, {} rows", snapshot, rows_label(run.rows_inserted()));
+    for (symbol, ratio) in &ratios {
+        if let Some(ratio) = ratio {
+            message.push_str(&format!(", {} PCR {:.2}", symbol, ratio));
+        }
+    }
+    Ok(json!({
+        "event": "snapshot_loaded",
+        "run_id": run.id(),
+        "snapshot_time": run.snapshot_time,
+        "rows_inserted": run.rows_inserted(),
+        "markets": markets,
+        "duration_secs": run.duration_secs(),
+        "put_call": ratios,
+        "message": message,
+    }))
+}
+
+// 只发布成功和失败的加载，快照未更新（skipped）和中断不发；发送失败只记录日志
+pub async fn publish(pool: Option<&PgPool>, table: &SnapshotTable, config: &EventsConfig, analytics: &AnalyticsConfig, run: &RunLog, outcome: Outcome) {
+    let Some(pool) = pool.filter(|_| config.enabled) else {
+        return;
+    };
+    if !matches!(outcome, Outcome::Success | Outcome::Failed) {
+        return;
+    }
+    let result = async {
+        let payload = event(pool, table, config, analytics, run, outcome).await?.to_string();
+        sqlx::query("SELECT pg_notify($1, $2)").bind(&config.channel).bind(&payload).execute(pool).await?;
+        anyhow::Ok(payload)
+    }
+    .await;
+    match result {
+        Ok(payload) => info!("Published load event: {}", payload),
+        Err(e) => warn!("Failed to publish load event: {:#}", e),
+    }
+}
+
+// 一个 LISTEN 连接接收所有事件，再广播给每个 WebSocket 客户端
+pub async fn relay(pool: &PgPool, channel: &str) -> Result<broadcast::Sender<String>> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(channel).await?;
+    let (sender, _) = broadcast::channel(64);
+    let relay = sender.clone();
+    info!("Listening for load events on channel {}.", channel);
+    tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => {
+                    let _ = relay.send(notification.payload().to_string());
+                }
+                // PgListener 会自动重连，断开期间的事件会丢失
+                Err(e) => {
+                    error!("Load event listener failed: {:#}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                }
+            }
+        }
+    });
+    Ok(sender)
+}
+
+pub async fn websocket(State(events): State<broadcast::Sender<String>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| forward(socket, events.subscribe()))
+}
+
+async fn forward(mut socket: WebSocket, mut events: broadcast::Receiver<String>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(payload) => {
+                    if socket.send(Message::Text(payload.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(n)) => warn!("WebSocket client fell behind, dropped {} events.", n),
+                Err(RecvError::Closed) => return,
+            },
+            // 客户端发来的消息只用于检测断开
+            incoming = socket.recv() => {
+                if !matches!(incoming, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
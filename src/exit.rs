@@ -0,0 +1,65 @@
+use anyhow::Result;
+use std::fmt;
+use std::process::ExitCode;
+
+use crate::shutdown::{self, EXIT_CANCELLED};
+
+// 进程退出码，供 cron / 调度器按结果分支；其它未分类的错误为 1
+pub const LOADED: u8 = 0;
+pub const SKIPPED: u8 = 10;
+pub const PARTIAL: u8 = 20;
+pub const FETCH_FAILED: u8 = 30;
+pub const DB_FAILED: u8 = 40;
+
+// 部分市场已写入后失败
+#[derive(Debug)]
+pub struct PartialLoad {
+    pub loaded: usize,
+}
+
+impl fmt::Display for PartialLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Partial load: {} markets were written before the failure", self.loaded)
+    }
+}
+
+impl std::error::Error for PartialLoad {}
+
+// 抓取或解析 CBOE 数据失败
+#[derive(Debug)]
+pub struct FetchFailed;
+
+impl fmt::Display for FetchFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to fetch snapshot data")
+    }
+}
+
+impl std::error::Error for FetchFailed {}
+
+pub fn code_for(e: &anyhow::Error) -> Option<u8> {
+    if shutdown::is_cancelled(e) {
+        Some(EXIT_CANCELLED)
+    } else if e.downcast_ref::<PartialLoad>().is_some() {
+        Some(PARTIAL)
+    } else if e.downcast_ref::<FetchFailed>().is_some() {
+        Some(FETCH_FAILED)
+    } else if e.chain().any(|c| c.is::<sqlx::Error>()) {
+        Some(DB_FAILED)
+    } else {
+        None
+    }
+}
+
+// 已分类的错误自己打印并返回对应退出码，其余交给 main 按默认方式（退出码 1）处理
+pub fn report(e: anyhow::Error) -> Result<ExitCode> {
+    match code_for(&e) {
+        Some(code) => {
+            if code != EXIT_CANCELLED {
+                eprintln!("Error: {:?}", e);
+            }
+            Ok(ExitCode::from(code))
+        }
+        None => Err(e),
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use cboe_snapshot::classify::expiration_cycle;
+use chrono::{NaiveDate, NaiveDateTime};
+use log::info;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+
+use crate::calendar;
+use crate::cli::RebuildExpirationsArgs;
+use crate::output::Table;
+use crate::storage::SnapshotTable;
+
+// 一次快照里出现的合约：加载时开启变化检测从状态表取本次出现的合约，重建历史时用 cboe_snapshot_at 还原
+enum Source {
+    Table,
+    State,
+    SnapshotAt,
+}
+
+impl Source {
+    fn sql(&self, table: &SnapshotTable) -> String {
+        match self {
+            Source::Table => format!("SELECT * FROM {table} WHERE last_updated_time = $1 AND source = 'symbol_data'"),
+            Source::State => "SELECT * FROM t_cboe_contract_state WHERE seen_at = $1".to_string(),
+            Source::SnapshotAt => "SELECT * FROM cboe_snapshot_at($1)".to_string(),
+        }
+    }
+}
+
+// 到期日周期在 Rust 里按 classify::expiration_cycle 判断（需要交易所假日），以数组传给 SQL
+async fn record(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime, source: Source) -> Result<u64> {
+    let source = source.sql(table);
+    let dates: Vec<Option<NaiveDate>> = sqlx::query_scalar(&format!("SELECT DISTINCT cboe_expiration_date(expiration) FROM ({source}) AS s"))
+        .bind(snapshot_time)
+        .fetch_all(pool)
+        .await?;
+    let dates: Vec<NaiveDate> = dates.into_iter().flatten().collect();
+    let cycles: Vec<&str> = dates.iter().map(|d| expiration_cycle(*d)).collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(r#"
+        INSERT INTO t_option_expirations AS e (symbol, expiration_date, expiration_class, first_seen, last_seen)
+        SELECT DISTINCT s.symbol, c.expiration_date, c.expiration_class, $1::TIMESTAMP, $1::TIMESTAMP
+        FROM ({source}) AS s
+        JOIN unnest($2::DATE[], $3::TEXT[]) AS c (expiration_date, expiration_class)
+          ON c.expiration_date = cboe_expiration_date(s.expiration)
+        ON CONFLICT (symbol, expiration_date) DO UPDATE SET
+            first_seen = LEAST(e.first_seen, EXCLUDED.first_seen),
+            last_seen = GREATEST(e.last_seen, EXCLUDED.last_seen)
+    "#))
+        .bind(snapshot_time)
+        .bind(&dates)
+        .bind(&cycles)
+        .execute(&mut *tx)
+        .await?;
+    let rows = sqlx::query(&format!(r#"
+        INSERT INTO t_option_expiration_days (snapshot_time, symbol, expiration_date, days_to_expiration, contracts)
+        SELECT $1, symbol, expiration_date, expiration_date - $1::DATE, count(DISTINCT (call_put, strike_price))::INTEGER
+        FROM (SELECT symbol, call_put, strike_price, cboe_expiration_date(expiration) AS expiration_date FROM ({source}) AS s) AS s
+        WHERE expiration_date IS NOT NULL
+        GROUP BY symbol, expiration_date
+        ON CONFLICT (snapshot_time, symbol, expiration_date) DO UPDATE SET
+            days_to_expiration = EXCLUDED.days_to_expiration,
+            contracts = EXCLUDED.contracts
+    "#))
+        .bind(snapshot_time)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(rows)
+}
+
+pub async fn update(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime) -> Result<u64> {
+    let rows = record(pool, table, snapshot_time, if table.change_detection { Source::State } else { Source::Table }).await?;
+    info!("Recorded {} symbol expirations in t_option_expiration_days.", rows);
+    Ok(rows)
+}
+
+// 按快照表里的每个完整快照重新生成；开启变化检测时每个时间点用 cboe_snapshot_at 还原
+pub async fn rebuild(pool: &PgPool, table: &SnapshotTable, args: &RebuildExpirationsArgs) -> Result<()> {
+    let times: Vec<NaiveDateTime> = sqlx::query_scalar(&format!(r#"
+        SELECT DISTINCT last_updated_time FROM {table}
+        WHERE source = 'symbol_data' AND ($1::DATE IS NULL OR last_updated_time >= $1)
+        ORDER BY last_updated_time
+    "#))
+        .bind(args.since)
+        .fetch_all(pool)
+        .await?;
+    let mut rows = 0;
+    for time in &times {
+        rows += record(pool, table, *time, if table.change_detection { Source::SnapshotAt } else { Source::Table }).await?;
+    }
+    info!("Rebuilt the expiration calendar from {} snapshots, {} symbol expirations.", times.len(), rows);
+    Ok(())
+}
+
+// 一个标的还没到期的到期日（all 时包括已到期的），剩余天数按纽约今天计算
+pub async fn list(pool: &PgPool, symbol: &str, all: bool) -> Result<Table> {
+    let today = calendar::now_new_york().date();
+    let rows = sqlx::query(r#"
+        SELECT expiration_date, expiration_class, first_seen, last_seen
+        FROM t_option_expirations
+        WHERE symbol = $1 AND ($3 OR expiration_date >= $2)
+        ORDER BY expiration_date
+    "#)
+        .bind(symbol.trim().to_uppercase())
+        .bind(today)
+        .bind(all)
+        .fetch_all(pool)
+        .await?;
+    let mut table = Table::new(vec!["expiration", "class", "days", "first_seen", "last_seen"]);
+    for row in rows {
+        let expiration: NaiveDate = row.try_get("expiration_date")?;
+        table.push(vec![
+            json!(expiration.to_string()),
+            json!(row.try_get::<String, _>("expiration_class")?),
+            json!((expiration - today).num_days()),
+            json!(row.try_get::<NaiveDateTime, _>("first_seen")?.format("%Y-%m-%d %H:%M:%S").to_string()),
+            json!(row.try_get::<NaiveDateTime, _>("last_seen")?.format("%Y-%m-%d %H:%M:%S").to_string()),
+        ]);
+    }
+    Ok(table)
+}
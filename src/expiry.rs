@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use log::info;
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+
+use crate::calendar;
+use crate::cli::PurgeExpiredArgs;
+use crate::config::Config;
+use crate::retention::PruneMode;
+use crate::storage::SnapshotTable;
+
+// 到期合约的处理：加载后标记 is_expired，purge-expired 删除或归档到期超过 keep_days 天的行
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ExpiryConfig {
+    pub flag_after_load: bool,
+    pub keep_days: u32,
+    pub mode: PruneMode,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        ExpiryConfig { flag_after_load: false, keep_days: 30, mode: PruneMode::Delete }
+    }
+}
+
+// 到期日当天收盘前合约仍在交易，只标记到期日早于纽约今天的行
+pub async fn flag(pool: &PgPool, table: &SnapshotTable) -> Result<u64> {
+    let today = calendar::now_new_york().date();
+    let flagged = sqlx::query(&format!(r#"
+        UPDATE {table} SET is_expired = true
+        WHERE NOT is_expired AND cboe_expiration_date(expiration) < $1
+    "#))
+        .bind(today)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if flagged > 0 {
+        info!("Flagged {} rows of contracts expired before {} as is_expired.", flagged, today);
+    }
+    Ok(flagged)
+}
+
+// 按到期日而不是 is_expired 判断，没开 flag_after_load 也能清理
+pub async fn purge(pool: &PgPool, table: &SnapshotTable, keep_days: u32, mode: PruneMode, dry_run: bool) -> Result<u64> {
+    if mode == PruneMode::Archive {
+        table.require_default("Archive mode")?;
+    }
+    let cutoff: NaiveDate = calendar::now_new_york().date() - Duration::days(keep_days as i64);
+
+    if dry_run {
+        let row = sqlx::query(&format!(r#"
+            SELECT count(*), count(DISTINCT (symbol, call_put, expiration, strike_price)), min(cboe_expiration_date(expiration))
+            FROM {table}
+            WHERE cboe_expiration_date(expiration) < $1
+        "#))
+            .bind(cutoff)
+            .fetch_one(pool)
+            .await?;
+        let rows: i64 = row.try_get(0)?;
+        let contracts: i64 = row.try_get(1)?;
+        let oldest: Option<NaiveDate> = row.try_get(2)?;
+        println!(
+            "Dry run: would {} {} rows of {} contracts that expired before {} (oldest expiration: {})",
+            if mode == PruneMode::Archive { "archive" } else { "delete" },
+            rows,
+            contracts,
+            cutoff,
+            oldest.map(|d| d.to_string()).unwrap_or_else(|| "-".to_string()),
+        );
+        return Ok(rows as u64);
+    }
+
+    let removed = match mode {
+        PruneMode::Delete => {
+            sqlx::query(&format!("DELETE FROM {table} WHERE cboe_expiration_date(expiration) < $1"))
+                .bind(cutoff)
+                .execute(pool)
+                .await?
+                .rows_affected()
+        }
+        // 和 prune 一样，归档表主键冲突时整条语句回滚
+        PruneMode::Archive => {
+            sqlx::query(&format!(r#"
+                WITH moved AS (
+                    DELETE FROM {table} WHERE cboe_expiration_date(expiration) < $1 RETURNING *
+                )
+                INSERT INTO t_options_cboe_snapshot_archive
+                SELECT * FROM moved
+            "#))
+                .bind(cutoff)
+                .execute(pool)
+                .await?
+                .rows_affected()
+        }
+    };
+    info!("Purged {} rows of contracts that expired before {}.", removed, cutoff);
+    Ok(removed)
+}
+
+pub async fn run(pool: &PgPool, table: &SnapshotTable, config: &Config, args: &PurgeExpiredArgs) -> Result<()> {
+    let keep_days = args.days.unwrap_or(config.expiry.keep_days);
+    let mode = args.mode.unwrap_or(config.expiry.mode);
+    purge(pool, table, keep_days, mode, args.dry_run).await?;
+    // 到期合约不会再出现，变化检测的状态一并清掉
+    if table.change_detection && !args.dry_run {
+        let cutoff = calendar::now_new_york().date() - Duration::days(keep_days as i64);
+        sqlx::query("DELETE FROM t_cboe_contract_state WHERE cboe_expiration_date(expiration) < $1")
+            .bind(cutoff)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
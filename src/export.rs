@@ -0,0 +1,241 @@
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use futures::TryStreamExt;
+use log::info;
+use sqlx::PgPool;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::cli::{ExportArgs, parse_timestamp};
+use crate::changes;
+use crate::db::SnapshotRow;
+use crate::hive;
+use crate::storage::SnapshotTable;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    // 需要 parquet feature
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ExportLayout {
+    // 一个快照写到一个文件或 stdout
+    File,
+    // underlying=SPX/date=2025-06-03/part-0.parquet，见 hive.rs
+    Hive,
+}
+
+#[derive(Clone, Copy)]
+pub enum SnapshotSelector {
+    Latest,
+    At(NaiveDateTime),
+}
+
+pub fn parse_snapshot(value: &str) -> Result<SnapshotSelector, String> {
+    if value.eq_ignore_ascii_case("latest") {
+        Ok(SnapshotSelector::Latest)
+    } else {
+        parse_timestamp(value).map(SnapshotSelector::At)
+    }
+}
+
+impl SnapshotSelector {
+    // 快照时间和 changes::snapshot_sql（时间绑定在 $1）
+    pub async fn resolve(&self, pool: &PgPool, table: &SnapshotTable) -> Result<(NaiveDateTime, String)> {
+        let snapshot = match self {
+            SnapshotSelector::At(t) => Some(*t),
+            SnapshotSelector::Latest => None,
+        };
+        match changes::resolve(pool, table, snapshot).await? {
+            Some(resolved) => Ok(resolved),
+            None => bail!("No snapshots in the database"),
+        }
+    }
+}
+
+pub async fn run(pool: &PgPool, table: &SnapshotTable, args: &ExportArgs) -> Result<()> {
+    if args.layout == ExportLayout::Hive {
+        return hive::run(pool, table, args).await;
+    }
+    if !args.date.is_empty() {
+        bail!("--date only applies to --layout hive, use --snapshot to pick a snapshot");
+    }
+    if args.format == ExportFormat::Parquet && args.gzip {
+        bail!("--gzip does not apply to parquet, the columns are already compressed");
+    }
+    let (snapshot, source) = args.snapshot.resolve(pool, table).await?;
+    // 不开变化检测时导出这个时间点写入的所有行，包括 watchlist 的延迟报价
+    let source = match table.change_detection {
+        true => source,
+        false => format!("SELECT * FROM {table} WHERE last_updated_time = $1"),
+    };
+    let symbols: Vec<String> = args.symbol.iter().map(|s| s.to_uppercase()).collect();
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let gzip = args.gzip || args.output.as_deref().is_some_and(|p| p.ends_with(".gz"));
+    let out: Box<dyn Write> = if gzip {
+        Box::new(GzEncoder::new(out, Compression::default()))
+    } else {
+        out
+    };
+
+    let sql = format!(r#"
+        SELECT symbol, call_put, expiration, strike_price, volume, matched, routed,
+               bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time,
+               days_to_expiration, expiration_class, is_adjusted, source, market
+        FROM ({source}) AS snapshot
+        WHERE (cardinality($2::text[]) = 0 OR symbol = ANY($2))
+          AND ($3::text IS NULL OR expiration = $3)
+        ORDER BY symbol, expiration, strike_price, call_put
+    "#);
+    let mut rows = sqlx::query_as::<_, SnapshotRow>(&sql)
+        .bind(snapshot)
+        .bind(&symbols)
+        .bind(&args.expiry)
+        .fetch(pool);
+
+    let mut count = 0u64;
+    match args.format {
+        ExportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(out);
+            while let Some(row) = rows.try_next().await? {
+                wtr.serialize(&row)?;
+                count += 1;
+            }
+            wtr.flush()?;
+        }
+        ExportFormat::Jsonl => {
+            let mut out = out;
+            while let Some(row) = rows.try_next().await? {
+                serde_json::to_writer(&mut out, &row)?;
+                out.write_all(b"\n")?;
+                count += 1;
+            }
+            out.flush()?;
+        }
+        // Parquet 的 footer 要等全部行写完才能生成，先收集到内存
+        ExportFormat::Parquet => {
+            let rows: Vec<SnapshotRow> = rows.try_collect().await?;
+            count = rows.len() as u64;
+            let mut out = out;
+            out.write_all(&encode(&rows, ExportFormat::Parquet, false)?)?;
+            out.flush()?;
+        }
+    }
+
+    info!("Exported {} rows from snapshot {}.", count, snapshot);
+    Ok(())
+}
+
+// 把一组行编码成一个完整的文件，用于分区导出的每个 part 文件
+pub fn encode(rows: &[SnapshotRow], format: ExportFormat, gzip: bool) -> Result<Vec<u8>> {
+    let body = match format {
+        ExportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(Vec::new());
+            for row in rows {
+                wtr.serialize(row)?;
+            }
+            wtr.into_inner().map_err(|e| e.into_error())?
+        }
+        ExportFormat::Jsonl => {
+            let mut out = Vec::new();
+            for row in rows {
+                serde_json::to_writer(&mut out, row)?;
+                out.write_all(b"\n")?;
+            }
+            out
+        }
+        ExportFormat::Parquet => return parquet(rows),
+    };
+    if !gzip {
+        return Ok(body);
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&body)?;
+    Ok(encoder.finish()?)
+}
+
+// 列与快照表相同；last_updated_time 用毫秒时间戳（不带时区的纽约时间），Athena / Spark 都能直接识别为 timestamp
+#[cfg(feature = "parquet")]
+fn parquet(rows: &[SnapshotRow]) -> Result<Vec<u8>> {
+    use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int32Array, Int64Array, StringArray, TimestampMillisecondArray};
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("call_put", DataType::Utf8, false),
+        Field::new("expiration", DataType::Utf8, false),
+        Field::new("strike_price", DataType::Float64, false),
+        Field::new("volume", DataType::Int64, false),
+        Field::new("matched", DataType::Int64, false),
+        Field::new("routed", DataType::Int64, false),
+        Field::new("bid_size", DataType::Int64, false),
+        Field::new("bid_price", DataType::Float64, false),
+        Field::new("ask_size", DataType::Int64, false),
+        Field::new("ask_price", DataType::Float64, false),
+        Field::new("last_price", DataType::Float64, false),
+        Field::new("last_updated_time", DataType::Timestamp(TimeUnit::Millisecond, None), false),
+        Field::new("days_to_expiration", DataType::Int32, true),
+        Field::new("expiration_class", DataType::Utf8, true),
+        Field::new("is_adjusted", DataType::Boolean, false),
+        Field::new("source", DataType::Utf8, false),
+        Field::new("market", DataType::Utf8, false),
+    ]));
+    let strings = |f: fn(&SnapshotRow) -> &str| -> ArrayRef { Arc::new(StringArray::from_iter_values(rows.iter().map(f))) };
+    let ints = |f: fn(&SnapshotRow) -> i64| -> ArrayRef { Arc::new(Int64Array::from_iter_values(rows.iter().map(f))) };
+    let floats = |f: fn(&SnapshotRow) -> f64| -> ArrayRef { Arc::new(Float64Array::from_iter_values(rows.iter().map(f))) };
+    let columns = vec![
+        strings(|r| &r.symbol),
+        strings(|r| &r.call_put),
+        strings(|r| &r.expiration),
+        floats(|r| r.strike_price),
+        ints(|r| r.volume),
+        ints(|r| r.matched),
+        ints(|r| r.routed),
+        ints(|r| r.bid_size),
+        floats(|r| r.bid_price),
+        ints(|r| r.ask_size),
+        floats(|r| r.ask_price),
+        floats(|r| r.last_price),
+        Arc::new(TimestampMillisecondArray::from_iter_values(rows.iter().map(|r| r.last_updated_time.and_utc().timestamp_millis()))),
+        Arc::new(rows.iter().map(|r| r.days_to_expiration).collect::<Int32Array>()),
+        Arc::new(rows.iter().map(|r| r.expiration_class.as_deref()).collect::<StringArray>()),
+        Arc::new(rows.iter().map(|r| Some(r.is_adjusted)).collect::<BooleanArray>()),
+        strings(|r| &r.source),
+        strings(|r| &r.market),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), schema, Some(props))?;
+    writer.write(&batch)?;
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn parquet(_rows: &[SnapshotRow]) -> Result<Vec<u8>> {
+    bail!("--format parquet requires building with --features parquet")
+}
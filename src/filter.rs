@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub include: Vec<String>,
+    pub include_prefix: Vec<String>,
+    pub include_regex: Option<String>,
+    pub exclude: Vec<String>,
+    pub exclude_prefix: Vec<String>,
+    pub exclude_regex: Option<String>,
+}
+
+impl FilterConfig {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.include_prefix.is_empty()
+            && self.include_regex.is_none()
+            && self.exclude.is_empty()
+            && self.exclude_prefix.is_empty()
+            && self.exclude_regex.is_none()
+    }
+}
+
+#[derive(Default)]
+struct Rule {
+    exact: HashSet<String>,
+    prefixes: Vec<String>,
+    regex: Option<Regex>,
+}
+
+impl Rule {
+    // 代码统一转成大写比较，正则也不区分大小写，和精确匹配、前缀的行为一致
+    fn new(exact: &[String], prefixes: &[String], regex: Option<&str>) -> Result<Rule> {
+        let regex = regex
+            .map(|r| RegexBuilder::new(r).case_insensitive(true).build().with_context(|| format!("Invalid symbol regex {}", r)))
+            .transpose()?;
+        Ok(Rule {
+            exact: exact.iter().map(|s| s.to_uppercase()).collect(),
+            prefixes: prefixes.iter().map(|s| s.to_uppercase()).collect(),
+            regex,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.prefixes.is_empty() && self.regex.is_none()
+    }
+
+    fn matches(&self, symbol: &str) -> bool {
+        self.exact.contains(symbol)
+            || self.prefixes.iter().any(|p| symbol.starts_with(p.as_str()))
+            || self.regex.as_ref().is_some_and(|r| r.is_match(symbol))
+    }
+}
+
+#[derive(Default)]
+pub struct SymbolFilter {
+    include: Rule,
+    exclude: Rule,
+    description: String,
+}
+
+impl SymbolFilter {
+    pub fn new(config: &FilterConfig) -> Result<SymbolFilter> {
+        let mut parts = Vec::new();
+        let mut describe = |name: &str, values: &[String]| {
+            if !values.is_empty() {
+                parts.push(format!("{}={}", name, values.join("|")));
+            }
+        };
+        describe("include", &config.include);
+        describe("include_prefix", &config.include_prefix);
+        describe("include_regex", config.include_regex.as_slice());
+        describe("exclude", &config.exclude);
+        describe("exclude_prefix", &config.exclude_prefix);
+        describe("exclude_regex", config.exclude_regex.as_slice());
+
+        Ok(SymbolFilter {
+            include: Rule::new(&config.include, &config.include_prefix, config.include_regex.as_deref())?,
+            exclude: Rule::new(&config.exclude, &config.exclude_prefix, config.exclude_regex.as_deref())?,
+            description: parts.join(";"),
+        })
+    }
+
+    // 没有 include 规则时默认全部保留
+    pub fn matches(&self, symbol: &str) -> bool {
+        let symbol = symbol.trim().to_uppercase();
+        (self.include.is_empty() || self.include.matches(&symbol)) && !self.exclude.matches(&symbol)
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        if self.description.is_empty() { None } else { Some(&self.description) }
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use futures::{Stream, StreamExt, stream};
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use crate::cboe::{ContractKey, OptionRecord};
+use crate::sink::Sink;
+
+pub mod proto {
+    tonic::include_proto!("cboe.snapshot.v1");
+}
+
+use proto::snapshot_stream_server::{SnapshotStream, SnapshotStreamServer};
+use proto::{ContractUpdate, SubscribeRequest, UpdateKind};
+
+#[derive(Deserialize)]
+pub struct GrpcConfig {
+    pub bind: String,
+    // 每个市场一批，订阅者落后超过这么多批时丢弃最旧的
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+}
+
+fn default_capacity() -> usize {
+    16
+}
+
+// 每个合约上一次推送时的成交量和字段指纹，用来判断新增 / 变化
+struct Hub {
+    sender: broadcast::Sender<Arc<Vec<ContractUpdate>>>,
+    last: Mutex<HashMap<(String, ContractKey), (i64, u64)>>,
+}
+
+static HUB: OnceLock<Arc<Hub>> = OnceLock::new();
+
+fn fingerprint(rec: &OptionRecord) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (rec.volume, rec.matched, rec.routed, rec.bid_size, rec.ask_size).hash(&mut hasher);
+    (rec.bid_price.to_bits(), rec.ask_price.to_bits(), rec.last_price.to_bits()).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Hub {
+    fn updates(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> Vec<ContractUpdate> {
+        let snapshot_time = snapshot_time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut last = self.last.lock().expect("grpc state lock");
+        records.iter()
+            .map(|rec| {
+                let previous = last.insert((market.to_string(), rec.key()), (rec.volume, fingerprint(rec)));
+                let (kind, volume_delta) = match previous {
+                    None => (UpdateKind::New, 0),
+                    Some((_, print)) if print == fingerprint(rec) => (UpdateKind::Unchanged, 0),
+                    // 成交量是当天累计值，跨交易日变小时增量就是当天的成交量
+                    Some((volume, _)) if rec.volume < volume => (UpdateKind::Changed, rec.volume),
+                    Some((volume, _)) => (UpdateKind::Changed, rec.volume - volume),
+                };
+                ContractUpdate {
+                    market: market.to_string(),
+                    snapshot_time: snapshot_time.clone(),
+                    symbol: rec.symbol.clone(),
+                    call_put: rec.call_put.clone(),
+                    expiration: rec.expiration.clone(),
+                    strike_price: rec.strike_price,
+                    volume: rec.volume,
+                    matched: rec.matched,
+                    routed: rec.routed,
+                    bid_size: rec.bid_size,
+                    bid_price: rec.bid_price,
+                    ask_size: rec.ask_size,
+                    ask_price: rec.ask_price,
+                    last_price: rec.last_price,
+                    kind: kind as i32,
+                    volume_delta,
+                }
+            })
+            .collect()
+    }
+}
+
+struct Filter {
+    symbols: HashSet<String>,
+    markets: HashSet<String>,
+    deltas_only: bool,
+}
+
+impl Filter {
+    fn matches(&self, update: &ContractUpdate) -> bool {
+        (self.symbols.is_empty() || self.symbols.contains(&update.symbol.trim().to_ascii_uppercase()))
+            && (self.markets.is_empty() || self.markets.contains(&update.market))
+            && !(self.deltas_only && update.kind == UpdateKind::Unchanged as i32)
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<ContractUpdate, Status>> + Send>>;
+
+struct Service {
+    hub: Arc<Hub>,
+}
+
+#[tonic::async_trait]
+impl SnapshotStream for Service {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(&self, request: Request<SubscribeRequest>) -> Result<Response<UpdateStream>, Status> {
+        let request = request.into_inner();
+        let filter = Filter {
+            symbols: request.symbols.iter().map(|s| s.trim().to_ascii_uppercase()).collect(),
+            markets: request.markets.into_iter().collect(),
+            deltas_only: request.deltas_only,
+        };
+        info!("gRPC subscriber connected ({} symbols, {} markets, deltas_only = {}).", filter.symbols.len(), filter.markets.len(), filter.deltas_only);
+        let batches = stream::unfold(self.hub.sender.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(batch) => return Some((batch, rx)),
+                    Err(RecvError::Lagged(n)) => warn!("gRPC subscriber fell behind, dropped {} batches.", n),
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        let updates = batches.flat_map(move |batch| {
+            let matched: Vec<_> = batch.iter().filter(|u| filter.matches(u)).cloned().map(Ok).collect();
+            stream::iter(matched)
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
+// 把每个市场写入的合约推送给 gRPC 订阅者；daemon 每次运行都会重建 Sinks，服务只在第一次创建时启动
+pub struct GrpcSink {
+    hub: Arc<Hub>,
+}
+
+impl GrpcSink {
+    pub async fn new(config: &GrpcConfig) -> Result<GrpcSink> {
+        if let Some(hub) = HUB.get() {
+            return Ok(GrpcSink { hub: hub.clone() });
+        }
+        let listener = TcpListener::bind(&config.bind).await.with_context(|| format!("Failed to bind gRPC server to {}", config.bind))?;
+        let (sender, _) = broadcast::channel(config.capacity.max(1));
+        let hub = HUB.get_or_init(|| Arc::new(Hub { sender, last: Mutex::new(HashMap::new()) })).clone();
+        let service = SnapshotStreamServer::new(Service { hub: hub.clone() });
+        info!("gRPC server listening on {}.", config.bind);
+        tokio::spawn(async move {
+            if let Err(e) = Server::builder().add_service(service).serve_with_incoming(TcpIncoming::from(listener)).await {
+                error!("gRPC server stopped: {:#}", e);
+            }
+        });
+        Ok(GrpcSink { hub })
+    }
+}
+
+#[async_trait]
+impl Sink for GrpcSink {
+    fn name(&self) -> &'static str {
+        "grpc"
+    }
+
+    async fn write(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> Result<()> {
+        let updates = self.hub.updates(market, snapshot_time, records);
+        // 没有订阅者时 send 会返回错误，忽略即可
+        let _ = self.hub.sender.send(Arc::new(updates));
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use log::{info, warn};
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::run_log::{Outcome, RunLog};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PingMethod {
+    #[default]
+    Get,
+    Post,
+}
+
+// healthchecks.io / Cronitor 风格的 dead man's switch：成功时 ping url，失败时 ping fail_url（默认 url + "/fail"）
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct HealthcheckConfig {
+    pub url: Option<String>,
+    pub fail_url: Option<String>,
+    pub method: PingMethod,
+}
+
+// 跳过（快照未更新、休市）也算成功；收到停止信号时不 ping，交给对方的超时判断
+pub async fn ping(client: &Client, config: &HealthcheckConfig, run: &RunLog, outcome: Outcome) {
+    let Some(url) = &config.url else {
+        return;
+    };
+    let url = match outcome {
+        Outcome::Success | Outcome::Skipped => url.clone(),
+        Outcome::Failed => config.fail_url.clone().unwrap_or_else(|| format!("{}/fail", url.trim_end_matches('/'))),
+        Outcome::Cancelled => return,
+    };
+    match send(client, config.method, &url, outcome, run).await {
+        Ok(()) => info!("Sent healthcheck ping to {}.", url),
+        Err(e) => warn!("Failed to send healthcheck ping to {}: {:#}", url, e),
+    }
+}
+
+async fn send(client: &Client, method: PingMethod, url: &str, outcome: Outcome, run: &RunLog) -> Result<()> {
+    // 失败时总是 POST，把错误摘要放在请求体里
+    let req = if method == PingMethod::Post || outcome == Outcome::Failed {
+        client.post(url).body(run.summary_text())
+    } else {
+        client.get(url)
+    };
+    req.send().await?.error_for_status()?;
+    Ok(())
+}
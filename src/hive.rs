@@ -0,0 +1,224 @@
+use anyhow::{Context, Result, bail};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use futures::TryStreamExt;
+use log::{debug, info, warn};
+use sqlx::PgPool;
+use std::path::PathBuf;
+
+use crate::cli::ExportArgs;
+use crate::db::SnapshotRow;
+use crate::export::{self, ExportFormat};
+use crate::storage::SnapshotTable;
+
+// 导出目标：本地目录或 s3://bucket/prefix
+enum Destination {
+    Local(PathBuf),
+    #[cfg(feature = "s3")]
+    S3 { client: aws_sdk_s3::Client, bucket: String, prefix: String },
+}
+
+impl Destination {
+    async fn parse(output: &str) -> Result<Destination> {
+        match output.strip_prefix("s3://") {
+            Some(location) => s3(location).await,
+            None => Ok(Destination::Local(PathBuf::from(output))),
+        }
+    }
+
+    // 分区在写第一个 part 之前清空，重新导出同一天时不会留下上次多出来的 part 文件
+    async fn clear(&self, partition: &str) -> Result<()> {
+        match self {
+            Destination::Local(dir) => {
+                let path = dir.join(partition);
+                if path.exists() {
+                    std::fs::remove_dir_all(&path).with_context(|| format!("Failed to clear {}", path.display()))?;
+                }
+                std::fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+            }
+            #[cfg(feature = "s3")]
+            Destination::S3 { client, bucket, prefix } => {
+                let partition = format!("{}/", key(prefix, partition));
+                let mut pages = client.list_objects_v2().bucket(bucket).prefix(&partition).into_paginator().send();
+                while let Some(page) = pages.next().await {
+                    let page = page.with_context(|| format!("Failed to list s3://{}/{}", bucket, partition))?;
+                    for object in page.contents() {
+                        if let Some(object) = object.key() {
+                            client.delete_object().bucket(bucket).key(object).send().await
+                                .with_context(|| format!("Failed to delete s3://{}/{}", bucket, object))?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn put(&self, path: &str, body: Vec<u8>) -> Result<()> {
+        match self {
+            Destination::Local(dir) => {
+                let path = dir.join(path);
+                std::fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            #[cfg(feature = "s3")]
+            Destination::S3 { client, bucket, prefix } => {
+                let object = key(prefix, path);
+                client.put_object().bucket(bucket).key(&object).body(body.into()).send().await
+                    .with_context(|| format!("Failed to upload s3://{}/{}", bucket, object))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 凭证和区域走 AWS SDK 的默认链，MinIO 等兼容存储用 AWS_ENDPOINT_URL 指定地址
+#[cfg(feature = "s3")]
+async fn s3(location: &str) -> Result<Destination> {
+    let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+    if bucket.is_empty() {
+        bail!("Missing bucket in s3://{}", location);
+    }
+    let config = aws_config::load_from_env().await;
+    Ok(Destination::S3 {
+        client: aws_sdk_s3::Client::new(&config),
+        bucket: bucket.to_string(),
+        prefix: prefix.trim_matches('/').to_string(),
+    })
+}
+
+#[cfg(not(feature = "s3"))]
+async fn s3(_location: &str) -> Result<Destination> {
+    bail!("s3:// outputs require building with --features s3")
+}
+
+#[cfg(feature = "s3")]
+fn key(prefix: &str, path: &str) -> String {
+    if prefix.is_empty() { path.to_string() } else { format!("{}/{}", prefix, path) }
+}
+
+// Hive 分区值的转义：字母数字和 _ - . 以外的字符写成 %XX，和 Hive / Spark 的规则兼容
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.') {
+            escaped.push(b as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    escaped
+}
+
+struct Written {
+    rows: u64,
+    files: u64,
+}
+
+async fn write_part(destination: &Destination, args: &ExportArgs, partition: &str, part: usize, rows: &[SnapshotRow], written: &mut Written) -> Result<()> {
+    let extension = if args.gzip { format!("{}.gz", args.format.extension()) } else { args.format.extension().to_string() };
+    let path = format!("{}/part-{}.{}", partition, part, extension);
+    destination.put(&path, export::encode(rows, args.format, args.gzip)?).await?;
+    debug!("Wrote {} ({} rows)", path, rows.len());
+    written.rows += rows.len() as u64;
+    written.files += 1;
+    Ok(())
+}
+
+// 每个交易日的全部快照按 underlying 分区写出：underlying=SPX/date=2025-06-03/part-0.parquet，
+// 一个分区超过 max_rows_per_file 行时拆成 part-1、part-2 ...
+pub async fn run(pool: &PgPool, table: &SnapshotTable, args: &ExportArgs) -> Result<()> {
+    let Some(output) = args.output.as_deref() else {
+        bail!("--layout hive needs --output, a directory or s3://bucket/prefix");
+    };
+    if args.format == ExportFormat::Parquet && args.gzip {
+        bail!("--gzip does not apply to parquet, the columns are already compressed");
+    }
+    let dates: Vec<NaiveDate> = if args.date.is_empty() {
+        vec![args.snapshot.resolve(pool, table).await?.0.date()]
+    } else {
+        args.date.clone()
+    };
+    let destination = Destination::parse(output).await?;
+    let symbols: Vec<String> = args.symbol.iter().map(|s| s.to_uppercase()).collect();
+    let max_rows = args.max_rows_per_file.max(1);
+
+    let columns = "symbol, call_put, expiration, strike_price, volume, matched, routed, \
+        bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, \
+        days_to_expiration, expiration_class, is_adjusted, source, market";
+    // 开启变化检测时每个快照用 cboe_snapshot_at 还原成完整快照，沿用的合约 last_updated_time 记为快照时间，
+    // 和不开变化检测时导出的内容一致；watchlist 的延迟报价照常导出
+    let rows = if table.change_detection {
+        format!(r#"
+            SELECT t.symbol, t.call_put, t.expiration, t.strike_price, t.volume, t.matched, t.routed,
+                   t.bid_size, t.bid_price, t.ask_size, t.ask_price, t.last_price, p.last_updated_time,
+                   t.days_to_expiration, t.expiration_class, t.is_adjusted, t.source, t.market
+            FROM (
+                SELECT DISTINCT last_updated_time FROM {table}
+                WHERE last_updated_time >= $1 AND last_updated_time < $2 AND source = 'symbol_data'
+            ) AS p
+            CROSS JOIN LATERAL cboe_snapshot_at(p.last_updated_time) a
+            JOIN {table} t
+              ON t.market = a.market AND t.symbol = a.symbol AND t.call_put = a.call_put
+             AND t.expiration = a.expiration AND t.strike_price = a.strike_price AND t.last_updated_time = a.changed_at
+            WHERE t.source = 'symbol_data'
+            UNION ALL
+            SELECT {columns} FROM {table}
+            WHERE last_updated_time >= $1 AND last_updated_time < $2 AND source <> 'symbol_data'
+        "#)
+    } else {
+        format!("SELECT {columns} FROM {table} WHERE last_updated_time >= $1 AND last_updated_time < $2")
+    };
+    let sql = format!(r#"
+        SELECT {columns}
+        FROM ({rows}) AS snapshot
+        WHERE (cardinality($3::text[]) = 0 OR symbol = ANY($3))
+          AND ($4::text IS NULL OR expiration = $4)
+        ORDER BY symbol, last_updated_time, expiration, strike_price, call_put
+    "#);
+
+    let mut written = Written { rows: 0, files: 0 };
+    for date in dates {
+        let start = date.and_time(NaiveTime::MIN);
+        let mut rows = sqlx::query_as::<_, SnapshotRow>(&sql)
+            .bind(start)
+            .bind(start + Duration::days(1))
+            .bind(&symbols)
+            .bind(&args.expiry)
+            .fetch(pool);
+
+        let before = written.rows;
+        let mut current: Option<(String, String)> = None;
+        let mut buffer: Vec<SnapshotRow> = Vec::new();
+        let mut part = 0;
+        while let Some(row) = rows.try_next().await? {
+            match &current {
+                Some((symbol, partition)) if *symbol == row.symbol => {
+                    if buffer.len() >= max_rows {
+                        write_part(&destination, args, partition, part, &buffer, &mut written).await?;
+                        buffer.clear();
+                        part += 1;
+                    }
+                }
+                _ => {
+                    if let Some((_, partition)) = &current {
+                        write_part(&destination, args, partition, part, &buffer, &mut written).await?;
+                        buffer.clear();
+                    }
+                    let partition = format!("underlying={}/date={}", escape(&row.symbol), date);
+                    destination.clear(&partition).await?;
+                    current = Some((row.symbol.clone(), partition));
+                    part = 0;
+                }
+            }
+            buffer.push(row);
+        }
+        if let Some((_, partition)) = &current {
+            write_part(&destination, args, partition, part, &buffer, &mut written).await?;
+        }
+        if written.rows == before {
+            warn!("No snapshot rows on {}.", date);
+        }
+    }
+
+    info!("Exported {} rows into {} files under {}.", written.rows, written.files, output);
+    Ok(())
+}
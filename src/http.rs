@@ -0,0 +1,265 @@
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use bytes::Bytes;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use log::{debug, warn};
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Proxy};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub user_agent: Option<String>,
+    pub headers: HashMap<String, String>,
+    pub ca_bundle: Option<String>,
+    pub min_delay_ms: u64,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown_secs: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            proxy: None,
+            https_proxy: None,
+            no_proxy: None,
+            connect_timeout_secs: None,
+            read_timeout_secs: None,
+            timeout_secs: None,
+            user_agent: None,
+            headers: HashMap::new(),
+            ca_bundle: None,
+            min_delay_ms: 1000,
+            breaker_threshold: 5,
+            breaker_cooldown_secs: 300,
+        }
+    }
+}
+
+fn env_or(name: &str, value: &Option<String>) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty()).or_else(|| value.clone())
+}
+
+// 抓页面和下载 CSV 共用同一个 client，配置项可被 CBOE_HTTP_* 环境变量覆盖
+pub fn build_client(config: &HttpConfig) -> Result<Client> {
+    let user_agent = env_or("CBOE_HTTP_USER_AGENT", &config.user_agent)
+        .unwrap_or_else(|| concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string());
+    // 解压由 Fetcher 自己做，这样才能拿到压缩前后的字节数
+    let mut builder = Client::builder().user_agent(user_agent).no_gzip().no_deflate().no_brotli();
+
+    let no_proxy = env_or("CBOE_HTTP_NO_PROXY", &config.no_proxy).and_then(|n| reqwest::NoProxy::from_string(&n));
+    if let Some(proxy) = env_or("CBOE_HTTP_PROXY", &config.proxy) {
+        let proxy = Proxy::all(&proxy).with_context(|| format!("Invalid proxy {}", proxy))?;
+        builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+    }
+    if let Some(proxy) = env_or("CBOE_HTTPS_PROXY", &config.https_proxy) {
+        let proxy = Proxy::https(&proxy).with_context(|| format!("Invalid proxy {}", proxy))?;
+        builder = builder.proxy(proxy.no_proxy(no_proxy));
+    }
+
+    if let Some(secs) = config.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = config.read_timeout_secs {
+        builder = builder.read_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = config.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
+
+    if !config.headers.is_empty() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name {}", name))?,
+                HeaderValue::from_str(value).with_context(|| format!("Invalid value for header {}", name))?,
+            );
+        }
+        builder = builder.default_headers(headers);
+    }
+
+    if let Some(path) = env_or("CBOE_HTTP_CA_BUNDLE", &config.ca_bundle) {
+        let pem = std::fs::read(&path).with_context(|| format!("Failed to read CA bundle {}", path))?;
+        for cert in Certificate::from_pem_bundle(&pem).with_context(|| format!("Invalid CA bundle {}", path))? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+const SUPPORTED_ENCODINGS: &str = "gzip, deflate, br";
+
+pub struct Download {
+    pub body: Bytes,
+    // 实际传输的字节数，未压缩时等于 body 的长度
+    pub wire_bytes: u64,
+}
+
+// 发 GET 请求拿响应体，生产环境是 Fetcher，测试时用 testing::MemoryFetcher
+#[async_trait]
+pub trait HttpFetcher: Send + Sync {
+    async fn get_download(&self, url: &str) -> Result<Download>;
+
+    async fn get_bytes(&self, url: &str) -> Result<Bytes> {
+        Ok(self.get_download(url).await?.body)
+    }
+
+    async fn get_text(&self, url: &str) -> Result<String> {
+        let bytes = self.get_bytes(url).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+// 对 cboe.com 的所有请求都经过这里：请求之间保持最小间隔，
+// 同一个 URL 连续失败达到阈值后熔断一段时间，期间直接返回错误
+pub struct Fetcher {
+    client: Client,
+    min_delay: Duration,
+    threshold: u32,
+    cooldown: Duration,
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+impl Fetcher {
+    pub fn new(config: &HttpConfig) -> Result<Fetcher> {
+        Ok(Fetcher {
+            client: build_client(config)?,
+            min_delay: Duration::from_millis(config.min_delay_ms),
+            threshold: config.breaker_threshold.max(1),
+            cooldown: Duration::from_secs(config.breaker_cooldown_secs),
+            last_request: tokio::sync::Mutex::new(None),
+            breakers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        let bytes = self.get_bytes(url).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    pub async fn get_bytes(&self, url: &str) -> Result<Bytes> {
+        Ok(self.get_download(url).await?.body)
+    }
+
+    pub async fn get_download(&self, url: &str) -> Result<Download> {
+        self.check_breaker(url)?;
+        self.wait_turn().await;
+        let result = self.fetch(url).await;
+        self.record(url, result.is_ok());
+        result
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Download> {
+        let resp = self.client.get(url).header(ACCEPT_ENCODING, SUPPORTED_ENCODINGS).send().await?.error_for_status()?;
+        let encoding = resp
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_ascii_lowercase());
+        let raw = resp.bytes().await?;
+        let wire_bytes = raw.len() as u64;
+        let body = match encoding.as_deref() {
+            None | Some("") | Some("identity") => raw,
+            Some(encoding) => {
+                let body = decode(encoding, &raw).with_context(|| format!("Failed to decode {} response from {}", encoding, url))?;
+                debug!("{}: {} bytes {} -> {} bytes", url, wire_bytes, encoding, body.len());
+                body
+            }
+        };
+        Ok(Download { body, wire_bytes })
+    }
+
+    async fn wait_turn(&self) {
+        let mut last = self.last_request.lock().await;
+        if let Some(last) = *last {
+            let elapsed = last.elapsed();
+            if elapsed < self.min_delay {
+                tokio::time::sleep(self.min_delay - elapsed).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+
+    fn check_breaker(&self, url: &str) -> Result<()> {
+        let breakers = self.breakers.lock().expect("breaker lock");
+        if let Some(open_until) = breakers.get(url).and_then(|b| b.open_until)
+            && Instant::now() < open_until
+        {
+            bail!("Circuit open for {}, retrying in {}s", url, (open_until - Instant::now()).as_secs());
+        }
+        Ok(())
+    }
+
+    fn record(&self, url: &str, success: bool) {
+        let mut breakers = self.breakers.lock().expect("breaker lock");
+        let breaker = breakers.entry(url.to_string()).or_default();
+        if success {
+            *breaker = Breaker::default();
+            return;
+        }
+        breaker.consecutive_failures += 1;
+        // 冷却结束后的试探请求再失败会立即重新熔断
+        if breaker.consecutive_failures >= self.threshold {
+            warn!("{} failed {} times in a row, opening circuit for {}s.", url, breaker.consecutive_failures, self.cooldown.as_secs());
+            breaker.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+#[async_trait]
+impl HttpFetcher for Fetcher {
+    async fn get_download(&self, url: &str) -> Result<Download> {
+        Fetcher::get_download(self, url).await
+    }
+}
+
+#[async_trait]
+impl<F: HttpFetcher + ?Sized> HttpFetcher for Arc<F> {
+    async fn get_download(&self, url: &str) -> Result<Download> {
+        self.as_ref().get_download(url).await
+    }
+}
+
+fn decode(encoding: &str, raw: &[u8]) -> Result<Bytes> {
+    let mut body = Vec::new();
+    match encoding {
+        "gzip" | "x-gzip" => {
+            MultiGzDecoder::new(raw).read_to_end(&mut body)?;
+        }
+        // 规范要求 zlib 格式，但有些服务器发的是裸 deflate
+        "deflate" => {
+            if ZlibDecoder::new(raw).read_to_end(&mut body).is_err() {
+                body.clear();
+                DeflateDecoder::new(raw).read_to_end(&mut body)?;
+            }
+        }
+        "br" => {
+            brotli::Decompressor::new(raw, 4096).read_to_end(&mut body)?;
+        }
+        other => bail!("Unsupported Content-Encoding {}", other),
+    }
+    Ok(Bytes::from(body))
+}
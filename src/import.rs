@@ -0,0 +1,307 @@
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::{ReaderBuilder, StringRecord};
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+use cboe_snapshot::classify::normalize_strike;
+#[cfg(feature = "arrow")]
+use cboe_snapshot::{MarketSnapshot, cache};
+
+use crate::cboe::{CsvContent, EXPECTED_HEADERS, OptionRecord, parse_expiration, parse_field};
+use crate::cli::ImportArgs;
+use crate::config::Config;
+use crate::expirations;
+use crate::memory;
+use crate::filter::SymbolFilter;
+use crate::maintenance;
+use crate::mirror::Mirrors;
+use crate::run_log::{MarketStats, Outcome, RunLog};
+use crate::shutdown;
+use crate::source::{MarketData, SnapshotSource};
+use crate::storage::{Database, Provenance, SnapshotTable};
+use crate::symbols;
+
+const FIELDS: [&str; 12] = [
+    "symbol", "call_put", "expiration", "strike_price", "volume", "matched", "routed",
+    "bid_size", "bid_price", "ask_size", "ask_price", "last_price",
+];
+const REQUIRED: [&str; 4] = ["symbol", "call_put", "expiration", "strike_price"];
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ImportConfig {
+    pub delimiter: Option<char>,
+    // 从文件名（不含扩展名）解析快照时间，例如 "cboe_%Y%m%d_%H%M"
+    pub filename_format: Option<String>,
+    // 把到期日统一改写成这个格式，例如 "%Y-%m-%d"
+    pub expiration_format: Option<String>,
+    // 字段 -> 候选表头，没配置的字段使用 CBOE 的默认表头
+    pub columns: HashMap<String, Vec<String>>,
+    // 解析 CSV 后在同目录写一份 <文件名>.arrow 二进制缓存（需要 arrow feature），import / diff 读取时有缓存就直接用
+    pub binary_cache: bool,
+}
+
+impl ImportConfig {
+    // 影响解析结果的设置，写进缓存，设置变了缓存作废
+    #[cfg(feature = "arrow")]
+    fn fingerprint(&self) -> String {
+        let mut columns: Vec<_> = self.columns.iter().collect();
+        columns.sort();
+        serde_json::json!([self.delimiter, self.expiration_format, columns]).to_string()
+    }
+}
+
+// 本地 CSV 文件，列名和顺序由 [import.columns] 映射
+pub struct FileSource<'a> {
+    path: PathBuf,
+    config: &'a ImportConfig,
+    snapshot_time: NaiveDateTime,
+}
+
+impl<'a> FileSource<'a> {
+    pub fn new(path: &Path, config: &'a ImportConfig, snapshot_time: Option<NaiveDateTime>) -> Result<FileSource<'a>> {
+        #[cfg(not(feature = "arrow"))]
+        if config.binary_cache {
+            bail!("import.binary_cache requires building with --features arrow");
+        }
+        let snapshot_time = match snapshot_time {
+            Some(t) => t,
+            None => snapshot_time_from_name(path, config)?,
+        };
+        Ok(FileSource { path: path.to_path_buf(), config, snapshot_time })
+    }
+
+    fn market(&self) -> String {
+        self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "import".to_string())
+    }
+
+    fn resolve_columns(&self, headers: &StringRecord) -> Result<HashMap<&'static str, usize>> {
+        let mut columns = HashMap::new();
+        for (field, default) in FIELDS.iter().zip(EXPECTED_HEADERS) {
+            let candidates = self.config.columns.get(*field)
+                .cloned()
+                .unwrap_or_else(|| vec![default.to_string()]);
+            let position = headers.iter()
+                .position(|h| candidates.iter().any(|c| h.trim().eq_ignore_ascii_case(c.trim())));
+            match position {
+                Some(i) => {
+                    columns.insert(*field, i);
+                }
+                None if REQUIRED.contains(field) => {
+                    bail!("{}: no column for {} (tried {})", self.path.display(), field, candidates.join(", "))
+                }
+                None => {}
+            }
+        }
+        Ok(columns)
+    }
+
+    // 有效的缓存里是全部记录，按 filter 过滤后使用；没有缓存时解析 CSV，开启 binary_cache 时顺便写缓存
+    #[cfg(feature = "arrow")]
+    fn read_cached(&self, filter: &SymbolFilter) -> Result<CsvContent> {
+        let fingerprint = self.config.fingerprint();
+        let (headers, mut records, parse_errors, bytes) = match cache::read(&self.path, &fingerprint)? {
+            Some(cached) => {
+                info!("Read {} rows from {}.", cached.records.len(), cache::cache_path(&self.path).display());
+                (cached.headers, cached.records, cached.parse_errors, cached.source_bytes)
+            }
+            None if self.config.binary_cache => {
+                let content = self.parse(&SymbolFilter::default())?;
+                let snapshot = MarketSnapshot {
+                    market: self.market(),
+                    snapshot_time: self.snapshot_time,
+                    records: content.records,
+                    parse_errors: content.parse_errors,
+                    http_bytes: content.http_bytes,
+                    wire_bytes: content.wire_bytes,
+                };
+                match cache::write(&self.path, &fingerprint, &content.headers, &snapshot) {
+                    Ok(path) => info!("Wrote binary cache {}.", path.display()),
+                    Err(e) => warn!("Failed to write binary cache for {}: {:#}", self.path.display(), e),
+                }
+                (content.headers, snapshot.records, snapshot.parse_errors, snapshot.http_bytes)
+            }
+            None => return self.parse(filter),
+        };
+        let total = records.len();
+        records.retain(|r| filter.matches(&r.symbol));
+        let filtered = (total - records.len()) as u64;
+        Ok(CsvContent { headers, records, http_bytes: bytes, wire_bytes: bytes, parse_errors, filtered, source_url: None })
+    }
+
+    fn parse(&self, filter: &SymbolFilter) -> Result<CsvContent> {
+        let data = std::fs::read(&self.path).with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let http_bytes = data.len() as u64;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .delimiter(self.config.delimiter.unwrap_or(',') as u8)
+            .from_reader(data.as_slice());
+        let header_record = rdr.headers()?.clone();
+        let columns = self.resolve_columns(&header_record)?;
+        let headers = header_record.iter().map(|h| h.trim().to_string()).collect();
+
+        let mut records = Vec::new();
+        let mut parse_errors = 0;
+        let mut filtered = 0;
+        for result in rdr.records() {
+            let record = match result {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("Skipping malformed CSV row in {}: {}", self.path.display(), e);
+                    parse_errors += 1;
+                    continue;
+                }
+            };
+            let field = |name: &str| columns.get(name).and_then(|i| record.get(*i)).unwrap_or_default().trim();
+            if !filter.matches(field("symbol")) {
+                filtered += 1;
+                continue;
+            }
+            let mut expiration = field("expiration").to_string();
+            if let Some(format) = &self.config.expiration_format {
+                match parse_expiration(&expiration) {
+                    Some(date) => expiration = date.format(format).to_string(),
+                    None => parse_errors += 1,
+                }
+            }
+            records.push(OptionRecord {
+                symbol: field("symbol").to_string(),
+                call_put: field("call_put").to_string(),
+                expiration,
+                strike_price: normalize_strike(parse_field(field("strike_price"), &mut parse_errors)),
+                volume: parse_field(field("volume"), &mut parse_errors),
+                matched: parse_field(field("matched"), &mut parse_errors),
+                routed: parse_field(field("routed"), &mut parse_errors),
+                bid_size: parse_field(field("bid_size"), &mut parse_errors),
+                bid_price: parse_field(field("bid_price"), &mut parse_errors),
+                ask_size: parse_field(field("ask_size"), &mut parse_errors),
+                ask_price: parse_field(field("ask_price"), &mut parse_errors),
+                last_price: parse_field(field("last_price"), &mut parse_errors),
+            });
+        }
+
+        Ok(CsvContent { headers, records, http_bytes, wire_bytes: http_bytes, parse_errors, filtered, source_url: None })
+    }
+}
+
+fn snapshot_time_from_name(path: &Path, config: &ImportConfig) -> Result<NaiveDateTime> {
+    let format = config.filename_format.as_deref()
+        .ok_or_else(|| anyhow!("{}: pass --snapshot or set import.filename_format", path.display()))?;
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    NaiveDateTime::parse_from_str(&stem, format)
+        .or_else(|_| NaiveDate::parse_from_str(&stem, format).map(|d| d.and_hms_opt(16, 15, 0).expect("valid time")))
+        .with_context(|| format!("File name {} does not match import.filename_format {}", stem, format))
+}
+
+#[async_trait]
+impl SnapshotSource for FileSource<'_> {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn markets(&self) -> Vec<String> {
+        vec![self.market()]
+    }
+
+    async fn snapshot_time(&self) -> Result<NaiveDateTime> {
+        Ok(self.snapshot_time)
+    }
+
+    // 列名由 [import.columns] 映射，映射不上时 parse 直接报错，不再单独检查格式
+    async fn fetch(&self, _market: &str, filter: &SymbolFilter) -> Result<MarketData> {
+        #[cfg(feature = "arrow")]
+        return self.read_cached(filter).map(MarketData::from);
+        #[cfg(not(feature = "arrow"))]
+        self.parse(filter).map(MarketData::from)
+    }
+}
+
+// 每个文件作为运行日志里的一个 market，按文件各自的快照时间写入
+pub async fn run(db: &Database, table: &SnapshotTable, config: &Config, args: &ImportArgs, token: &CancellationToken) -> Result<()> {
+    let filter = SymbolFilter::new(&config.filter)?;
+    let mut run_log = RunLog::start(db.postgres_opt(), config.job.as_deref()).await?;
+    let result = import_files(db, table, config, args, &filter, &mut run_log, token).await;
+    match &result {
+        Ok(()) => run_log.finish(Outcome::Success, None).await?,
+        Err(e) if shutdown::is_cancelled(e) => run_log.finish(Outcome::Cancelled, Some(e.to_string())).await?,
+        Err(e) => {
+            error!("Import failed: {:#}", e);
+            run_log.finish(Outcome::Failed, Some(format!("{:#}", e))).await?;
+        }
+    }
+    result
+}
+
+async fn import_files(
+    db: &Database,
+    table: &SnapshotTable,
+    config: &Config,
+    args: &ImportArgs,
+    filter: &SymbolFilter,
+    run_log: &mut RunLog,
+    token: &CancellationToken,
+) -> Result<()> {
+    let mirrors = Mirrors::connect(&config.mirror, &config.database, table).await?;
+    run_log.memory = Some(memory::limits(&args.insert_options(&config.memory)));
+    for path in &args.files {
+        let source = FileSource::new(path, &config.import, args.snapshot)?;
+        let snapshot_time = source.snapshot_time().await?;
+        for market in source.markets() {
+            let content = source.fetch(&market, filter).await?;
+            info!("Importing {} as snapshot {}.", market, snapshot_time);
+            let progress = run_log.start_market(&market, snapshot_time, &content, content.records.len() as u64, 0).await?;
+            let provenance = Provenance { market: args.market.as_deref().unwrap_or_default(), source_url: None };
+            let options = args.insert_options(&config.memory);
+            if mirrors.mirror_first()
+                && let Err(e) = mirrors.write(run_log, table, &market, &content.records, snapshot_time, &provenance, &options, token).await
+            {
+                progress.finish(if shutdown::is_cancelled(&e) { "cancelled" } else { "failed" }).await?;
+                return Err(e);
+            }
+            let insert = db.store.insert_records(table, &content.records, snapshot_time, &provenance, &options, &progress, token);
+            let rows_inserted = match insert.await {
+                Ok(rows_inserted) => rows_inserted,
+                Err(e) => {
+                    let status = if shutdown::is_cancelled(&e) { "cancelled" } else { "failed" };
+                    progress.finish(status).await?;
+                    if mirrors.mirror_first() {
+                        warn!("{} was already written to the mirrors, they stay ahead of the primary until it is imported again.", market);
+                    }
+                    return Err(e);
+                }
+            };
+            progress.finish("done").await?;
+            if !mirrors.mirror_first() {
+                mirrors.write(run_log, table, &market, &content.records, snapshot_time, &provenance, &options, token).await?;
+            }
+            if let Some(pool) = db.postgres_opt() {
+                // 导入写的是完整快照，即使开了变化检测也直接读快照表
+                let mut full = table.clone();
+                full.change_detection = false;
+                symbols::update(pool, &full, snapshot_time).await?;
+                if config.storage.expiration_calendar {
+                    expirations::update(pool, &full, snapshot_time).await?;
+                }
+            }
+            run_log.snapshot_time = run_log.snapshot_time.max(Some(snapshot_time));
+            run_log.markets.push(MarketStats {
+                market,
+                rows_fetched: content.records.len() as u64,
+                rows_inserted,
+                rows_filtered: content.filtered,
+                parse_errors: content.parse_errors,
+                http_bytes: content.http_bytes,
+                wire_bytes: content.wire_bytes,
+                ..Default::default()
+            });
+        }
+    }
+    mirrors.close().await;
+    maintenance::after_load(db, table, &config.maintenance, run_log, 0).await
+}
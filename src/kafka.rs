@@ -0,0 +1,179 @@
+use anyhow::{Context, Result, anyhow};
+use apache_avro::Schema;
+use apache_avro::writer::datum::GenericDatumWriter;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cboe::OptionRecord;
+use crate::sink::Sink;
+
+const BATCH_SIZE: usize = 500;
+
+const AVRO_SCHEMA: &str = r#"
+{
+    "type": "record",
+    "name": "OptionContract",
+    "namespace": "cboe",
+    "fields": [
+        {"name": "occ_symbol", "type": "string"},
+        {"name": "market", "type": "string"},
+        {"name": "snapshot_time", "type": "string"},
+        {"name": "symbol", "type": "string"},
+        {"name": "call_put", "type": "string"},
+        {"name": "expiration", "type": "string"},
+        {"name": "strike_price", "type": "double"},
+        {"name": "volume", "type": "long"},
+        {"name": "matched", "type": "long"},
+        {"name": "routed", "type": "long"},
+        {"name": "bid_size", "type": "long"},
+        {"name": "bid_price", "type": "double"},
+        {"name": "ask_size", "type": "long"},
+        {"name": "ask_price", "type": "double"},
+        {"name": "last_price", "type": "double"}
+    ]
+}
+"#;
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaFormat {
+    #[default]
+    Json,
+    Avro,
+}
+
+#[derive(Deserialize)]
+pub struct KafkaConfig {
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub format: KafkaFormat,
+    #[serde(default)]
+    pub gzip: bool,
+}
+
+#[derive(Serialize)]
+struct ContractMessage<'a> {
+    occ_symbol: String,
+    market: &'a str,
+    snapshot_time: String,
+    symbol: &'a str,
+    call_put: &'a str,
+    expiration: &'a str,
+    strike_price: f64,
+    volume: i64,
+    matched: i64,
+    routed: i64,
+    bid_size: i64,
+    bid_price: f64,
+    ask_size: i64,
+    ask_price: f64,
+    last_price: f64,
+}
+
+pub struct KafkaSink {
+    partitions: Vec<PartitionClient>,
+    format: KafkaFormat,
+    schema: Schema,
+    compression: Compression,
+}
+
+// FNV-1a，保证同一个合约总是落在同一个分区
+fn partition_for(key: &[u8], partitions: usize) -> usize {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in key {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash % partitions as u64) as usize
+}
+
+impl KafkaSink {
+    pub async fn new(config: &KafkaConfig) -> Result<KafkaSink> {
+        let client = ClientBuilder::new(config.brokers.clone())
+            .build()
+            .await
+            .context("Failed to connect to Kafka")?;
+        let topic = client.list_topics().await?
+            .into_iter()
+            .find(|t| t.name == config.topic)
+            .ok_or_else(|| anyhow!("Kafka topic {} does not exist", config.topic))?;
+        let mut partitions = Vec::new();
+        for partition in topic.partitions {
+            partitions.push(client.partition_client(config.topic.clone(), partition, UnknownTopicHandling::Retry).await?);
+        }
+        Ok(KafkaSink {
+            partitions,
+            format: config.format,
+            schema: Schema::parse_str(AVRO_SCHEMA)?,
+            compression: if config.gzip { Compression::Gzip } else { Compression::NoCompression },
+        })
+    }
+
+    fn encode(&self, avro: Option<&GenericDatumWriter>, message: &ContractMessage) -> Result<Vec<u8>> {
+        match avro {
+            Some(writer) => Ok(writer.write_ser_to_vec(message)?),
+            None => Ok(serde_json::to_vec(message)?),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn write(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> Result<()> {
+        let snapshot = snapshot_time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let timestamp = snapshot_time.and_utc();
+        let mut by_partition: HashMap<usize, Vec<Record>> = HashMap::new();
+        let avro = match self.format {
+            KafkaFormat::Avro => Some(GenericDatumWriter::builder(&self.schema).build()?),
+            KafkaFormat::Json => None,
+        };
+
+        for rec in records {
+            let message = ContractMessage {
+                occ_symbol: rec.occ_symbol(),
+                market,
+                snapshot_time: snapshot.clone(),
+                symbol: &rec.symbol,
+                call_put: &rec.call_put,
+                expiration: &rec.expiration,
+                strike_price: rec.strike_price,
+                volume: rec.volume,
+                matched: rec.matched,
+                routed: rec.routed,
+                bid_size: rec.bid_size,
+                bid_price: rec.bid_price,
+                ask_size: rec.ask_size,
+                ask_price: rec.ask_price,
+                last_price: rec.last_price,
+            };
+            let key = message.occ_symbol.as_bytes().to_vec();
+            let partition = partition_for(&key, self.partitions.len());
+            by_partition.entry(partition).or_default().push(Record {
+                key: Some(key),
+                value: Some(self.encode(avro.as_ref(), &message)?),
+                headers: BTreeMap::new(),
+                timestamp,
+            });
+        }
+
+        for (partition, records) in by_partition {
+            let client = &self.partitions[partition];
+            let mut records = records.into_iter().peekable();
+            while records.peek().is_some() {
+                let batch: Vec<Record> = records.by_ref().take(BATCH_SIZE).collect();
+                client.produce(batch, self.compression).await?;
+            }
+        }
+        Ok(())
+    }
+}
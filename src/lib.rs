@@ -0,0 +1,65 @@
+//! CBOE 期权快照的抓取和解析，只需要内存里的数据时不用开启任何数据库 feature
+#[cfg(feature = "arrow")]
+pub mod batch;
+#[cfg(feature = "arrow")]
+pub mod cache;
+pub mod calendar;
+pub mod cboe;
+pub mod chain;
+pub mod classify;
+pub mod clock;
+pub mod filter;
+pub mod http;
+pub mod source;
+pub mod surface;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+
+pub use cboe::{MARKETS, OptionRecord};
+pub use chain::{Expiry, OptionChain, StrikeRow};
+pub use clock::{Clock, SystemClock};
+pub use filter::{FilterConfig, SymbolFilter};
+pub use http::{Fetcher, HttpConfig, HttpFetcher};
+pub use surface::{Smile, Surface};
+
+// 一个市场的一次快照
+pub struct MarketSnapshot {
+    pub market: String,
+    pub snapshot_time: NaiveDateTime,
+    pub records: Vec<OptionRecord>,
+    pub parse_errors: u64,
+    pub http_bytes: u64,
+    pub wire_bytes: u64,
+}
+
+// 用默认的 HTTP 配置抓取指定市场（空表示全部市场）的最新快照
+pub async fn fetch_all(markets: &[&str]) -> Result<Vec<MarketSnapshot>> {
+    let fetcher = Fetcher::new(&HttpConfig::default())?;
+    let filter = SymbolFilter::default();
+    fetch_stream(&fetcher, markets, &filter).await?.try_collect().await
+}
+
+// 先读取页面上的快照时间，之后每个市场下载解析完成就产出一个，不必等全部市场下载完
+pub async fn fetch_stream<'a>(
+    fetcher: &'a dyn HttpFetcher,
+    markets: &'a [&'a str],
+    filter: &'a SymbolFilter,
+) -> Result<impl Stream<Item = Result<MarketSnapshot>> + 'a> {
+    let snapshot_time = cboe::get_page_content_last_update_time(fetcher).await?;
+    let markets = if markets.is_empty() { &MARKETS[..] } else { markets };
+    Ok(stream::iter(markets).then(move |market| async move {
+        let content = cboe::get_csv_content(fetcher, market, filter).await?;
+        Ok(MarketSnapshot {
+            market: market.to_string(),
+            snapshot_time,
+            records: content.records,
+            parse_errors: content.parse_errors,
+            http_bytes: content.http_bytes,
+            wire_bytes: content.wire_bytes,
+        })
+    }))
+}
@@ -1,159 +1,607 @@
-use anyhow::Result;
-use chrono::NaiveDateTime;
-use log::{info, error};
-use regex::Regex;
+mod alerts;
+mod baseline;
+mod cfe;
+mod changes;
+mod cli;
+mod config;
+mod consolidate;
+mod datasets;
+mod db;
+mod delayed;
+mod diff;
+mod drift;
+mod events;
+mod exit;
+mod expirations;
+mod expiry;
+mod export;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod healthcheck;
+mod hive;
+mod import;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod maintenance;
+mod memory;
+mod mirror;
+#[cfg(feature = "mysql")]
+mod mysql;
+mod notify;
+mod output;
+mod preview;
+mod progress;
+mod query;
+mod ratelimit;
+#[cfg(feature = "redis")]
+mod redis_cache;
+mod report;
+mod resume;
+mod retention;
+mod run_log;
+mod secrets;
+mod serve;
+mod shutdown;
+mod sink;
+mod sqlite;
+mod storage;
+mod surfaces;
+mod symbols;
+mod telemetry;
+mod timescale;
+mod validate;
+mod verify;
+
+use anyhow::{Context, Result, anyhow, bail};
+use cboe_snapshot::{calendar, cboe, chain, clock, filter, http, source, surface};
+use clap::Parser;
+use log::{error, info, warn};
 use reqwest::Client;
-use sqlx::{PgPool, Row};
-use std::io::Cursor;
-use csv::ReaderBuilder;
-use chrono_tz::{America};
-
-const PAGE_URL: &str = "https://www.cboe.com/us/options/market_statistics/symbol_data/?mkt=cone";
-const URLS: [&str; 4] = [
-    "https://www.cboe.com/us/options/market_statistics/symbol_data/csv/?mkt=cone",
-    "https://www.cboe.com/us/options/market_statistics/symbol_data/csv/?mkt=opt",
-    "https://www.cboe.com/us/options/market_statistics/symbol_data/csv/?mkt=ctwo",
-    "https://www.cboe.com/us/options/market_statistics/symbol_data/csv/?mkt=exo"
-];
-
-struct OptionRecord {
-    symbol: String,
-    call_put: String,
-    expiration: String,
-    strike_price: f64,
-    volume: i64,
-    matched: i64,
-    routed: i64,
-    bid_size: i64,
-    bid_price: f64,
-    ask_size: i64,
-    ask_price: f64,
-    last_price: f64,
-}
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span, field, info_span};
+
+use alerts::AlertDetector;
+use baseline::BaselineGuard;
+use calendar::TradingCalendar;
+use cboe::CboeSource;
+use cli::{Cli, Command, DaemonArgs, RunArgs};
+use clock::{Clock, SystemClock};
+use config::Config;
+use drift::DriftDetector;
+use exit::FetchFailed;
+use filter::SymbolFilter;
+use http::{Fetcher, HttpFetcher};
+use mirror::Mirrors;
+use notify::Notifiers;
+use resume::Resume;
+use run_log::{MarketStats, Outcome, RunLog};
+use shutdown::Cancelled;
+use sink::Sinks;
+use source::SnapshotSource;
+use storage::{Database, Provenance, SnapshotTable};
+use validate::Validator;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     dotenv::dotenv().ok();
     env_logger::init();
+    let telemetry = telemetry::init()?;
 
-    let db_url = std::env::var("DATABASE_URL")?;
-    let pool = PgPool::connect(&db_url).await?;
-
-    let client = Client::new();
+    let cli = Cli::parse();
+    let base = Config::load(cli.config.as_deref())?;
+    let jobs = base.select_jobs(&cli.job)?;
+    // run / daemon 依次运行每个 job；其它子命令只能指定一个 job，不指定时用顶层配置
+    let targets: Vec<&Config> = if jobs.is_empty() { vec![&base] } else { jobs.iter().collect() };
+    let command = cli.command.unwrap_or(Command::Run(RunArgs::default()));
+    if cli.job.len() > 1 && !matches!(command, Command::Run(_) | Command::Daemon(_)) {
+        bail!("Only run and daemon accept more than one --job");
+    }
+    let config = match cli.job.as_slice() {
+        [_] => targets[0],
+        _ => &base,
+    };
+    let table = SnapshotTable::from_config(&config.storage)?;
+    // SQLite 在连接时建表，run / daemon 的每个 job 可能写不同的表
+    let mut tables = vec![table.clone()];
+    for target in &targets {
+        let job_table = SnapshotTable::from_config(&target.storage)?;
+        // 只按表名去重，各 job 的 change_detection 可以不同
+        if tables.iter().all(|t| t.to_string() != job_table.to_string()) {
+            tables.push(job_table);
+        }
+    }
+    for table in tables.iter().filter(|t| !t.is_default()) {
+        info!("Using snapshot table {}.", table);
+    }
 
-    let last_update_time = get_page_content_last_update_time(&client).await?;
-    let max_updated_time = get_max_updated_date(&pool).await?;
+    let db_url = secrets::database_url().await?;
+    let db = match Database::connect(&db_url, &config.database, &tables).await {
+        Ok(db) => db,
+        Err(e) => return exit::report(e),
+    };
+    let token = shutdown::install();
+    let clock = SystemClock;
 
-    if Some(last_update_time) == max_updated_time {
-        info!("Already updated, no need to update.");
-        return Ok(());
+    let mut skipped = false;
+    let result = match command {
+        Command::Run(args) => {
+            let fetchers = targets.iter().map(|c| Fetcher::new(&c.http)).collect::<Result<Vec<_>>>()?;
+            run_jobs(&db, &targets, &fetchers, &clock, &args, &token).await
+                .map(|outcome| skipped = outcome == Outcome::Skipped)
+        }
+        Command::Daemon(args) => daemon(&db, &base, &targets, &clock, &args, &token).await,
+        Command::Status(args) => run_log::print_recent_runs(db.postgres()?, args.limit, config.job.as_deref()).await,
+        Command::Prune(args) => {
+            let days = args.days.or(config.retention.days)
+                .ok_or_else(|| anyhow!("No retention window: pass --days or set retention.days"))?;
+            let mode = args.mode.unwrap_or(config.retention.mode);
+            retention::prune(db.postgres()?, &table, config, days, mode, args.dry_run).await
+        }
+        Command::PurgeExpired(args) => expiry::run(db.postgres()?, &table, config, &args).await,
+        Command::TimescaleSetup => timescale::setup(db.postgres()?, &table, &config.timescale).await,
+        Command::Query(args) => query::run(db.postgres()?, &table, &config.analytics, &args).await,
+        Command::Export(args) => export::run(db.postgres()?, &table, &args).await,
+        Command::Serve(args) => serve::serve(db.postgres()?.clone(), table.clone(), &args.bind, &config.events, config.analytics, &config.api, token.clone()).await,
+        Command::Consolidate(args) => {
+            let date = args.date.unwrap_or_else(|| clock.now_new_york().date());
+            consolidate::consolidate(db.postgres()?, &table, date, args.prune_intraday).await.map(|_| ())
+        }
+        Command::Import(args) => import::run(&db, &table, config, &args, &token).await,
+        Command::Diff(args) => diff::run(db.postgres()?, &table, config, &args).await,
+        Command::Report(args) => report::run(db.postgres()?, &table, config, &args).await,
+        Command::Verify(args) => verify::run(db.postgres()?, &table, &args).await,
+        Command::RebuildExpirations(args) => expirations::rebuild(db.postgres()?, &table, &args).await,
+        Command::Quotes(args) => {
+            delayed::run(db.postgres()?, &table, &Fetcher::new(&config.http)?, &config.delayed_quotes, &args.symbols, &token).await
+        }
+    };
+    db.close().await;
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
     }
 
-    for url in URLS {
-        let records = get_csv_content(&client, url).await?;
-        insert_records(&pool, &records, last_update_time).await?;
+    match result {
+        Ok(()) if skipped => Ok(ExitCode::from(exit::SKIPPED)),
+        Ok(()) => Ok(ExitCode::from(exit::LOADED)),
+        Err(e) => exit::report(e),
     }
+}
 
-    Ok(())
+// 交易日历取顶层配置，所有 job 在同一个循环里依次运行
+async fn daemon(db: &Database, config: &Config, jobs: &[&Config], clock: &dyn Clock, args: &DaemonArgs, token: &CancellationToken) -> Result<()> {
+    let calendar = TradingCalendar::new(&config.calendar)?;
+    // 熔断状态需要跨多次运行保留，所以 daemon 为每个 job 只创建一个 Fetcher
+    let fetchers = jobs.iter().map(|c| Fetcher::new(&c.http)).collect::<Result<Vec<_>>>()?;
+    let interval = Duration::from_secs(args.interval_secs);
+    info!("Daemon started, checking every {}s during trading hours.", args.interval_secs);
+    loop {
+        let now = clock.now_new_york();
+        if !calendar.is_open(now) {
+            let next_open = calendar.next_open(now);
+            info!("Market closed, sleeping until {}.", next_open);
+            let wait = (next_open - now).to_std().unwrap_or(interval);
+            token.run_until_cancelled(tokio::time::sleep(wait)).await.ok_or(Cancelled)?;
+            continue;
+        }
+        match run_jobs(db, jobs, &fetchers, clock, &args.run, token).await {
+            Err(e) if shutdown::is_cancelled(&e) => return Err(e),
+            Err(e) => error!("Daemon run failed: {:#}", e),
+            Ok(_) => {}
+        }
+        token.run_until_cancelled(tokio::time::sleep(interval)).await.ok_or(Cancelled)?;
+    }
 }
 
-async fn get_page_content_last_update_time(client: &Client) -> Result<NaiveDateTime> {
-    let resp = client.get(PAGE_URL).send().await?.text().await?;
-    let re = Regex::new(r"last updated (\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2})")?;
-    if let Some(caps) = re.captures(&resp) {
-        let dt = NaiveDateTime::parse_from_str(&caps[1], "%Y-%m-%d %H:%M:%S")?;
-        info!("Last update time: {}", dt);
-        Ok(dt)
-    } else {
-        error!("Failed to parse last update time.");
-        Err(anyhow::anyhow!("Parse error"))
+// 一个 job 失败不影响后面的 job，返回第一个错误；全部跳过时才算跳过
+async fn run_jobs(db: &Database, jobs: &[&Config], fetchers: &[Fetcher], clock: &dyn Clock, args: &RunArgs, token: &CancellationToken) -> Result<Outcome> {
+    let mut outcomes = Vec::with_capacity(jobs.len());
+    let mut first_error = None;
+    for (config, fetcher) in jobs.iter().zip(fetchers) {
+        match run(db, config, args, fetcher, fetcher.client(), clock, token).await {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) if shutdown::is_cancelled(&e) => return Err(e),
+            Err(e) => match &config.job {
+                Some(job) if jobs.len() > 1 => {
+                    error!("Job {} failed: {:#}", job, e);
+                    first_error.get_or_insert(e.context(format!("Job {} failed", job)));
+                }
+                _ => {
+                    first_error.get_or_insert(e);
+                }
+            },
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None if outcomes.iter().all(|o| *o == Outcome::Skipped) => Ok(Outcome::Skipped),
+        None => Ok(Outcome::Success),
     }
 }
 
-async fn get_max_updated_date(pool: &PgPool) -> Result<Option<NaiveDateTime>> {
-    let row = sqlx::query("SELECT max(last_updated_time) FROM t_options_cboe_snapshot")
-        .fetch_one(pool)
-        .await?;
-    let max_time: Option<NaiveDateTime> = row.try_get(0)?;
-    Ok(max_time)
+// 存储（db.store）、HTTP 和时钟都是注入的：命令行里是各后端的 store、每个 job 的 Fetcher 和 SystemClock，
+// 测试里换成内存替身；client 只用于通知和健康检查
+#[allow(clippy::too_many_arguments)]
+async fn run(
+    db: &Database,
+    config: &Config,
+    args: &RunArgs,
+    fetcher: &dyn HttpFetcher,
+    client: &Client,
+    clock: &dyn Clock,
+    token: &CancellationToken,
+) -> Result<Outcome> {
+    if args.skip_if_closed {
+        let calendar = TradingCalendar::new(&config.calendar)?;
+        let now = clock.now_new_york();
+        if !calendar.is_open(now) {
+            info!("Market closed at {}, next open {}. Skipping.", now.format("%Y-%m-%d %H:%M"), calendar.next_open(now));
+            return Ok(Outcome::Skipped);
+        }
+    }
+
+    // 每个 job 用自己的快照表，启动时已经检查过配置
+    let table = SnapshotTable::from_config(&config.storage)?;
+    if args.dry_run {
+        return preview::run(db, &table, config, &CboeSource::new(fetcher), args).await.map(|_| Outcome::Success);
+    }
+    let notifiers = Notifiers::from_config(&config.notify, client)?;
+
+    let mut run_log = RunLog::start(db.postgres_opt(), config.job.as_deref()).await?;
+    let source = CboeSource::new(fetcher);
+    let load_span = info_span!("load", run_id = run_log.id(), snapshot_time = field::Empty);
+    let result = match load(db, &table, config, &source, fetcher, args, &notifiers, &mut run_log, token).instrument(load_span).await {
+        // 市场统计和期权快照的更新时间无关，快照没变时也照常抓取
+        Ok(outcome) if !config.datasets.is_empty() => {
+            datasets::load_all(db.postgres()?, &config.datasets, fetcher, &mut run_log, token).await.map(|_| outcome)
+        }
+        other => other,
+    };
+    let outcome = match &result {
+        Ok(outcome) => {
+            run_log.finish(*outcome, None).await?;
+            *outcome
+        }
+        Err(e) if shutdown::is_cancelled(e) => {
+            run_log.finish(Outcome::Cancelled, Some(e.to_string())).await?;
+            Outcome::Cancelled
+        }
+        Err(e) => {
+            error!("Run failed: {:#}", e);
+            run_log.finish(Outcome::Failed, Some(format!("{:#}", e))).await?;
+            Outcome::Failed
+        }
+    };
+    notifiers.notify(&run_log, outcome).await;
+    if config.format_drift.notify && !run_log.format_drift.is_empty() {
+        notifiers.send(&drift::notification(&run_log)).await;
+    }
+    healthcheck::ping(client, &config.healthcheck, &run_log, outcome).await;
+    events::publish(db.postgres_opt(), &table, &config.events, &config.analytics, &run_log, outcome).await;
+    if let Err(e) = result {
+        let loaded = run_log.markets.len();
+        if loaded > 0 && !shutdown::is_cancelled(&e) {
+            return Err(e.context(exit::PartialLoad { loaded }));
+        }
+        return Err(e);
+    }
+
+    if outcome == Outcome::Success && config.retention.after_load
+        && let Some(days) = config.retention.days
+    {
+        retention::prune(db.postgres()?, &table, config, days, config.retention.mode, false).await?;
+    }
+    Ok(outcome)
 }
-async fn get_csv_content(client: &Client, url: &str) -> Result<Vec<OptionRecord>> {
-    info!("Fetching CSV from {}", url);
-    let resp = client.get(url).send().await?.bytes().await?;
-    let cursor = Cursor::new(resp);
-
-    let mut rdr = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(cursor);
-
-    let mut records = Vec::new();
-    for result in rdr.records() {
-        let record = result?;
-        if record.len() < 12 {
+
+// fetcher 用于期货、市场统计等附加数据，期权快照本身来自 source
+#[allow(clippy::too_many_arguments)]
+async fn load(
+    db: &Database,
+    table: &SnapshotTable,
+    config: &Config,
+    source: &dyn SnapshotSource,
+    fetcher: &dyn HttpFetcher,
+    args: &RunArgs,
+    notifiers: &Notifiers,
+    run_log: &mut RunLog,
+    token: &CancellationToken,
+) -> Result<Outcome> {
+    let filter = SymbolFilter::new(&args.filter(&config.filter))?;
+    let sinks = Sinks::from_config(&config.sinks).await?;
+    run_log.symbol_filter = filter.description().map(|d| d.to_string());
+    run_log.memory = Some(memory::limits(&args.insert_options(&config.memory)));
+
+    let scrape = source.snapshot_time().instrument(info_span!("scrape_page", source = source.name()));
+    let scraped = token.run_until_cancelled(scrape).await.ok_or(Cancelled)?;
+    // 页面措辞变化时取快照时间会失败，签名要在返回错误之前比较
+    let drift = match (config.format_drift.enabled, db.postgres_opt()) {
+        (true, Some(pool)) => Some(DriftDetector::new(pool, run_log.id())),
+        _ => None,
+    };
+    if let (Some(drift), Some(signature)) = (&drift, source.format_signature()) {
+        run_log.format_drift.extend(drift.observe(drift::PAGE, &[signature]).await?);
+    }
+    let last_update_time = scraped.context(FetchFailed)?;
+    run_log.snapshot_time = Some(last_update_time);
+    Span::current().record("snapshot_time", last_update_time.to_string());
+    // 多个 job 可能写同一张表，定义了 job 时按该 job 自己成功加载过的快照判断
+    let mut max_updated_time = match (&config.job, db.postgres_opt()) {
+        (Some(job), Some(pool)) => run_log::last_loaded(pool, job).await?,
+        _ => db.store.max_updated_time(table).await?,
+    };
+    let change_pool = match table.change_detection {
+        true => Some(db.postgres().context("[storage] change_detection requires Postgres")?),
+        false => None,
+    };
+    let delta_pool = match config.storage.volume_deltas {
+        true => Some(db.postgres().context("[storage] volume_deltas requires Postgres")?),
+        false => None,
+    };
+    // 变化检测下快照没有任何变化时不会写入新行，最新快照时间以状态表为准
+    if let Some(pool) = change_pool
+        && config.job.is_none()
+    {
+        max_updated_time = max_updated_time.max(changes::last_seen(pool).await?);
+    }
+
+    // 中断的运行已经写入了部分市场，表里的最大时间等于本次快照，不能按它判断已经加载
+    let resume = match args.resume {
+        true => Resume::find(db.postgres().context("--resume requires Postgres")?, config.job.as_deref(), last_update_time, run_log.id()).await?,
+        false => None,
+    };
+    run_log.resumed_from = resume.as_ref().map(|r| r.run_id);
+
+    if Some(last_update_time) == max_updated_time && resume.is_none() {
+        info!("Already updated, no need to update.");
+        return Ok(Outcome::Skipped);
+    }
+
+    if config.storage.partitioned {
+        db::ensure_partition(db.postgres()?, table, last_update_time.date()).await?;
+    }
+
+    let validator = if config.validation.enabled {
+        Some(Validator::new(db.postgres_opt(), table, &config.validation, last_update_time).await?)
+    } else {
+        None
+    };
+    let detector = if config.alerts.enabled {
+        Some(AlertDetector::new(db.postgres()?, table, &config.alerts, last_update_time).await?)
+    } else {
+        None
+    };
+    // 变化检测下每次只提交有变化的合约，已提交行数不能作为行数基线
+    let baseline = if change_pool.is_some() && config.baseline.enabled {
+        warn!("Row count baseline is disabled while storage.change_detection is on.");
+        None
+    } else {
+        BaselineGuard::new(db.postgres_opt(), &config.baseline, config.job.as_deref())
+    };
+    let mut alerts = Vec::new();
+    let mirrors = Mirrors::connect(&config.mirror, &config.database, table).await?;
+    let mut surface_records = Vec::new();
+
+    for market in source.markets() {
+        let market = market.as_str();
+        if token.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        if let Some(done_in) = resume.as_ref().and_then(|r| r.done_in(market)) {
+            info!("{} was already loaded by run {}, skipping.", market, done_in);
             continue;
         }
-
-        let option_record = OptionRecord {
-            symbol: record[0].to_string(),
-            call_put: record[1].to_string(),
-            expiration: record[2].to_string(),
-            strike_price: record[3].parse().unwrap_or(0.0),
-            volume: record[4].parse().unwrap_or(0),
-            matched: record[5].parse().unwrap_or(0),
-            routed: record[6].parse().unwrap_or(0),
-            bid_size: record[7].parse().unwrap_or(0),
-            bid_price: record[8].parse().unwrap_or(0.0),
-            ask_size: record[9].parse().unwrap_or(0),
-            ask_price: record[10].parse().unwrap_or(0.0),
-            last_price: record[11].parse().unwrap_or(0.0),
+        let fetch_span = info_span!("fetch_market", market, rows = field::Empty, bytes = field::Empty, parse_errors = field::Empty);
+        let fetch = source.fetch(market, &filter).instrument(fetch_span.clone());
+        let mut content = token.run_until_cancelled(fetch).await.ok_or(Cancelled)?.context(FetchFailed)?;
+        if let Some(drift) = &drift {
+            run_log.format_drift.extend(drift.observe(market, &content.fields).await?);
+        }
+        fetch_span.record("rows", content.records.len());
+        fetch_span.record("bytes", content.http_bytes);
+        fetch_span.record("parse_errors", content.parse_errors);
+        let rows_fetched = content.records.len() as u64;
+        let mut rows_skipped = 0;
+        if args.skip_inactive {
+            content.records.retain(|r| !r.is_inactive());
+            rows_skipped = rows_fetched - content.records.len() as u64;
+            info!("Skipped {} inactive rows from {}.", rows_skipped, market);
+        }
+        let mut dq_issues = 0;
+        if let Some(validator) = &validator {
+            let issues = validator.check(&content.records);
+            dq_issues = issues.len() as u64;
+            if config.validation.store_issues && !issues.is_empty() {
+                validate::store_issues(db.postgres()?, run_log.id(), market, last_update_time, &issues).await?;
+            }
+            validator.enforce(market, &issues, content.records.len())?;
+        }
+        if let Some(baseline) = &baseline {
+            baseline.check(market, content.records.len() as u64).await?;
+        }
+        let changes = match change_pool {
+            Some(pool) => Some(changes::detect(pool, market, &content.records).await?),
+            None => None,
         };
+        if let Some(changes) = &changes {
+            rows_skipped += changes.unchanged;
+        }
+        let records = changes.as_ref().map_or(content.records.as_slice(), |c| c.changed.as_slice());
+        let offset = resume.as_ref().map_or(0, |r| r.offset(market, records.len() as u64));
+        let progress = run_log.start_market(market, last_update_time, &content, records.len() as u64, offset).await?;
+        let insert_span = info_span!("insert_market", market, rows = content.records.len(), inserted = field::Empty);
+        let options = args.insert_options(&config.memory);
+        let source_url = content.source_url.as_deref().filter(|_| config.storage.source_url);
+        let provenance = Provenance { market, source_url };
+        // 镜像库没有变化检测的状态表，始终写入完整快照；mirror_first 先写镜像，失败时主库不写入，下次运行整体重试
+        if mirrors.mirror_first()
+            && let Err(e) = mirrors.write(run_log, table, market, &content.records, last_update_time, &provenance, &options, token).await
+        {
+            progress.finish(if shutdown::is_cancelled(&e) { "cancelled" } else { "failed" }).await?;
+            return Err(e);
+        }
+        let insert = db.store.insert_records(table, &records[offset as usize..], last_update_time, &provenance, &options, &progress, token);
+        let rows_inserted = match insert.instrument(insert_span.clone()).await {
+            Ok(rows_inserted) => {
+                insert_span.record("inserted", rows_inserted);
+                rows_inserted
+            }
+            Err(e) => {
+                let status = if shutdown::is_cancelled(&e) { "cancelled" } else { "failed" };
+                progress.finish(status).await?;
+                if mirrors.mirror_first() {
+                    warn!("{} was already written to the mirrors, they stay ahead of the primary until the next run.", market);
+                }
+                return Err(e);
+            }
+        };
+        // 对这次写入主库的行计算，verify 按 (market, last_updated_time) 从数据库重新计算比较
+        let checksum = verify::checksum(records);
+        progress.record_checksum(&checksum).await?;
+        progress.finish("done").await?;
+        // 变化检测的状态要在这之后更新，计算增量时还要用到合约上一次变化的时间
+        if let Some(pool) = delta_pool {
+            db::update_volume_deltas(pool, table, market, last_update_time).await?;
+        }
+        if let (Some(pool), Some(changes)) = (change_pool, &changes) {
+            changes::record(pool, market, last_update_time, changes).await?;
+        }
+        if !mirrors.mirror_first() {
+            mirrors.write(run_log, table, market, &content.records, last_update_time, &provenance, &options, token).await?;
+        }
+        sinks.write(market, last_update_time, &content.records).await;
+        let wanted: Vec<_> = content.records.iter().filter(|r| config.surface.wants(r)).cloned().collect();
+        if !wanted.is_empty() {
+            surface_records.push((market.to_string(), wanted));
+        }
+        if let Some(detector) = &detector {
+            let found = detector.detect(market, &content.records);
+            alerts::store_alerts(db.postgres()?, run_log.id(), last_update_time, &found).await?;
+            alerts.extend(found);
+        }
+        run_log.markets.push(MarketStats {
+            market: market.to_string(),
+            rows_fetched,
+            rows_inserted,
+            rows_filtered: content.filtered,
+            rows_skipped,
+            parse_errors: content.parse_errors,
+            dq_issues,
+            http_bytes: content.http_bytes,
+            wire_bytes: content.wire_bytes,
+            checksum: Some(checksum.digest),
+        });
+    }
+    mirrors.close().await;
+
+    if config.storage.latest_table {
+        db::refresh_latest(db.postgres()?, table, last_update_time).await?;
+    }
+    if let Some(pool) = db.postgres_opt() {
+        symbols::update(pool, table, last_update_time).instrument(info_span!("update_symbols")).await?;
+    }
+    if config.storage.expiration_calendar {
+        let pool = db.postgres().context("[storage] expiration_calendar requires Postgres")?;
+        expirations::update(pool, table, last_update_time).instrument(info_span!("update_expirations")).await?;
+    }
+    if let Some(pool) = db.postgres_opt()
+        && !surface_records.is_empty()
+    {
+        surfaces::fit(pool, &config.surface, &config.analytics, last_update_time, &surface_records).instrument(info_span!("fit_surfaces")).await?;
+    }
+
+    if config.futures.enabled {
+        let content = token.run_until_cancelled(cfe::get_csv_content(fetcher, &config.futures.url, &filter)).await.ok_or(Cancelled)??;
+        let rows_inserted = cfe::insert_records(db.postgres()?, &content.records, last_update_time, token).await?;
+        run_log.markets.push(MarketStats {
+            market: "cfe".to_string(),
+            rows_fetched: content.records.len() as u64,
+            rows_inserted,
+            rows_filtered: content.filtered,
+            parse_errors: content.parse_errors,
+            http_bytes: content.http_bytes,
+            wire_bytes: content.wire_bytes,
+            ..Default::default()
+        });
+    }
+
+    if config.alerts.notify && !alerts.is_empty() {
+        notifiers.send(&alerts::notification(last_update_time, &alerts, config.alerts.notify_limit)).await;
+    }
+
+    let mut rows_removed = 0;
+    if args.deep_clean {
+        rows_removed = db.store.clean_duplicate_data(table).instrument(info_span!("clean_duplicates")).await?;
+    }
 
-        records.push(option_record);
+    if config.expiry.flag_after_load
+        && let Some(pool) = db.postgres_opt()
+    {
+        expiry::flag(pool, table).instrument(info_span!("flag_expired")).await?;
     }
 
-    Ok(records)
+    maintenance::after_load(db, table, &config.maintenance, run_log, rows_removed).instrument(info_span!("maintenance")).await?;
+
+    Ok(Outcome::Success)
 }
-async fn insert_records(pool: &PgPool, records: &[OptionRecord], last_updated_time: NaiveDateTime) -> Result<()> {
-    let mut tx = pool.begin().await?;
-    let utc_now = chrono::Utc::now();
-    let etl_in_dt = utc_now.with_timezone(&America::New_York);
-
-    for rec in records {
-        sqlx::query(r#"
-            INSERT INTO t_options_cboe_snapshot
-            (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
-            ON CONFLICT (symbol, call_put, expiration, strike_price, last_updated_time)
-            DO UPDATE SET
-                volume = EXCLUDED.volume,
-                matched = EXCLUDED.matched,
-                routed = EXCLUDED.routed,
-                bid_size = EXCLUDED.bid_size,
-                bid_price = EXCLUDED.bid_price,
-                ask_size = EXCLUDED.ask_size,
-                ask_price = EXCLUDED.ask_price,
-                last_price = EXCLUDED.last_price,
-                etl_in_dt = EXCLUDED.etl_in_dt
-        "#)
-            .bind(&rec.symbol)
-            .bind(&rec.call_put)
-            .bind(&rec.expiration)
-            .bind(rec.strike_price)
-            .bind(rec.volume)
-            .bind(rec.matched)
-            .bind(rec.routed)
-            .bind(rec.bid_size)
-            .bind(rec.bid_price)
-            .bind(rec.ask_size)
-            .bind(rec.ask_price)
-            .bind(rec.last_price)
-            .bind(last_updated_time)
-            .bind(etl_in_dt)
-            .execute(&mut *tx)
-            .await?;
-    }
-
-    tx.commit().await?;
-    info!("Inserted {} records.", records.len());
-    Ok(())
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cboe_snapshot::MARKETS;
+    use cboe_snapshot::testing::{FixedClock, MemoryFetcher};
+    use chrono::NaiveDateTime;
+    use std::sync::Arc;
+    use storage::MemoryStore;
+
+    const CSV: &str = "Symbol,Call/Put,Expiration,Strike Price,Volume,Matched,Routed,Bid Size,Bid Price,Ask Size,Ask Price,Last Price\n\
+        SPX,C,2026-10-16,5800,10,8,2,5,12.5,7,13.0,12.8\n\
+        SPX,P,2026-10-16,5800,4,4,0,3,9.1,2,9.4,9.2\n";
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    // 页面显示 snapshot_time，四个市场都是同一份 CSV
+    fn fetcher(snapshot_time: NaiveDateTime) -> MemoryFetcher {
+        MARKETS.iter().fold(MemoryFetcher::new().with_snapshot_time(snapshot_time), |f, market| f.with_market_csv(market, CSV))
+    }
+
+    async fn run_at(db: &Database, fetcher: &MemoryFetcher, now: NaiveDateTime, args: &RunArgs) -> Result<Outcome> {
+        run(db, &Config::default(), args, fetcher, &Client::new(), &FixedClock::new_york(now), &CancellationToken::new()).await
+    }
+
+    #[tokio::test]
+    async fn skips_when_market_closed() {
+        let store = Arc::new(MemoryStore::default());
+        let db = Database::with_store(store.clone());
+        let fetcher = fetcher(at("2026-10-16 16:15:00"));
+        let args = RunArgs { skip_if_closed: true, ..Default::default() };
+        // 周日
+        assert!(run_at(&db, &fetcher, at("2026-10-18 11:00:00"), &args).await.unwrap() == Outcome::Skipped);
+        assert!(fetcher.requests().is_empty());
+        assert!(store.snapshot_times().is_empty());
+    }
+
+    #[tokio::test]
+    async fn loads_then_skips_the_same_snapshot() {
+        let now = at("2026-10-14 10:30:00");
+        let store = Arc::new(MemoryStore::default());
+        let db = Database::with_store(store.clone());
+        let fetcher = fetcher(now);
+        let args = RunArgs { skip_if_closed: true, ..Default::default() };
+
+        assert!(run_at(&db, &fetcher, now, &args).await.unwrap() == Outcome::Success);
+        let table = SnapshotTable::default();
+        for market in MARKETS {
+            let records = store.records(&table, now, market);
+            assert_eq!(records.iter().map(|r| r.call_put.as_str()).collect::<Vec<_>>(), ["C", "P"]);
+        }
+
+        // 页面上的快照时间没变，只请求页面不再下载 CSV
+        assert!(run_at(&db, &fetcher, now, &args).await.unwrap() == Outcome::Skipped);
+        assert_eq!(fetcher.requests().iter().filter(|url| url.as_str() == cboe::csv_url("opt")).count(), 1);
+        assert_eq!(store.snapshot_times(), vec![now]);
+    }
 }
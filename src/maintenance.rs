@@ -0,0 +1,103 @@
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::time::Instant;
+
+use crate::run_log::RunLog;
+use crate::storage::{Database, SnapshotTable};
+
+// 大批量写入和去重之后主动 ANALYZE / VACUUM，不等 autovacuum；写入加删除的行数不足 min_rows 时跳过
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    pub min_rows: u64,
+    pub analyze: bool,
+    pub vacuum: bool,
+    // 死元组占比超过该百分比时 REINDEX TABLE CONCURRENTLY
+    pub reindex_dead_pct: Option<f64>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        MaintenanceConfig {
+            enabled: false,
+            min_rows: 100_000,
+            analyze: true,
+            vacuum: false,
+            reindex_dead_pct: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Step {
+    pub step: &'static str,
+    pub secs: f64,
+}
+
+async fn timed(steps: &mut Vec<Step>, step: &'static str, pool: &PgPool, sql: String) -> Result<()> {
+    let started = Instant::now();
+    sqlx::query(&sql).execute(pool).await?;
+    let secs = started.elapsed().as_secs_f64();
+    info!("{} finished in {:.1}s.", sql, secs);
+    steps.push(Step { step, secs });
+    Ok(())
+}
+
+async fn dead_tuple_pct(pool: &PgPool, table: &SnapshotTable) -> Result<Option<f64>> {
+    let row = sqlx::query(r#"
+        SELECT n_live_tup, n_dead_tup FROM pg_stat_user_tables
+        WHERE relname = $1 AND schemaname = COALESCE($2, current_schema())
+    "#)
+        .bind(&table.name)
+        .bind(&table.schema)
+        .fetch_optional(pool)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let live: i64 = row.try_get("n_live_tup")?;
+    let dead: i64 = row.try_get("n_dead_tup")?;
+    Ok((live + dead > 0).then(|| dead as f64 / (live + dead) as f64 * 100.0))
+}
+
+// 各步骤耗时写入运行日志；VACUUM 不能放在事务里，这里直接在连接池上执行
+async fn run(pool: &PgPool, table: &SnapshotTable, config: &MaintenanceConfig, rows_changed: u64) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    if rows_changed < config.min_rows {
+        info!("{} rows changed, below maintenance.min_rows {}, skipping maintenance.", rows_changed, config.min_rows);
+        return Ok(steps);
+    }
+    // 先看死元组比例，VACUUM 之后统计会被清零
+    let dead_pct = match config.reindex_dead_pct {
+        Some(_) => dead_tuple_pct(pool, table).await?,
+        None => None,
+    };
+    match (config.vacuum, config.analyze) {
+        (true, true) => timed(&mut steps, "vacuum_analyze", pool, format!("VACUUM (ANALYZE) {}", table)).await?,
+        (true, false) => timed(&mut steps, "vacuum", pool, format!("VACUUM {}", table)).await?,
+        (false, true) => timed(&mut steps, "analyze", pool, format!("ANALYZE {}", table)).await?,
+        (false, false) => {}
+    }
+    if let (Some(threshold), Some(pct)) = (config.reindex_dead_pct, dead_pct)
+        && pct >= threshold
+    {
+        info!("Dead tuples at {:.1}% (threshold {}%), reindexing.", pct, threshold);
+        timed(&mut steps, "reindex", pool, format!("REINDEX TABLE CONCURRENTLY {}", table)).await?;
+    }
+    Ok(steps)
+}
+
+// 只对 Postgres 执行，其它后端直接跳过
+pub async fn after_load(db: &Database, table: &SnapshotTable, config: &MaintenanceConfig, run_log: &mut RunLog, rows_removed: u64) -> Result<()> {
+    let Some(pool) = db.postgres_opt().filter(|_| config.enabled) else {
+        return Ok(());
+    };
+    let steps = run(pool, table, config, run_log.rows_inserted() + rows_removed).await?;
+    if !steps.is_empty() {
+        run_log.maintenance = Some(serde_json::to_value(&steps)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::storage::InsertOptions;
+
+// RSS 超过上限的这个比例时批次减半，低于 GROW_AT 时逐步恢复
+const SHRINK_AT: f64 = 0.8;
+const GROW_AT: f64 = 0.5;
+
+// 小内存机器上的写入预算；max_rss_mb 为 0 时不做自适应。
+// 只约束写入阶段：市场的 CSV 整体下载、解析成记录后才开始切批次，这部分内存不受 max_rss_mb 限制
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub max_rss_mb: u64,
+    // 同时写入的批次数，0 表示等于 --insert-workers
+    pub max_in_flight_batches: usize,
+    // 切好等待写入的批次队列长度
+    pub queue_batches: usize,
+    // 设置了 max_rss_mb 且没有 --commit-batch-size 时的初始批次大小
+    pub batch_size: usize,
+    pub min_batch_size: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig { max_rss_mb: 0, max_in_flight_batches: 0, queue_batches: 2, batch_size: 50_000, min_batch_size: 1_000 }
+    }
+}
+
+// /proc/self/status 里的 VmRSS（当前）或 VmHWM（峰值），单位字节；非 Linux 时为 None
+fn proc_status(key: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with(key))?;
+    let kb: u64 = line[key.len()..].trim_start_matches(':').trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+pub fn rss_bytes() -> Option<u64> {
+    proc_status("VmRSS")
+}
+
+pub fn peak_rss_mb() -> Option<u64> {
+    proc_status("VmHWM").map(|b| b / 1024 / 1024)
+}
+
+// 本次运行实际使用的限制，写入运行日志
+pub fn limits(options: &InsertOptions) -> serde_json::Value {
+    let memory = &options.memory;
+    let batch_size = options.batch_size.or((memory.max_rss_mb > 0).then_some(memory.batch_size));
+    let limits = json!({
+        "max_rss_mb": (memory.max_rss_mb > 0).then_some(memory.max_rss_mb),
+        "in_flight_batches": options.in_flight(),
+        "queue_batches": memory.queue_batches.max(1),
+        "batch_size": batch_size,
+        "min_batch_size": (memory.max_rss_mb > 0).then_some(memory.min_batch_size.max(1)),
+    });
+    info!("Memory limits: {}", limits);
+    if memory.max_rss_mb > 0 && rss_bytes().is_none() {
+        warn!("Cannot read RSS on this platform, [memory] max_rss_mb only caps the batch size.");
+    }
+    limits
+}
+
+// 每次切下一批时按当前 RSS 调整批次大小，已经切出的批次不受影响
+pub struct BatchSizer {
+    ceiling: Option<u64>,
+    max: usize,
+    min: usize,
+    current: AtomicUsize,
+}
+
+impl BatchSizer {
+    pub fn new(memory: &MemoryConfig, initial: usize) -> BatchSizer {
+        let max = initial.max(1);
+        BatchSizer {
+            ceiling: (memory.max_rss_mb > 0).then_some(memory.max_rss_mb * 1024 * 1024),
+            max,
+            min: memory.min_batch_size.clamp(1, max),
+            current: AtomicUsize::new(max),
+        }
+    }
+
+    fn next(&self) -> usize {
+        let current = self.current.load(Ordering::Relaxed);
+        let Some(ceiling) = self.ceiling else {
+            return current;
+        };
+        let Some(rss) = rss_bytes() else {
+            return current;
+        };
+        let used = rss as f64 / ceiling as f64;
+        let size = if used >= SHRINK_AT {
+            (current / 2).max(self.min)
+        } else if used < GROW_AT {
+            (current * 2).min(self.max)
+        } else {
+            current
+        };
+        if size < current {
+            warn!("RSS {} MB is close to the {} MB limit, shrinking insert batches to {} rows.", rss / 1024 / 1024, ceiling / 1024 / 1024, size);
+        }
+        self.current.store(size, Ordering::Relaxed);
+        size
+    }
+
+    // 同时给出批次在 records 里的位置，用于记录 --resume 的进度
+    pub fn chunks<'r, T: Sync>(&self, records: &'r [T]) -> impl Iterator<Item = (Range<u64>, &'r [T])> + Send {
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            let rest = &records[start..];
+            if rest.is_empty() {
+                return None;
+            }
+            let chunk = &rest[..self.next().min(rest.len())];
+            let range = start as u64..(start + chunk.len()) as u64;
+            start += chunk.len();
+            Some((range, chunk))
+        })
+    }
+}
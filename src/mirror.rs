@@ -0,0 +1,161 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::NaiveDateTime;
+use futures::future::join_all;
+use log::{info, warn};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::cboe::OptionRecord;
+use crate::config::DatabaseConfig;
+use crate::run_log::{MarketProgress, RunLog};
+use crate::shutdown;
+use crate::storage::{Database, InsertOptions, Provenance, SnapshotStore, SnapshotTable};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorMode {
+    // 镜像失败只记录到运行日志，不影响主库
+    #[default]
+    BestEffort,
+    // 先写所有镜像，全部成功后才写主库；任何一个失败整个运行失败，下次运行重新写入。
+    // 各库分别提交，主库在镜像之后失败时镜像不会回滚，比主库多出这个市场，直到下次运行重新写入主库
+    #[serde(alias = "all_or_nothing")]
+    MirrorFirst,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct MirrorConfig {
+    pub mode: MirrorMode,
+    pub targets: Vec<MirrorTarget>,
+}
+
+// 连接串直接写 url，或用 url_env 指定环境变量，避免把密码写进配置文件
+#[derive(Deserialize)]
+pub struct MirrorTarget {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub url_env: Option<String>,
+}
+
+// 每个镜像在每个市场的写入结果，保存在运行日志的 targets 列
+#[derive(Serialize)]
+pub struct TargetStatus {
+    pub target: String,
+    pub market: String,
+    pub status: &'static str,
+    pub rows_inserted: u64,
+    pub error: Option<String>,
+}
+
+struct Target {
+    name: String,
+    // best_effort 下连不上的镜像保留下来，每个市场记录一次 unavailable
+    store: Result<Box<dyn SnapshotStore>, String>,
+}
+
+pub struct Mirrors {
+    mode: MirrorMode,
+    targets: Vec<Target>,
+}
+
+// 不带密码的 host/数据库，用作默认名称
+fn default_name(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if parsed.host_str().is_some() => {
+            format!("{}{}", parsed.host_str().unwrap_or_default(), parsed.path())
+        }
+        Ok(parsed) => parsed.path().to_string(),
+        Err(_) => "mirror".to_string(),
+    }
+}
+
+impl MirrorTarget {
+    fn url(&self) -> Result<String> {
+        match (&self.url, &self.url_env) {
+            (Some(url), _) => Ok(url.clone()),
+            (None, Some(var)) => std::env::var(var).with_context(|| format!("Mirror connection string variable {} is not set", var)),
+            (None, None) => bail!("[[mirror.targets]] needs url or url_env"),
+        }
+    }
+}
+
+impl Mirrors {
+    pub async fn connect(config: &MirrorConfig, database: &DatabaseConfig, table: &SnapshotTable) -> Result<Mirrors> {
+        let mut targets = Vec::with_capacity(config.targets.len());
+        for target in &config.targets {
+            let url = target.url()?;
+            let name = target.name.clone().unwrap_or_else(|| default_name(&url));
+            let store = match Database::connect(&url, database, std::slice::from_ref(table)).await {
+                Ok(db) => {
+                    info!("Connected to mirror {} ({}).", name, db.store.backend());
+                    Ok(db.store)
+                }
+                Err(e) if config.mode == MirrorMode::MirrorFirst => {
+                    return Err(e.context(format!("Failed to connect to mirror {}", name)));
+                }
+                Err(e) => {
+                    warn!("Mirror {} is unavailable: {:#}", name, e);
+                    Err(format!("{:#}", e))
+                }
+            };
+            targets.push(Target { name, store });
+        }
+        Ok(Mirrors { mode: config.mode, targets })
+    }
+
+    pub fn mirror_first(&self) -> bool {
+        self.mode == MirrorMode::MirrorFirst
+    }
+
+    // 各镜像并行写入；主库之外的库不记录分批进度，只在运行日志里记结果
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write(
+        &self,
+        run_log: &mut RunLog,
+        table: &SnapshotTable,
+        market: &str,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance<'_>,
+        options: &InsertOptions,
+        token: &CancellationToken,
+    ) -> Result<()> {
+        let writes = self.targets.iter().map(|target| async move {
+            let store = target.store.as_ref().map_err(|e| anyhow!("unavailable: {}", e))?;
+            let progress = MarketProgress::detached(&format!("{}@{}", market, target.name), records.len() as u64);
+            let result = store.insert_records(table, records, snapshot_time, provenance, options, &progress, token).await;
+            progress.finish("done").await?;
+            result
+        });
+        let results = join_all(writes).await;
+        let mut failed = None;
+        for (target, result) in self.targets.iter().zip(results) {
+            let status = match result {
+                Ok(rows_inserted) => TargetStatus { target: target.name.clone(), market: market.to_string(), status: "done", rows_inserted, error: None },
+                Err(e) if shutdown::is_cancelled(&e) => return Err(e),
+                Err(e) => {
+                    warn!("Mirror {} failed for {}: {:#}", target.name, market, e);
+                    let status = if target.store.is_err() { "unavailable" } else { "failed" };
+                    let error = format!("{:#}", e);
+                    failed.get_or_insert_with(|| anyhow!("Mirror {} failed for {}: {}", target.name, market, error));
+                    TargetStatus { target: target.name.clone(), market: market.to_string(), status, rows_inserted: 0, error: Some(error) }
+                }
+            };
+            run_log.targets.push(status);
+        }
+        match failed {
+            Some(e) if self.mirror_first() => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn close(&self) {
+        for target in &self.targets {
+            if let Ok(store) = &target.store {
+                store.close().await;
+            }
+        }
+    }
+}
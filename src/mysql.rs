@@ -0,0 +1,162 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use chrono_tz::America;
+use log::info;
+use sqlx::mysql::{MySql, MySqlConnectOptions, MySqlPool};
+use std::str::FromStr;
+use sqlx::Row;
+use tokio_util::sync::CancellationToken;
+
+use crate::cboe::OptionRecord;
+use crate::config::DatabaseConfig;
+use crate::run_log::MarketProgress;
+use crate::shutdown::Cancelled;
+use crate::storage::{Provenance, InsertOptions, SnapshotStore, SnapshotTable, pool_options};
+
+// MySQL / MariaDB 快照表，表结构见 migrations-mysql
+pub struct MySqlStore {
+    pool: MySqlPool,
+}
+
+impl MySqlStore {
+    pub async fn connect(url: &str, config: &DatabaseConfig) -> Result<MySqlStore> {
+        // sqlx 只认 mysql://，mariadb:// 作为别名
+        let url = match url.strip_prefix("mariadb://") {
+            Some(rest) => format!("mysql://{}", rest),
+            None => url.to_string(),
+        };
+        let options = MySqlConnectOptions::from_str(&url)?.statement_cache_capacity(config.statement_cache_capacity);
+        let pool = pool_options::<MySql>(config).connect_with(options).await?;
+        Ok(MySqlStore { pool })
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for MySqlStore {
+    fn backend(&self) -> &'static str {
+        "mysql"
+    }
+
+    // 和 Postgres 一样只看完整快照
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+        let row = sqlx::query(&format!("SELECT max(last_updated_time) FROM {table} WHERE source = 'symbol_data'", table = table.quoted('`')))
+            .fetch_one(&self.pool)
+            .await?;
+        let max_time: Option<NaiveDateTime> = row.try_get(0)?;
+        Ok(max_time)
+    }
+
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64> {
+        let etl_in_dt = chrono::Utc::now().with_timezone(&America::New_York).naive_local();
+        let sizer = options.batch_sizer(records.len());
+        let mut inserted = 0;
+        let mut updated = 0;
+        let mut committed = 0;
+        let mut batches = 0;
+
+        for (batch, chunk) in sizer.chunks(records) {
+            let mut tx = self.pool.begin().await?;
+            let insert = format!(r#"
+                INSERT INTO {table}
+                (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, market, source_url)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON DUPLICATE KEY UPDATE
+                    volume = VALUES(volume),
+                    matched = VALUES(matched),
+                    routed = VALUES(routed),
+                    bid_size = VALUES(bid_size),
+                    bid_price = VALUES(bid_price),
+                    ask_size = VALUES(ask_size),
+                    ask_price = VALUES(ask_price),
+                    last_price = VALUES(last_price),
+                    etl_in_dt = VALUES(etl_in_dt),
+                    days_to_expiration = VALUES(days_to_expiration),
+                    expiration_class = VALUES(expiration_class),
+                    is_adjusted = VALUES(is_adjusted),
+                    source_url = VALUES(source_url)
+            "#, table = table.quoted('`'));
+            for rec in chunk {
+                if token.is_cancelled() {
+                    tx.rollback().await?;
+                    info!("Rolled back batch {} after shutdown signal, {} rows committed.", batches + 1, committed);
+                    return Err(Cancelled.into());
+                }
+                let class = rec.classify(snapshot_time.date());
+                let result = sqlx::query(&insert)
+                    .bind(&rec.symbol)
+                    .bind(&rec.call_put)
+                    .bind(&rec.expiration)
+                    .bind(rec.strike_price)
+                    .bind(rec.volume)
+                    .bind(rec.matched)
+                    .bind(rec.routed)
+                    .bind(rec.bid_size)
+                    .bind(rec.bid_price)
+                    .bind(rec.ask_size)
+                    .bind(rec.ask_price)
+                    .bind(rec.last_price)
+                    .bind(snapshot_time)
+                    .bind(etl_in_dt)
+                    .bind(class.as_ref().map(|c| c.days_to_expiration))
+                    .bind(class.as_ref().map(|c| c.expiration_class))
+                    .bind(rec.is_adjusted())
+                    .bind(provenance.market)
+                    .bind(provenance.source_url)
+                    .execute(&mut *tx)
+                    .await?;
+                // ON DUPLICATE KEY UPDATE 的影响行数：新插入为 1，更新为 2；sqlx 连接带 CLIENT_FOUND_ROWS，值没变的更新也是 1，
+                // 但 etl_in_dt 每次写入都不同，只有同一批里重复的行会这样
+                match result.rows_affected() {
+                    2 => updated += 1,
+                    _ => inserted += 1,
+                }
+                progress.tick();
+            }
+            tx.commit().await?;
+
+            committed += chunk.len() as u64;
+            batches += 1;
+            progress.record_committed(committed, batches, batch).await?;
+        }
+
+        // 和 Postgres 的 upsert 一样，返回写入（新插入或更新）的行数，每行只算一次
+        info!("Inserted {} records in {} batches ({} new, {} updated).", records.len(), batches, inserted, updated);
+        Ok(inserted + updated)
+    }
+
+    async fn clean_duplicate_data(&self, table: &SnapshotTable) -> Result<u64> {
+        // MySQL 不允许 DELETE 时直接子查询同一张表，多包一层派生表
+        let result = sqlx::query(&format!(r#"
+            DELETE t FROM {table} t
+            JOIN (
+                SELECT id FROM (
+                    SELECT id,
+                           row_number() OVER (
+                               PARTITION BY symbol, call_put, expiration, strike_price, last_updated_time, market
+                               ORDER BY etl_in_dt DESC, id DESC
+                           ) AS rn
+                    FROM {table}
+                ) ranked
+                WHERE rn > 1
+            ) d ON t.id = d.id
+        "#, table = table.quoted('`')))
+            .execute(&self.pool)
+            .await?;
+        info!("Removed {} duplicate rows.", result.rows_affected());
+        Ok(result.rows_affected())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+}
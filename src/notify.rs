@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{info, warn};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::run_log::{Outcome, RunLog};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyOn {
+    #[default]
+    Failure,
+    Always,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub on: NotifyOn,
+    pub slack: Option<SlackConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub email: Option<EmailConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+pub struct Notification {
+    pub title: String,
+    pub text: String,
+    pub json: serde_json::Value,
+    // 有 HTML 版本时邮件同时发送纯文本和 HTML
+    pub html: Option<String>,
+}
+
+impl Notification {
+    fn from_run(run: &RunLog) -> Notification {
+        Notification { title: run.summary_title(), text: run.summary_text(), json: run.summary_json(), html: None }
+    }
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn send(&self, message: &Notification) -> Result<()>;
+}
+
+pub struct SlackNotifier {
+    client: Client,
+    webhook_url: String,
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn send(&self, message: &Notification) -> Result<()> {
+        let body = serde_json::json!({ "text": message.text });
+        self.client.post(&self.webhook_url).json(&body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+    headers: HashMap<String, String>,
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, message: &Notification) -> Result<()> {
+        let mut req = self.client.post(&self.url).json(&message.json);
+        for (key, value) in &self.headers {
+            req = req.header(key, value);
+        }
+        req.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    fn new(config: &EmailConfig) -> Result<EmailNotifier> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?;
+        if let Some(port) = config.smtp_port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        let to = config.to.iter()
+            .map(|addr| addr.parse().with_context(|| format!("Invalid email address {}", addr)))
+            .collect::<Result<Vec<Mailbox>>>()?;
+        Ok(EmailNotifier {
+            transport: builder.build(),
+            from: config.from.parse().with_context(|| format!("Invalid email address {}", config.from))?,
+            to,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&self, message: &Notification) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(&message.title);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let email = match &message.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(message.text.clone(), html.clone()))?,
+            None => builder.body(message.text.clone())?,
+        };
+        self.transport.send(email).await?;
+        Ok(())
+    }
+}
+
+pub struct Notifiers {
+    on: NotifyOn,
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl Notifiers {
+    pub fn from_config(config: &NotifyConfig, client: &Client) -> Result<Notifiers> {
+        let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+        if let Some(slack) = &config.slack {
+            notifiers.push(Box::new(SlackNotifier {
+                client: client.clone(),
+                webhook_url: slack.webhook_url.clone(),
+            }));
+        }
+        if let Some(webhook) = &config.webhook {
+            notifiers.push(Box::new(WebhookNotifier {
+                client: client.clone(),
+                url: webhook.url.clone(),
+                headers: webhook.headers.clone(),
+            }));
+        }
+        if let Some(email) = &config.email {
+            notifiers.push(Box::new(EmailNotifier::new(email)?));
+        }
+        Ok(Notifiers { on: config.on, notifiers })
+    }
+
+    // 通知失败只记录日志，不影响本次运行结果
+    pub async fn notify(&self, run: &RunLog, outcome: Outcome) {
+        if self.on == NotifyOn::Failure && outcome != Outcome::Failed {
+            return;
+        }
+        self.send(&Notification::from_run(run)).await;
+    }
+
+    // 不受 notify.on 限制，调用方自己决定是否发送
+    pub async fn send(&self, message: &Notification) {
+        for notifier in &self.notifiers {
+            match notifier.send(message).await {
+                Ok(()) => info!("Sent {} notification.", notifier.name()),
+                Err(e) => warn!("Failed to send {} notification: {:#}", notifier.name(), e),
+            }
+        }
+    }
+}
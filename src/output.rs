@@ -0,0 +1,104 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::Value;
+use std::io::Write;
+
+#[derive(ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+pub struct Table {
+    pub headers: Vec<&'static str>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+pub fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl Table {
+    pub fn new(headers: Vec<&'static str>) -> Table {
+        Table { headers, rows: Vec::new() }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        self.rows.push(row);
+    }
+
+    pub fn to_json(&self) -> Value {
+        let objects = self.rows.iter()
+            .map(|row| {
+                let map = self.headers.iter()
+                    .zip(row)
+                    .map(|(h, v)| (h.to_string(), v.clone()))
+                    .collect();
+                Value::Object(map)
+            })
+            .collect();
+        Value::Array(objects)
+    }
+
+    pub fn print(&self, format: Format) -> Result<()> {
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        match format {
+            Format::Table => self.write_table(&mut out)?,
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut out, &self.to_json())?;
+                writeln!(out)?;
+            }
+            Format::Csv => {
+                let mut wtr = csv::Writer::from_writer(out);
+                wtr.write_record(&self.headers)?;
+                for row in &self.rows {
+                    wtr.write_record(row.iter().map(cell))?;
+                }
+                wtr.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> Result<String> {
+        let mut out = Vec::new();
+        self.write_table(&mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn write_table(&self, out: &mut impl Write) -> Result<()> {
+        let cells: Vec<Vec<String>> = self.rows.iter().map(|row| row.iter().map(cell).collect()).collect();
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &cells {
+            for (i, c) in row.iter().enumerate() {
+                widths[i] = widths[i].max(c.len());
+            }
+        }
+        // 数字右对齐，文本左对齐
+        let numeric: Vec<bool> = (0..self.headers.len())
+            .map(|i| self.rows.iter().all(|row| matches!(row[i], Value::Number(_) | Value::Null)))
+            .collect();
+
+        let header: Vec<String> = self.headers.iter().enumerate()
+            .map(|(i, h)| if numeric[i] { format!("{:>w$}", h, w = widths[i]) } else { format!("{:<w$}", h, w = widths[i]) })
+            .collect();
+        writeln!(out, "{}", header.join("  ").trim_end())?;
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        writeln!(out, "{}", rule.join("  "))?;
+        for row in &cells {
+            let line: Vec<String> = row.iter().enumerate()
+                .map(|(i, c)| if numeric[i] { format!("{:>w$}", c, w = widths[i]) } else { format!("{:<w$}", c, w = widths[i]) })
+                .collect();
+            writeln!(out, "{}", line.join("  ").trim_end())?;
+        }
+        writeln!(out, "({} rows)", cells.len())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Result, bail};
+use log::warn;
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::cli::RunArgs;
+use crate::config::Config;
+use crate::db;
+use crate::filter::SymbolFilter;
+use crate::output::{Format, Table};
+use crate::source::SnapshotSource;
+use crate::storage::{Database, SnapshotTable};
+
+// --dry-run：只抓取和解析，打印加载预览，不写数据库
+pub async fn run(db: &Database, snapshot_table: &SnapshotTable, config: &Config, source: &dyn SnapshotSource, args: &RunArgs) -> Result<()> {
+    let filter = SymbolFilter::new(&args.filter(&config.filter))?;
+    let last_update_time = source.snapshot_time().await?;
+    let max_updated_time = db.store.max_updated_time(snapshot_table).await?;
+
+    println!("Detected snapshot: {} (source {})", last_update_time, source.name());
+    match max_updated_time {
+        Some(t) if t == last_update_time => println!("Latest in database: {} (a real run would skip)", t),
+        Some(t) => println!("Latest in database: {}", t),
+        None => println!("Latest in database: -"),
+    }
+    if let Some(description) = filter.description() {
+        println!("Symbol filter: {}", description);
+    }
+
+    let mut problems = Vec::new();
+    if let Some(pool) = db.postgres_opt() {
+        for column in db::missing_snapshot_columns(pool, snapshot_table).await? {
+            problems.push(format!("table {} is missing column {}", snapshot_table, column));
+        }
+    }
+
+    let mut table = Table::new(vec!["market", "rows", "skipped", "filtered", "errors", "underlyings", "min_expiration", "max_expiration", "bytes", "wire_bytes"]);
+    for market in source.markets() {
+        let mut content = source.fetch(&market, &filter).await?;
+        for problem in &content.format_problems {
+            problems.push(format!("{}: {}", market, problem));
+        }
+        let rows_fetched = content.records.len();
+        if args.skip_inactive {
+            content.records.retain(|r| !r.is_inactive());
+        }
+
+        let underlyings: HashSet<&str> = content.records.iter().map(|r| r.symbol.as_str()).collect();
+        let expirations: Vec<_> = content.records.iter().filter_map(|r| r.expiration_date()).collect();
+        table.push(vec![
+            json!(market),
+            json!(content.records.len()),
+            json!(rows_fetched - content.records.len()),
+            json!(content.filtered),
+            json!(content.parse_errors),
+            json!(underlyings.len()),
+            json!(expirations.iter().min().map(|d| d.to_string())),
+            json!(expirations.iter().max().map(|d| d.to_string())),
+            json!(content.http_bytes),
+            json!(content.wire_bytes),
+        ]);
+    }
+    table.print(Format::Table)?;
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            warn!("Schema check: {}", problem);
+        }
+        bail!("Dry run found {} schema problems", problems.len());
+    }
+    println!("Dry run OK, nothing was written.");
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// 非终端（cron、容器日志）时每隔这么久打一行进度
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+// 单个市场的写入进度：终端里显示进度条，否则定期输出日志行
+pub struct Reporter {
+    market: String,
+    total: u64,
+    started: Instant,
+    done: AtomicU64,
+    bar: Option<ProgressBar>,
+    last_log: Mutex<Instant>,
+}
+
+impl Reporter {
+    pub fn new(market: &str, total: u64) -> Reporter {
+        let bar = std::io::stderr().is_terminal().then(|| {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template("{prefix:>6} [{bar:40}] {pos}/{len} rows {per_sec} ETA {eta}")
+                    .expect("valid progress template")
+                    .progress_chars("=> "),
+            );
+            bar.set_prefix(market.to_string());
+            bar
+        });
+        let now = Instant::now();
+        Reporter { market: market.to_string(), total, started: now, done: AtomicU64::new(0), bar, last_log: Mutex::new(now) }
+    }
+
+    pub fn inc(&self, rows: u64) {
+        let done = self.done.fetch_add(rows, Ordering::Relaxed) + rows;
+        if let Some(bar) = &self.bar {
+            bar.set_position(done);
+            return;
+        }
+        let mut last_log = self.last_log.lock().expect("progress lock");
+        if last_log.elapsed() >= LOG_INTERVAL {
+            *last_log = Instant::now();
+            info!("{}", self.line(done));
+        }
+    }
+
+    // 批次回滚重试前退回已经计入的行数
+    pub fn rewind(&self, rows: u64) {
+        let done = self.done.fetch_sub(rows, Ordering::Relaxed).saturating_sub(rows);
+        if let Some(bar) = &self.bar {
+            bar.set_position(done);
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn line(&self, done: u64) -> String {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { done as f64 / elapsed } else { 0.0 };
+        let eta = if rate > 0.0 { format!("{:.0}s", self.total.saturating_sub(done) as f64 / rate) } else { "-".to_string() };
+        let percent = if self.total > 0 { done as f64 * 100.0 / self.total as f64 } else { 100.0 };
+        format!("{}: {}/{} rows ({:.0}%), {:.0} rows/s, ETA {}", self.market, done, self.total, percent, rate, eta)
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+
+use crate::changes;
+use crate::cli::{QueryArgs, QueryCommand};
+use crate::config::AnalyticsConfig;
+use crate::expirations;
+use crate::output::Table;
+use crate::storage::SnapshotTable;
+use crate::surfaces;
+use crate::symbols;
+
+pub async fn run(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, args: &QueryArgs) -> Result<()> {
+    let output = match &args.command {
+        QueryCommand::Chain { symbol, expiry } => chain(pool, table, symbol, expiry.as_deref(), args.snapshot).await?,
+        QueryCommand::TopVolume { limit } => top_volume(pool, table, analytics, *limit, args.snapshot).await?,
+        QueryCommand::SymbolSummary { symbol } => symbol_summary(pool, table, analytics, symbol, args.snapshot).await?,
+        QueryCommand::Listings { days } => symbols::listings(pool, *days).await?,
+        QueryCommand::Expirations { symbol, all } => expirations::list(pool, symbol, *all).await?,
+        QueryCommand::Iv { symbol, strike, expiry } => surfaces::query(pool, symbol, *strike, *expiry, args.snapshot).await?,
+    };
+    output.print(args.format)
+}
+
+// 不指定快照时查最新快照，并跳过已标记 is_expired 的合约（CBOE 有时在到期后仍会列出）；指定历史快照时原样返回
+fn expired_filter(snapshot: Option<NaiveDateTime>) -> &'static str {
+    if snapshot.is_none() { "AND NOT is_expired" } else { "" }
+}
+
+// 快照通过 changes::resolve 读取，开启变化检测时包括从之前的快照沿用的合约
+pub async fn chain(pool: &PgPool, table: &SnapshotTable, symbol: &str, expiry: Option<&str>, snapshot: Option<NaiveDateTime>) -> Result<Table> {
+    let rows = match changes::resolve(pool, table, snapshot).await? {
+        Some((snapshot_time, source)) => sqlx::query(&format!(r#"
+            SELECT expiration, strike_price, call_put, bid_size, bid_price, ask_price, ask_size, last_price, volume
+            FROM ({source}) AS snapshot
+            WHERE symbol = $2
+              AND ($3::text IS NULL OR expiration = $3)
+              {expired}
+            ORDER BY expiration, strike_price, call_put
+        "#, expired = expired_filter(snapshot)))
+            .bind(snapshot_time)
+            .bind(symbol.to_uppercase())
+            .bind(expiry)
+            .fetch_all(pool)
+            .await?,
+        None => Vec::new(),
+    };
+
+    let mut table = Table::new(vec!["expiration", "strike", "cp", "bid_size", "bid", "ask", "ask_size", "last", "volume"]);
+    for row in rows {
+        table.push(vec![
+            json!(row.try_get::<String, _>("expiration")?),
+            json!(row.try_get::<f64, _>("strike_price")?),
+            json!(row.try_get::<String, _>("call_put")?),
+            json!(row.try_get::<i64, _>("bid_size")?),
+            json!(row.try_get::<f64, _>("bid_price")?),
+            json!(row.try_get::<f64, _>("ask_price")?),
+            json!(row.try_get::<i64, _>("ask_size")?),
+            json!(row.try_get::<f64, _>("last_price")?),
+            json!(row.try_get::<i64, _>("volume")?),
+        ]);
+    }
+    Ok(table)
+}
+
+pub async fn top_volume(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, limit: i64, snapshot: Option<NaiveDateTime>) -> Result<Table> {
+    let rows = match changes::resolve(pool, table, snapshot).await? {
+        Some((snapshot_time, source)) => sqlx::query(&format!(r#"
+            SELECT symbol, call_put, expiration, strike_price, volume, last_price
+            FROM ({source}) AS snapshot
+            WHERE true {expired} {adjusted}
+            ORDER BY volume DESC
+            LIMIT $2
+        "#, expired = expired_filter(snapshot), adjusted = analytics.adjusted_filter()))
+            .bind(snapshot_time)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?,
+        None => Vec::new(),
+    };
+
+    let mut table = Table::new(vec!["symbol", "cp", "expiration", "strike", "volume", "last"]);
+    for row in rows {
+        table.push(vec![
+            json!(row.try_get::<String, _>("symbol")?),
+            json!(row.try_get::<String, _>("call_put")?),
+            json!(row.try_get::<String, _>("expiration")?),
+            json!(row.try_get::<f64, _>("strike_price")?),
+            json!(row.try_get::<i64, _>("volume")?),
+            json!(row.try_get::<f64, _>("last_price")?),
+        ]);
+    }
+    Ok(table)
+}
+
+pub async fn symbol_summary(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, symbol: &str, snapshot: Option<NaiveDateTime>) -> Result<Table> {
+    let rows = match changes::resolve(pool, table, snapshot).await? {
+        Some((snapshot_time, source)) => sqlx::query(&format!(r#"
+            SELECT expiration,
+                   count(*)                                                             AS contracts,
+                   COALESCE(sum(volume) FILTER (WHERE upper(left(call_put, 1)) = 'C'), 0)::BIGINT AS call_volume,
+                   COALESCE(sum(volume) FILTER (WHERE upper(left(call_put, 1)) = 'P'), 0)::BIGINT AS put_volume,
+                   min(strike_price)                                                    AS min_strike,
+                   max(strike_price)                                                    AS max_strike
+            FROM ({source}) AS snapshot
+            WHERE symbol = $2 {expired} {adjusted}
+            GROUP BY expiration
+            ORDER BY expiration
+        "#, expired = expired_filter(snapshot), adjusted = analytics.adjusted_filter()))
+            .bind(snapshot_time)
+            .bind(symbol.to_uppercase())
+            .fetch_all(pool)
+            .await?,
+        None => Vec::new(),
+    };
+
+    let mut table = Table::new(vec!["expiration", "contracts", "call_volume", "put_volume", "put_call", "min_strike", "max_strike"]);
+    for row in rows {
+        let call_volume: i64 = row.try_get("call_volume")?;
+        let put_volume: i64 = row.try_get("put_volume")?;
+        let put_call = if call_volume > 0 {
+            json!((put_volume as f64 / call_volume as f64 * 100.0).round() / 100.0)
+        } else {
+            json!(null)
+        };
+        table.push(vec![
+            json!(row.try_get::<String, _>("expiration")?),
+            json!(row.try_get::<i64, _>("contracts")?),
+            json!(call_volume),
+            json!(put_volume),
+            put_call,
+            json!(row.try_get::<f64, _>("min_strike")?),
+            json!(row.try_get::<f64, _>("max_strike")?),
+        ]);
+    }
+    Ok(table)
+}
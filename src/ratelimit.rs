@@ -0,0 +1,133 @@
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+// 超过这么多个客户端时清掉已经回满的桶
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// 每个客户端 IP 一个令牌桶：容量 burst，每分钟补充 per_minute 个
+pub struct RateLimiter {
+    per_minute: u32,
+    burst: u32,
+    // X-Forwarded-For 里可信代理追加的地址数，0 表示不看这个头
+    trusted_hops: usize,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, burst: u32, trusted_hops: usize) -> RateLimiter {
+        RateLimiter { per_minute, burst: if burst == 0 { per_minute } else { burst }, trusted_hops, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    fn refill_rate(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+
+    // 允许时为 None，否则返回需要等待的时间
+    fn check(&self, client: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        let capacity = self.burst.max(1) as f64;
+        let rate = self.refill_rate();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() > MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, b| b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < capacity);
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, updated: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    // 在反向代理后面时取 X-Forwarded-For 右边第 trusted_hops 个地址，否则取连接的对端地址；
+    // 左边的地址是客户端自己填的，不能用来区分客户端。多个 X-Forwarded-For 头按顺序拼接
+    fn client(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        let forwarded = (self.trusted_hops > 0)
+            .then(|| {
+                let hops: Vec<&str> = headers.get_all("x-forwarded-for").iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .flat_map(|v| v.split(','))
+                    .map(str::trim)
+                    .filter(|h| !h.is_empty())
+                    .collect();
+                hops.get(hops.len().checked_sub(self.trusted_hops)?)?.parse().ok()
+            })
+            .flatten();
+        forwarded.or(peer.map(|p| p.ip()))
+    }
+}
+
+pub async fn limit(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    let Some(client) = limiter.client(request.headers(), peer) else {
+        return next.run(request).await;
+    };
+    match limiter.check(client) {
+        None => next.run(request).await,
+        Some(wait) => {
+            let retry_after = (wait.as_secs_f64().ceil() as u64).max(1);
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(json!({ "error": "rate limit exceeded", "retry_after_secs": retry_after }))).into_response();
+            if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn spoofed_forwarded_for_does_not_change_client() {
+        let limiter = RateLimiter::new(60, 1, 1);
+        let peer: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        let real: IpAddr = "203.0.113.9".parse().unwrap();
+        assert_eq!(limiter.client(&forwarded("1.1.1.1, 203.0.113.9"), Some(peer)), Some(real));
+        assert_eq!(limiter.client(&forwarded("2.2.2.2, 3.3.3.3, 203.0.113.9"), Some(peer)), Some(real));
+        // 换了伪造地址仍然是同一个桶
+        assert!(limiter.check(real).is_none());
+        let client = limiter.client(&forwarded("4.4.4.4, 203.0.113.9"), Some(peer)).unwrap();
+        assert!(limiter.check(client).is_some());
+    }
+
+    #[test]
+    fn counts_trusted_hops_from_the_right() {
+        let limiter = RateLimiter::new(60, 0, 2);
+        let peer: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        let client = limiter.client(&forwarded("6.6.6.6, 198.51.100.7, 10.0.0.1"), Some(peer));
+        assert_eq!(client, Some("198.51.100.7".parse().unwrap()));
+        // 地址比可信层数少时说明请求绕过了代理，退回对端地址
+        assert_eq!(limiter.client(&forwarded("198.51.100.7"), Some(peer)), Some(peer.ip()));
+    }
+
+    #[test]
+    fn ignores_forwarded_for_when_untrusted() {
+        let limiter = RateLimiter::new(60, 0, 0);
+        let peer: SocketAddr = "192.0.2.1:5000".parse().unwrap();
+        assert_eq!(limiter.client(&forwarded("203.0.113.9"), Some(peer)), Some(peer.ip()));
+    }
+}
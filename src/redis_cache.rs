@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use redis::aio::ConnectionManager;
+use serde::Deserialize;
+
+use crate::cboe::OptionRecord;
+use crate::sink::Sink;
+
+const PIPELINE_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct RedisConfig {
+    pub url: String,
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+    pub ttl_secs: Option<i64>,
+}
+
+fn default_key_prefix() -> String {
+    "cboe:quote:".to_string()
+}
+
+pub struct RedisSink {
+    conn: ConnectionManager,
+    key_prefix: String,
+    ttl_secs: Option<i64>,
+}
+
+impl RedisSink {
+    pub async fn new(config: &RedisConfig) -> Result<RedisSink> {
+        let client = redis::Client::open(config.url.as_str())?;
+        let conn = client.get_connection_manager().await.context("Failed to connect to Redis")?;
+        Ok(RedisSink {
+            conn,
+            key_prefix: config.key_prefix.clone(),
+            ttl_secs: config.ttl_secs,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for RedisSink {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    // 每个合约一个 hash：{prefix}{OCC 代码} -> bid/ask/last 等最新报价
+    async fn write(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> Result<()> {
+        let snapshot = snapshot_time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut conn = self.conn.clone();
+
+        for chunk in records.chunks(PIPELINE_SIZE) {
+            let mut pipe = redis::pipe();
+            for rec in chunk {
+                let key = format!("{}{}", self.key_prefix, rec.occ_symbol().replace(' ', ""));
+                pipe.hset_multiple(&key, &[
+                    ("bid_price", rec.bid_price.to_string()),
+                    ("bid_size", rec.bid_size.to_string()),
+                    ("ask_price", rec.ask_price.to_string()),
+                    ("ask_size", rec.ask_size.to_string()),
+                    ("last_price", rec.last_price.to_string()),
+                    ("volume", rec.volume.to_string()),
+                    ("snapshot_time", snapshot.clone()),
+                    ("market", market.to_string()),
+                ]).ignore();
+                if let Some(ttl) = self.ttl_secs {
+                    pipe.expire(&key, ttl).ignore();
+                }
+            }
+            pipe.query_async::<()>(&mut conn).await?;
+        }
+        Ok(())
+    }
+}
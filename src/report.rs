@@ -0,0 +1,277 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use clap::ValueEnum;
+use log::info;
+use serde_json::{Value, json};
+use sqlx::{PgPool, Row};
+
+use crate::calendar;
+use crate::changes;
+use crate::cli::ReportArgs;
+use crate::config::{AnalyticsConfig, Config};
+use crate::http::Fetcher;
+use crate::notify::{Notification, Notifiers};
+use crate::output::{Table, cell};
+use crate::storage::SnapshotTable;
+
+#[derive(ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+struct Section {
+    title: &'static str,
+    table: Table,
+}
+
+pub struct Report {
+    title: String,
+    start: NaiveDate,
+    end: NaiveDate,
+    sections: Vec<Section>,
+}
+
+fn put_call(calls: i64, puts: i64) -> Value {
+    if calls > 0 {
+        json!((puts as f64 / calls as f64 * 100.0).round() / 100.0)
+    } else {
+        json!(null)
+    }
+}
+
+// CBOE 的 volume 是当天累计值：先取每个合约每天的最大值，再跨合约、跨天求和；开启变化检测时包括沿用到当天的合约
+fn daily_volume(table: &SnapshotTable, analytics: &AnalyticsConfig) -> String {
+    format!(r#"
+        WITH daily AS (
+            SELECT day, symbol, upper(left(call_put, 1)) AS cp, expiration, strike_price, market,
+                   max(volume) AS volume
+            FROM ({rows}) AS snapshot
+            WHERE true {adjusted}
+            GROUP BY 1, 2, 3, 4, 5, 6
+        )
+    "#, rows = changes::daily_rows_sql(table), adjusted = analytics.adjusted_filter())
+}
+
+async fn top_underlyings(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, from: NaiveDateTime, to: NaiveDateTime, top: i64) -> Result<Table> {
+    let rows = sqlx::query(&format!(r#"
+        {daily}
+        SELECT symbol,
+               sum(volume)::BIGINT AS volume,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'C'), 0)::BIGINT AS call_volume,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'P'), 0)::BIGINT AS put_volume
+        FROM daily
+        GROUP BY symbol
+        ORDER BY volume DESC, symbol
+        LIMIT $3
+    "#, daily = daily_volume(table, analytics)))
+        .bind(from)
+        .bind(to)
+        .bind(top)
+        .fetch_all(pool)
+        .await?;
+    let mut table = Table::new(vec!["symbol", "volume", "call_volume", "put_volume", "put_call"]);
+    for row in rows {
+        let (calls, puts): (i64, i64) = (row.try_get("call_volume")?, row.try_get("put_volume")?);
+        table.push(vec![json!(row.try_get::<String, _>("symbol")?), json!(row.try_get::<i64, _>("volume")?), json!(calls), json!(puts), put_call(calls, puts)]);
+    }
+    Ok(table)
+}
+
+async fn put_call_trend(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, from: NaiveDateTime, to: NaiveDateTime) -> Result<Table> {
+    let rows = sqlx::query(&format!(r#"
+        {daily}
+        SELECT day,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'C'), 0)::BIGINT AS call_volume,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'P'), 0)::BIGINT AS put_volume
+        FROM daily
+        GROUP BY day
+        ORDER BY day
+    "#, daily = daily_volume(table, analytics)))
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+    let mut table = Table::new(vec!["date", "call_volume", "put_volume", "put_call"]);
+    for row in rows {
+        let (calls, puts): (i64, i64) = (row.try_get("call_volume")?, row.try_get("put_volume")?);
+        table.push(vec![json!(row.try_get::<NaiveDate, _>("day")?.to_string()), json!(calls), json!(puts), put_call(calls, puts)]);
+    }
+    Ok(table)
+}
+
+async fn busiest_expirations(pool: &PgPool, table: &SnapshotTable, analytics: &AnalyticsConfig, from: NaiveDateTime, to: NaiveDateTime, top: i64) -> Result<Table> {
+    let rows = sqlx::query(&format!(r#"
+        {daily}
+        SELECT symbol, expiration,
+               sum(volume)::BIGINT AS volume,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'C'), 0)::BIGINT AS call_volume,
+               COALESCE(sum(volume) FILTER (WHERE cp = 'P'), 0)::BIGINT AS put_volume
+        FROM daily
+        GROUP BY symbol, expiration
+        ORDER BY volume DESC, symbol, expiration
+        LIMIT $3
+    "#, daily = daily_volume(table, analytics)))
+        .bind(from)
+        .bind(to)
+        .bind(top)
+        .fetch_all(pool)
+        .await?;
+    let mut table = Table::new(vec!["symbol", "expiration", "volume", "put_call"]);
+    for row in rows {
+        let (calls, puts): (i64, i64) = (row.try_get("call_volume")?, row.try_get("put_volume")?);
+        table.push(vec![
+            json!(row.try_get::<String, _>("symbol")?),
+            json!(row.try_get::<String, _>("expiration")?),
+            json!(row.try_get::<i64, _>("volume")?),
+            put_call(calls, puts),
+        ]);
+    }
+    Ok(table)
+}
+
+// 校验规则命中次数，加上失败 / 中断的运行次数
+async fn incidents(pool: &PgPool, from: NaiveDateTime, to: NaiveDateTime) -> Result<Table> {
+    let rows = sqlx::query(r#"
+        SELECT 'dq:' || rule AS incident, count(*) AS occurrences, count(DISTINCT snapshot_time) AS snapshots
+        FROM t_cboe_snapshot_dq_issues
+        WHERE snapshot_time >= $1 AND snapshot_time < $2
+        GROUP BY rule
+        UNION ALL
+        SELECT 'run:' || outcome, count(*), count(DISTINCT snapshot_time)
+        FROM t_cboe_snapshot_etl_log
+        WHERE started_at >= $1 AND started_at < $2 AND outcome IN ('failed', 'cancelled')
+        GROUP BY outcome
+        ORDER BY occurrences DESC, incident
+    "#)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+    let mut table = Table::new(vec!["incident", "occurrences", "snapshots"]);
+    for row in rows {
+        table.push(vec![
+            json!(row.try_get::<String, _>("incident")?),
+            json!(row.try_get::<i64, _>("occurrences")?),
+            json!(row.try_get::<i64, _>("snapshots")?),
+        ]);
+    }
+    Ok(table)
+}
+
+impl Report {
+    pub async fn build(pool: &PgPool, snapshot_table: &SnapshotTable, analytics: &AnalyticsConfig, end: NaiveDate, days: i64, top: i64) -> Result<Report> {
+        let start = end - Duration::days(days.max(1) - 1);
+        let from = start.and_time(NaiveTime::MIN);
+        let to = (end + Duration::days(1)).and_time(NaiveTime::MIN);
+        let sections = vec![
+            Section { title: "Top underlyings by volume", table: top_underlyings(pool, snapshot_table, analytics, from, to, top).await? },
+            Section { title: "Put/call ratio by day", table: put_call_trend(pool, snapshot_table, analytics, from, to).await? },
+            Section { title: "Busiest expirations", table: busiest_expirations(pool, snapshot_table, analytics, from, to, top).await? },
+            Section { title: "Data quality incidents", table: incidents(pool, from, to).await? },
+        ];
+        let title = format!("CBOE options report {} to {}", start, end);
+        Ok(Report { title, start, end, sections })
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        match format {
+            ReportFormat::Text => self.text(),
+            ReportFormat::Markdown => Ok(self.markdown()),
+            ReportFormat::Html => Ok(self.html()),
+        }
+    }
+
+    fn text(&self) -> Result<String> {
+        let mut out = format!("{}\n{}\n", self.title, "=".repeat(self.title.len()));
+        for section in &self.sections {
+            out.push_str(&format!("\n{}\n{}", section.title, section.table.to_text()?));
+        }
+        Ok(out)
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title);
+        for section in &self.sections {
+            let table = &section.table;
+            out.push_str(&format!("\n## {}\n\n| {} |\n|", section.title, table.headers.join(" | ")));
+            out.push_str(&" --- |".repeat(table.headers.len()));
+            out.push('\n');
+            for row in &table.rows {
+                let cells: Vec<String> = row.iter().map(|v| cell(v).replace('|', "\\|")).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            if table.rows.is_empty() {
+                out.push_str("\n_No data._\n");
+            }
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\n\
+             <style>body{{font-family:sans-serif}} table{{border-collapse:collapse;margin-bottom:1em}} \
+             th,td{{border:1px solid #ccc;padding:2px 8px}} td.n{{text-align:right}}</style></head><body>\n<h1>{title}</h1>\n",
+            title = escape(&self.title)
+        );
+        for section in &self.sections {
+            let table = &section.table;
+            out.push_str(&format!("<h2>{}</h2>\n<table><tr>", escape(section.title)));
+            for header in &table.headers {
+                out.push_str(&format!("<th>{}</th>", escape(header)));
+            }
+            out.push_str("</tr>\n");
+            for row in &table.rows {
+                out.push_str("<tr>");
+                for value in row {
+                    let class = if value.is_number() { " class=\"n\"" } else { "" };
+                    out.push_str(&format!("<td{}>{}</td>", class, escape(&cell(value))));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    fn notification(&self, format: ReportFormat) -> Result<Notification> {
+        let text = match format {
+            ReportFormat::Markdown => self.markdown(),
+            _ => self.text()?,
+        };
+        let sections: serde_json::Map<String, Value> = self.sections.iter()
+            .map(|s| (s.title.to_string(), s.table.to_json()))
+            .collect();
+        let json = json!({ "title": self.title, "start": self.start, "end": self.end, "sections": sections });
+        let html = (format == ReportFormat::Html).then(|| self.html());
+        Ok(Notification { title: self.title.clone(), text, json, html })
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// 默认截止到纽约时间今天的最近 7 天
+pub async fn run(pool: &PgPool, table: &SnapshotTable, config: &Config, args: &ReportArgs) -> Result<()> {
+    let end = args.end.unwrap_or_else(|| calendar::now_new_york().date());
+    let report = Report::build(pool, table, &config.analytics, end, args.days, args.top).await?;
+    let rendered = report.render(args.format)?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, &rendered)?;
+            info!("Wrote report to {}.", path);
+        }
+        None => print!("{}", rendered),
+    }
+    if args.notify {
+        let fetcher = Fetcher::new(&config.http)?;
+        let notifiers = Notifiers::from_config(&config.notify, fetcher.client())?;
+        notifiers.send(&report.notification(args.format)?).await;
+    }
+    Ok(())
+}
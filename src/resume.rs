@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+
+struct MarketState {
+    // 记录这个市场状态的运行，沿着 resumed_from 往前找到的可能不是 Resume::run_id
+    run_id: i64,
+    status: String,
+    committed_offset: i64,
+    rows_total: Option<i64>,
+}
+
+// 同一个 job 同一个快照上一次没有完成的运行，run --resume 从它每个市场连续提交到的位置继续
+pub struct Resume {
+    pub run_id: i64,
+    markets: HashMap<String, MarketState>,
+}
+
+impl Resume {
+    // 该快照最近一次运行已经成功（或从未运行过）时为 None；进程被杀掉的运行 outcome 仍是 running
+    pub async fn find(pool: &PgPool, job: Option<&str>, snapshot_time: NaiveDateTime, current_run: i64) -> Result<Option<Resume>> {
+        let run: Option<(i64, String)> = sqlx::query_as(r#"
+            SELECT l.id, l.outcome FROM t_cboe_snapshot_etl_log l
+            WHERE l.job IS NOT DISTINCT FROM $1 AND l.id <> $3
+              AND EXISTS (SELECT 1 FROM t_cboe_snapshot_etl_log_market m WHERE m.run_id = l.id AND m.snapshot_time = $2)
+            ORDER BY l.id DESC
+            LIMIT 1
+        "#)
+            .bind(job)
+            .bind(snapshot_time)
+            .bind(current_run)
+            .fetch_optional(pool)
+            .await?;
+        let Some((run_id, outcome)) = run.filter(|(_, outcome)| outcome != "success") else {
+            info!("No interrupted run of snapshot {} to resume.", snapshot_time);
+            return Ok(None);
+        };
+        // 被继续的运行本身也可能是 --resume，它跳过的市场只记录在更早的运行里；沿着 resumed_from 往前合并，越新的运行优先
+        let mut markets = HashMap::new();
+        let mut chain = Some(run_id);
+        while let Some(id) = chain {
+            let rows = sqlx::query("SELECT market, status, committed_offset, rows_total FROM t_cboe_snapshot_etl_log_market WHERE run_id = $1")
+                .bind(id)
+                .fetch_all(pool)
+                .await?;
+            for row in rows {
+                markets.entry(row.try_get("market")?).or_insert(MarketState {
+                    run_id: id,
+                    status: row.try_get("status")?,
+                    committed_offset: row.try_get("committed_offset")?,
+                    rows_total: row.try_get("rows_total")?,
+                });
+            }
+            let previous: Option<i64> = sqlx::query_scalar("SELECT resumed_from FROM t_cboe_snapshot_etl_log WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?
+                .flatten();
+            // resumed_from 总是更早的运行，防止手工改过的数据形成环
+            chain = previous.filter(|p| *p < id);
+        }
+        info!("Resuming run {} ({}) of snapshot {}.", run_id, outcome, snapshot_time);
+        Ok(Some(Resume { run_id, markets }))
+    }
+
+    // 之前已经写完的市场整个跳过，不重新下载，返回写完它的运行
+    pub fn done_in(&self, market: &str) -> Option<i64> {
+        self.markets.get(market).filter(|m| m.status == "done").map(|m| m.run_id)
+    }
+
+    // 要写入的行数和上次不同（CSV 不同、过滤条件变了）时从头开始，重复写入会被冲突键覆盖
+    pub fn offset(&self, market: &str, rows: u64) -> u64 {
+        let Some(state) = self.markets.get(market) else {
+            return 0;
+        };
+        match state.rows_total {
+            Some(total) if total as u64 == rows => (state.committed_offset as u64).min(rows),
+            Some(total) => {
+                warn!("{} has {} rows to write but run {} had {}, reloading it from the start.", market, rows, state.run_id, total);
+                0
+            }
+            None => 0,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Result, bail};
+use chrono::{Duration, NaiveDateTime};
+use chrono_tz::America;
+use clap::ValueEnum;
+use log::info;
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+
+use crate::config::Config;
+use crate::storage::SnapshotTable;
+
+#[derive(Deserialize, ValueEnum, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneMode {
+    #[default]
+    Delete,
+    Archive,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub days: Option<u32>,
+    pub mode: PruneMode,
+    pub after_load: bool,
+}
+
+pub fn cutoff(days: u32) -> NaiveDateTime {
+    chrono::Utc::now().with_timezone(&America::New_York).naive_local() - Duration::days(days as i64)
+}
+
+pub async fn prune(pool: &PgPool, table: &SnapshotTable, config: &Config, days: u32, mode: PruneMode, dry_run: bool) -> Result<()> {
+    if days == 0 {
+        bail!("Retention window must be at least one day");
+    }
+    if mode == PruneMode::Archive {
+        table.require_default("Archive mode")?;
+    }
+    let cutoff = cutoff(days);
+    // hypertable 的 delete 模式按 chunk 删除，只能报告 chunk 数
+    let by_chunk = config.timescale.enabled && mode == PruneMode::Delete;
+
+    if dry_run && by_chunk {
+        let chunks = crate::timescale::drop_chunks(pool, table, cutoff, true).await?;
+        println!("Dry run: would drop {} chunks entirely older than {}", chunks, cutoff.format("%Y-%m-%d %H:%M:%S"));
+        return Ok(());
+    }
+    if dry_run {
+        let row = sqlx::query(&format!(r#"
+            SELECT count(*), count(DISTINCT last_updated_time), min(last_updated_time)
+            FROM {table}
+            WHERE last_updated_time < $1
+        "#))
+            .bind(cutoff)
+            .fetch_one(pool)
+            .await?;
+        let rows: i64 = row.try_get(0)?;
+        let snapshots: i64 = row.try_get(1)?;
+        let oldest: Option<NaiveDateTime> = row.try_get(2)?;
+        println!(
+            "Dry run: would {} {} rows across {} snapshots older than {} (oldest: {})",
+            if mode == PruneMode::Archive { "archive" } else { "delete" },
+            rows,
+            snapshots,
+            cutoff.format("%Y-%m-%d %H:%M:%S"),
+            oldest.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string()),
+        );
+        return Ok(());
+    }
+
+    if table.change_detection {
+        crate::changes::forget_before(pool, cutoff).await?;
+    }
+
+    if by_chunk {
+        let chunks = crate::timescale::drop_chunks(pool, table, cutoff, false).await?;
+        info!("Dropped {} chunks older than {}.", chunks, cutoff);
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    let removed = match mode {
+        PruneMode::Delete => {
+            sqlx::query(&format!("DELETE FROM {table} WHERE last_updated_time < $1"))
+                .bind(cutoff)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+        }
+        // 归档表里已有同一主键的行时整条语句报错回滚，不能让删掉的行没有归档
+        PruneMode::Archive => {
+            sqlx::query(&format!(r#"
+                WITH moved AS (
+                    DELETE FROM {table} WHERE last_updated_time < $1 RETURNING *
+                )
+                INSERT INTO t_options_cboe_snapshot_archive
+                SELECT * FROM moved
+            "#))
+                .bind(cutoff)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+        }
+    };
+    tx.commit().await?;
+
+    info!("Pruned {} rows older than {}.", removed, cutoff);
+    Ok(())
+}
@@ -0,0 +1,461 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use chrono_tz::America;
+use log::info;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool, Row};
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+
+use crate::drift::FormatDrift;
+use crate::mirror::TargetStatus;
+use crate::progress::Reporter;
+use crate::source::MarketData;
+use crate::verify::Checksum;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::Skipped => "skipped",
+            Outcome::Failed => "failed",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct MarketStats {
+    pub market: String,
+    pub rows_fetched: u64,
+    pub rows_inserted: u64,
+    pub rows_filtered: u64,
+    pub rows_skipped: u64,
+    pub parse_errors: u64,
+    pub dq_issues: u64,
+    // 解压后的字节数和实际传输的字节数
+    pub http_bytes: u64,
+    pub wire_bytes: u64,
+    // 写入行的 SHA-256，见 verify::checksum
+    pub checksum: Option<String>,
+}
+
+// 没有 Postgres 时（其它存储后端）运行日志只保存在内存里，用于通知
+pub struct RunLog {
+    pool: Option<PgPool>,
+    id: i64,
+    // [jobs] 里的 job 名称，没有定义 job 时为空
+    pub job: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub snapshot_time: Option<NaiveDateTime>,
+    pub markets: Vec<MarketStats>,
+    pub symbol_filter: Option<String>,
+    pub outcome: Option<Outcome>,
+    pub error_message: Option<String>,
+    // 加载后维护（ANALYZE / VACUUM / REINDEX）各步骤的耗时
+    pub maintenance: Option<serde_json::Value>,
+    // [mirror] 各镜像库每个市场的写入结果
+    pub targets: Vec<TargetStatus>,
+    // 本次运行的内存限制，见 memory::limits；结束时加上进程的峰值 RSS
+    pub memory: Option<serde_json::Value>,
+    // run --resume 继续的运行
+    pub resumed_from: Option<i64>,
+    // 和上一次运行相比页面措辞或 CSV 表头的变化，见 drift.rs
+    pub format_drift: Vec<FormatDrift>,
+}
+
+// 已提交的批次（按记录的位置），批次并行写入时提交顺序不定，连续提交的前缀才能作为继续的起点
+#[derive(Default)]
+struct Committed {
+    prefix: u64,
+    pending: Vec<Range<u64>>,
+}
+
+fn advance(mut prefix: u64, pending: &mut Vec<Range<u64>>) -> u64 {
+    while let Some(i) = pending.iter().position(|r| r.start <= prefix) {
+        prefix = prefix.max(pending.swap_remove(i).end);
+    }
+    prefix
+}
+
+pub struct MarketProgress {
+    pool: Option<PgPool>,
+    run_id: i64,
+    market: String,
+    reporter: Reporter,
+    // 写入从这个位置开始（--resume 时跳过上次已提交的部分），批次的位置相对于它
+    offset: u64,
+    committed: Mutex<Committed>,
+}
+
+impl MarketProgress {
+    fn new(pool: Option<PgPool>, run_id: i64, market: &str, rows: u64, offset: u64) -> MarketProgress {
+        MarketProgress {
+            pool,
+            run_id,
+            market: market.to_string(),
+            reporter: Reporter::new(market, rows - offset.min(rows)),
+            offset,
+            committed: Mutex::new(Committed { prefix: offset, pending: Vec::new() }),
+        }
+    }
+
+    // 不写运行日志的进度，用于镜像库
+    pub fn detached(label: &str, rows: u64) -> MarketProgress {
+        MarketProgress::new(None, 0, label, rows, 0)
+    }
+
+    // 加上 batch 之后连续提交到的位置
+    fn prefix_with(&self, batch: &Range<u64>) -> u64 {
+        let committed = self.committed.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut pending = committed.pending.clone();
+        pending.push(self.offset + batch.start..self.offset + batch.end);
+        advance(committed.prefix, &mut pending)
+    }
+
+    // 批次的事务提交成功之后调用
+    pub fn committed(&self, batch: Range<u64>) {
+        let mut committed = self.committed.lock().unwrap_or_else(PoisonError::into_inner);
+        let Committed { prefix, pending } = &mut *committed;
+        pending.push(self.offset + batch.start..self.offset + batch.end);
+        *prefix = advance(*prefix, pending);
+    }
+
+    // 每写入一行调用一次，只更新内存里的计数和进度显示
+    pub fn tick(&self) {
+        self.reporter.inc(1);
+    }
+
+    pub fn rewind(&self, rows: u64) {
+        self.reporter.rewind(rows);
+    }
+
+    // batch 是当前批次在本次写入的记录里的位置，和批次数据在同一个事务里提交
+    pub async fn record(&self, conn: &mut PgConnection, rows_committed: u64, batches: u64, batch: &Range<u64>) -> Result<()> {
+        if self.pool.is_none() {
+            return Ok(());
+        }
+        sqlx::query(r#"
+            UPDATE t_cboe_snapshot_etl_log_market
+            SET rows_committed = $3, batches = $4, updated_at = $5, committed_offset = GREATEST(committed_offset, $6)
+            WHERE run_id = $1 AND market = $2
+        "#)
+            .bind(self.run_id)
+            .bind(&self.market)
+            .bind(rows_committed as i64)
+            .bind(batches as i64)
+            .bind(now())
+            .bind(self.prefix_with(batch) as i64)
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+
+    // 非 Postgres 后端在批次提交之后单独记录进度
+    pub async fn record_committed(&self, rows_committed: u64, batches: u64, batch: Range<u64>) -> Result<()> {
+        if let Some(pool) = &self.pool {
+            let mut conn = pool.acquire().await?;
+            self.record(&mut conn, rows_committed, batches, &batch).await?;
+        }
+        self.committed(batch);
+        Ok(())
+    }
+
+    pub async fn record_checksum(&self, checksum: &Checksum) -> Result<()> {
+        let Some(pool) = &self.pool else {
+            return Ok(());
+        };
+        sqlx::query("UPDATE t_cboe_snapshot_etl_log_market SET checksum = $3, checksum_rows = $4 WHERE run_id = $1 AND market = $2")
+            .bind(self.run_id)
+            .bind(&self.market)
+            .bind(&checksum.digest)
+            .bind(checksum.rows as i64)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn finish(&self, status: &str) -> Result<()> {
+        self.reporter.finish();
+        let Some(pool) = &self.pool else {
+            return Ok(());
+        };
+        sqlx::query("UPDATE t_cboe_snapshot_etl_log_market SET status = $3, updated_at = $4 WHERE run_id = $1 AND market = $2")
+            .bind(self.run_id)
+            .bind(&self.market)
+            .bind(status)
+            .bind(now())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().with_timezone(&America::New_York).naive_local()
+}
+
+impl RunLog {
+    pub async fn start(pool: Option<&PgPool>, job: Option<&str>) -> Result<RunLog> {
+        let started_at = now();
+        let id = match pool {
+            Some(pool) => {
+                let row = sqlx::query("INSERT INTO t_cboe_snapshot_etl_log (started_at, outcome, job) VALUES ($1, 'running', $2) RETURNING id")
+                    .bind(started_at)
+                    .bind(job)
+                    .fetch_one(pool)
+                    .await?;
+                row.try_get(0)?
+            }
+            None => 0,
+        };
+        Ok(RunLog {
+            pool: pool.cloned(),
+            id,
+            job: job.map(|j| j.to_string()),
+            started_at,
+            finished_at: None,
+            snapshot_time: None,
+            markets: Vec::new(),
+            symbol_filter: None,
+            outcome: None,
+            error_message: None,
+            maintenance: None,
+            targets: Vec::new(),
+            memory: None,
+            resumed_from: None,
+            format_drift: Vec::new(),
+        })
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    // rows 是实际要写入的行数，开启变化检测时少于解析出的行数；offset 是 --resume 时跳过的已提交行数
+    pub async fn start_market(&self, market: &str, snapshot_time: NaiveDateTime, content: &MarketData, rows: u64, offset: u64) -> Result<MarketProgress> {
+        if offset > 0 {
+            info!("Resuming {} at row {} of {}.", market, offset, rows);
+        } else {
+            info!("Loading {} of {} rows parsed from {}.", rows, content.records.len(), market);
+        }
+        let progress = MarketProgress::new(self.pool.clone(), self.id, market, rows, offset);
+        let Some(pool) = &self.pool else {
+            return Ok(progress);
+        };
+        sqlx::query(r#"
+            INSERT INTO t_cboe_snapshot_etl_log_market (run_id, market, snapshot_time, status, updated_at, http_bytes, wire_bytes, rows_total, committed_offset)
+            VALUES ($1, $2, $3, 'running', $4, $5, $6, $7, $8)
+        "#)
+            .bind(self.id)
+            .bind(market)
+            .bind(snapshot_time)
+            .bind(now())
+            .bind(content.http_bytes as i64)
+            .bind(content.wire_bytes as i64)
+            .bind(rows as i64)
+            .bind(offset as i64)
+            .execute(pool)
+            .await?;
+        Ok(progress)
+    }
+
+    pub fn rows_fetched(&self) -> u64 {
+        self.markets.iter().map(|m| m.rows_fetched).sum()
+    }
+
+    pub fn rows_inserted(&self) -> u64 {
+        self.markets.iter().map(|m| m.rows_inserted).sum()
+    }
+
+    pub fn rows_skipped(&self) -> u64 {
+        self.markets.iter().map(|m| m.rows_skipped).sum()
+    }
+
+    pub fn parse_errors(&self) -> u64 {
+        self.markets.iter().map(|m| m.parse_errors).sum()
+    }
+
+    pub fn http_bytes(&self) -> u64 {
+        self.markets.iter().map(|m| m.http_bytes).sum()
+    }
+
+    pub fn wire_bytes(&self) -> u64 {
+        self.markets.iter().map(|m| m.wire_bytes).sum()
+    }
+
+    pub fn duration_secs(&self) -> i64 {
+        self.finished_at.map(|f| (f - self.started_at).num_seconds()).unwrap_or(0)
+    }
+
+    pub async fn finish(&mut self, outcome: Outcome, error_message: Option<String>) -> Result<()> {
+        self.finished_at = Some(now());
+        self.outcome = Some(outcome);
+        self.error_message = error_message;
+        if let Some(memory) = &mut self.memory {
+            memory["peak_rss_mb"] = serde_json::json!(crate::memory::peak_rss_mb());
+        }
+        let Some(pool) = &self.pool else {
+            return Ok(());
+        };
+        let markets: Vec<&str> = self.markets.iter().map(|m| m.market.as_str()).collect();
+        sqlx::query(r#"
+            UPDATE t_cboe_snapshot_etl_log
+            SET finished_at = $2, markets = $3, snapshot_time = $4, rows_fetched = $5, rows_inserted = $6,
+                parse_errors = $7, http_bytes = $8, outcome = $9, error_message = $10, symbol_filter = $11,
+                rows_skipped = $12, wire_bytes = $13, maintenance = $14, targets = $15, memory_limits = $16, resumed_from = $17,
+                format_drift = $18
+            WHERE id = $1
+        "#)
+            .bind(self.id)
+            .bind(self.finished_at)
+            .bind(markets.join(","))
+            .bind(self.snapshot_time)
+            .bind(self.rows_fetched() as i64)
+            .bind(self.rows_inserted() as i64)
+            .bind(self.parse_errors() as i64)
+            .bind(self.http_bytes() as i64)
+            .bind(outcome.as_str())
+            .bind(&self.error_message)
+            .bind(&self.symbol_filter)
+            .bind(self.rows_skipped() as i64)
+            .bind(self.wire_bytes() as i64)
+            .bind(&self.maintenance)
+            .bind((!self.targets.is_empty()).then(|| serde_json::json!(self.targets)))
+            .bind(&self.memory)
+            .bind(self.resumed_from)
+            .bind((!self.format_drift.is_empty()).then(|| serde_json::json!(self.format_drift)))
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub fn summary_title(&self) -> String {
+        let outcome = self.outcome.map(|o| o.as_str()).unwrap_or("running");
+        let job = self.job.as_ref().map(|j| format!(" [{}]", j)).unwrap_or_default();
+        match self.snapshot_time {
+            Some(t) => format!("CBOE snapshot load{} {}: {}", job, outcome, t.format("%Y-%m-%d %H:%M:%S")),
+            None => format!("CBOE snapshot load{} {}", job, outcome),
+        }
+    }
+
+    pub fn summary_text(&self) -> String {
+        let mut text = format!(
+            "{}\n{} rows inserted ({} fetched) in {}s",
+            self.summary_title(),
+            self.rows_inserted(),
+            self.rows_fetched(),
+            self.duration_secs()
+        );
+        if self.wire_bytes() > 0 {
+            text.push_str(&format!(", {} transferred ({} uncompressed)", format_bytes(self.wire_bytes()), format_bytes(self.http_bytes())));
+        }
+        for m in &self.markets {
+            text.push_str(&format!("\n  {}: {} rows", m.market, m.rows_inserted));
+        }
+        for d in &self.format_drift {
+            text.push_str(&format!("\n  format drift in {}: {}", d.source, d.changes.join("; ")));
+        }
+        for t in self.targets.iter().filter(|t| t.status != "done") {
+            text.push_str(&format!("\n  mirror {} {}: {}", t.target, t.market, t.status));
+        }
+        if let Some(e) = &self.error_message {
+            text.push_str(&format!("\nError: {}", e));
+        }
+        text
+    }
+
+    pub fn summary_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "run_id": self.id,
+            "job": self.job,
+            "outcome": self.outcome.map(|o| o.as_str()),
+            "started_at": self.started_at,
+            "finished_at": self.finished_at,
+            "duration_secs": self.duration_secs(),
+            "snapshot_time": self.snapshot_time,
+            "rows_fetched": self.rows_fetched(),
+            "rows_inserted": self.rows_inserted(),
+            "markets": self.markets,
+            "symbol_filter": self.symbol_filter,
+            "maintenance": self.maintenance,
+            "targets": self.targets,
+            "memory": self.memory,
+            "resumed_from": self.resumed_from,
+            "format_drift": self.format_drift,
+            "error": self.error_message,
+        })
+    }
+}
+
+// 同一个 job 最近一次成功加载的快照时间，多个 job 写入同一张表时不能用表里的最大时间判断是否需要加载
+pub async fn last_loaded(pool: &PgPool, job: &str) -> Result<Option<NaiveDateTime>> {
+    Ok(sqlx::query_scalar("SELECT max(snapshot_time) FROM t_cboe_snapshot_etl_log WHERE job = $1 AND outcome = 'success'")
+        .bind(job)
+        .fetch_one(pool)
+        .await?)
+}
+
+pub async fn print_recent_runs(pool: &PgPool, limit: i64, job: Option<&str>) -> Result<()> {
+    let rows = sqlx::query(r#"
+        SELECT id, started_at, finished_at, markets, snapshot_time, rows_fetched, rows_inserted,
+               parse_errors, http_bytes, wire_bytes, outcome, error_message, job
+        FROM t_cboe_snapshot_etl_log
+        WHERE $2::TEXT IS NULL OR job = $2
+        ORDER BY id DESC
+        LIMIT $1
+    "#)
+        .bind(limit)
+        .bind(job)
+        .fetch_all(pool)
+        .await?;
+
+    println!(
+        "{:>6}  {:<12}  {:<19}  {:>8}  {:<19}  {:<16}  {:>10}  {:>10}  {:>7}  {:>12}  {:>12}  {:<7}",
+        "id", "job", "started_at", "duration", "snapshot_time", "markets", "fetched", "inserted", "errors", "bytes", "wire_bytes", "outcome"
+    );
+    for row in rows {
+        let started_at: NaiveDateTime = row.try_get("started_at")?;
+        let finished_at: Option<NaiveDateTime> = row.try_get("finished_at")?;
+        let snapshot_time: Option<NaiveDateTime> = row.try_get("snapshot_time")?;
+        let outcome: String = row.try_get("outcome")?;
+        let error_message: Option<String> = row.try_get("error_message")?;
+        let duration = finished_at
+            .map(|f| format!("{}s", (f - started_at).num_seconds()))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:>6}  {:<12}  {:<19}  {:>8}  {:<19}  {:<16}  {:>10}  {:>10}  {:>7}  {:>12}  {:>12}  {}{}",
+            row.try_get::<i64, _>("id")?,
+            row.try_get::<Option<String>, _>("job")?.unwrap_or_else(|| "-".to_string()),
+            started_at.format("%Y-%m-%d %H:%M:%S"),
+            duration,
+            snapshot_time.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_else(|| "-".to_string()),
+            row.try_get::<Option<String>, _>("markets")?.unwrap_or_default(),
+            row.try_get::<Option<i64>, _>("rows_fetched")?.unwrap_or(0),
+            row.try_get::<Option<i64>, _>("rows_inserted")?.unwrap_or(0),
+            row.try_get::<Option<i64>, _>("parse_errors")?.unwrap_or(0),
+            row.try_get::<Option<i64>, _>("http_bytes")?.unwrap_or(0),
+            row.try_get::<Option<i64>, _>("wire_bytes")?.unwrap_or(0),
+            outcome,
+            error_message.map(|e| format!(" ({})", e)).unwrap_or_default(),
+        );
+    }
+    Ok(())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1 << 20 {
+        format!("{:.1} MB", bytes as f64 / (1 << 20) as f64)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result, anyhow, bail};
+use log::{info, warn};
+use reqwest::Url;
+use std::process::Stdio;
+
+// 数据库密码的来源，最多设置一个；读到的密码在启动时填进 DATABASE_URL
+const SOURCES: [&str; 4] = [
+    "DATABASE_PASSWORD_FILE",
+    "DATABASE_PASSWORD_COMMAND",
+    "DATABASE_PASSWORD_VAULT_PATH",
+    "DATABASE_PASSWORD_AWS_SECRET",
+];
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn read_file(path: &str) -> Result<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(meta) = std::fs::metadata(path)
+            && meta.permissions().mode() & 0o004 != 0
+        {
+            warn!("Secret file {} is world-readable.", path);
+        }
+    }
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read secret file {}", path))?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// 命令的 stderr 直接透传，方便排查；只取 stdout 作为密码
+async fn run_command(command: &str) -> Result<String> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .await
+        .context("Failed to run DATABASE_PASSWORD_COMMAND")?;
+    if !output.status.success() {
+        bail!("DATABASE_PASSWORD_COMMAND exited with {}", output.status);
+    }
+    let stdout = String::from_utf8(output.stdout).context("DATABASE_PASSWORD_COMMAND printed non-UTF-8 output")?;
+    Ok(stdout.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// 密钥内容是 JSON 对象时取其中一个字段（RDS 托管的密钥就是这种格式），否则整个字符串就是密码
+#[cfg(any(feature = "vault", feature = "aws-secrets"))]
+fn secret_field(secret: &serde_json::Value, field: &str, source: &str) -> Result<String> {
+    match secret {
+        serde_json::Value::String(s) => match serde_json::from_str::<serde_json::Value>(s) {
+            Ok(parsed) if parsed.is_object() => secret_field(&parsed, field, source),
+            _ => Ok(s.clone()),
+        },
+        other => other.get(field)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("{} has no string field {:?}", source, field)),
+    }
+}
+
+#[cfg(feature = "vault")]
+async fn vault(path: &str) -> Result<String> {
+    let addr = env("VAULT_ADDR").ok_or_else(|| anyhow!("DATABASE_PASSWORD_VAULT_PATH requires VAULT_ADDR"))?;
+    let token = match (env("VAULT_TOKEN"), env("VAULT_TOKEN_FILE")) {
+        (Some(token), _) => token,
+        (None, Some(file)) => read_file(&file)?,
+        (None, None) => bail!("DATABASE_PASSWORD_VAULT_PATH requires VAULT_TOKEN or VAULT_TOKEN_FILE"),
+    };
+    let url = format!("{}/v1/{}", addr.trim_end_matches('/'), path.trim_start_matches('/'));
+    let mut request = reqwest::Client::new().get(&url).header("X-Vault-Token", token);
+    if let Some(namespace) = env("VAULT_NAMESPACE") {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    let body: serde_json::Value = request.send().await?
+        .error_for_status()
+        .with_context(|| format!("Failed to read Vault secret {}", path))?
+        .json()
+        .await?;
+    // KV v2 的返回多包了一层 data
+    let data = &body["data"];
+    let data = data.get("data").filter(|d| d.is_object()).unwrap_or(data);
+    let name = env("DATABASE_PASSWORD_VAULT_FIELD").unwrap_or_else(|| "password".to_string());
+    secret_field(data, &name, &format!("Vault secret {}", path))
+}
+
+#[cfg(not(feature = "vault"))]
+async fn vault(_path: &str) -> Result<String> {
+    bail!("DATABASE_PASSWORD_VAULT_PATH requires building with --features vault")
+}
+
+// 凭证和区域走 AWS SDK 的默认链（环境变量、~/.aws、实例角色等）
+#[cfg(feature = "aws-secrets")]
+async fn aws(secret_id: &str) -> Result<String> {
+    let config = aws_config::load_from_env().await;
+    let client = aws_sdk_secretsmanager::Client::new(&config);
+    let output = client.get_secret_value().secret_id(secret_id).send().await
+        .with_context(|| format!("Failed to read AWS secret {}", secret_id))?;
+    let secret = output.secret_string()
+        .ok_or_else(|| anyhow!("AWS secret {} has no SecretString", secret_id))?;
+    let name = env("DATABASE_PASSWORD_AWS_FIELD").unwrap_or_else(|| "password".to_string());
+    secret_field(&serde_json::Value::String(secret.to_string()), &name, &format!("AWS secret {}", secret_id))
+}
+
+#[cfg(not(feature = "aws-secrets"))]
+async fn aws(_secret_id: &str) -> Result<String> {
+    bail!("DATABASE_PASSWORD_AWS_SECRET requires building with --features aws-secrets")
+}
+
+async fn database_password() -> Result<Option<String>> {
+    let configured: Vec<(&str, String)> = SOURCES.iter().filter_map(|name| env(name).map(|v| (*name, v))).collect();
+    let (name, value) = match configured.as_slice() {
+        [] => return Ok(None),
+        [(name, value)] => (*name, value.as_str()),
+        _ => bail!("Only one of {} may be set", configured.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")),
+    };
+    info!("Reading database password from {}.", name);
+    let password = match name {
+        "DATABASE_PASSWORD_FILE" => read_file(value)?,
+        "DATABASE_PASSWORD_COMMAND" => run_command(value).await?,
+        "DATABASE_PASSWORD_VAULT_PATH" => vault(value).await?,
+        _ => aws(value).await?,
+    };
+    if password.is_empty() {
+        bail!("{} returned an empty password", name);
+    }
+    Ok(Some(password))
+}
+
+// DATABASE_URL 里只放用户名和地址，密码由上面的来源提供；URL 里原有的密码会被覆盖
+pub async fn database_url() -> Result<String> {
+    let url = std::env::var("DATABASE_URL").context("DATABASE_URL is not set")?;
+    let Some(password) = database_password().await? else {
+        return Ok(url);
+    };
+    let mut parsed = Url::parse(&url).context("DATABASE_URL is not a valid URL")?;
+    if parsed.scheme() == "sqlite" {
+        bail!("Database password sources do not apply to SQLite URLs");
+    }
+    if parsed.password().is_some() {
+        warn!("DATABASE_URL already contains a password, overriding it.");
+    }
+    parsed.set_password(Some(&password)).map_err(|_| anyhow!("Cannot set a password on DATABASE_URL"))?;
+    Ok(parsed.to_string())
+}
@@ -0,0 +1,401 @@
+use anyhow::{Result, anyhow};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router, middleware};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::{error, info};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+use sqlx::{PgPool, Row};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::parse_timestamp;
+use crate::config::AnalyticsConfig;
+use crate::changes;
+use crate::events::{self, EventsConfig};
+use crate::query;
+use crate::ratelimit::{self, RateLimiter};
+use crate::storage::SnapshotTable;
+
+// 对外提供数据时的分页和限流；requests_per_minute 为 0 表示不限流
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub requests_per_minute: u32,
+    // 允许的突发请求数，0 表示等于 requests_per_minute
+    pub burst: u32,
+    // 在反向代理后面时按 X-Forwarded-For 限流
+    pub trust_forwarded_for: bool,
+    // 客户端到本服务之间的可信代理层数，从 X-Forwarded-For 右边数第 N 个地址是客户端
+    pub trusted_hops: usize,
+    pub page_size: i64,
+    pub max_page_size: i64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig { requests_per_minute: 0, burst: 0, trust_forwarded_for: false, trusted_hops: 1, page_size: 500, max_page_size: 5000 }
+    }
+}
+
+pub struct ApiError(StatusCode, anyhow::Error);
+
+impl ApiError {
+    fn bad_request(message: String) -> ApiError {
+        ApiError(StatusCode::BAD_REQUEST, anyhow!(message))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.0.is_server_error() {
+            error!("API request failed: {:#}", self.1);
+        }
+        (self.0, Json(json!({ "error": format!("{:#}", self.1) }))).into_response()
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+#[derive(Deserialize)]
+struct ChainParams {
+    expiry: Option<String>,
+}
+
+// 查询参数里的空值（?expiry_from=）按没有给出处理
+fn empty_as_none<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T::Err: std::fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => value.trim().parse().map(Some).map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+struct ContractParams {
+    // 逗号分隔的多个标的
+    #[serde(default, deserialize_with = "empty_as_none")]
+    underlying: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    expiry_from: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    expiry_to: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    min_volume: Option<i64>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    call_put: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    market: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    snapshot: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    limit: Option<i64>,
+    // 上一页返回的 next_page
+    #[serde(default, deserialize_with = "empty_as_none")]
+    page: Option<String>,
+}
+
+// 翻页游标：固定快照时间和上一页最后一行的排序键，翻页期间有新的加载也不会错位或重复
+struct Cursor {
+    snapshot_time: NaiveDateTime,
+    symbol: String,
+    call_put: String,
+    expiration: String,
+    strike_price: f64,
+    market: String,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let value = json!([self.snapshot_time, self.symbol, self.call_put, self.expiration, self.strike_price, self.market]);
+        value.to_string().bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn decode(page: &str) -> Option<Cursor> {
+        let bytes = (0..page.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(page.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let (snapshot_time, symbol, call_put, expiration, strike_price, market) = serde_json::from_slice(&bytes).ok()?;
+        Some(Cursor { snapshot_time, symbol, call_put, expiration, strike_price, market })
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiState {
+    pool: PgPool,
+    table: Arc<SnapshotTable>,
+    analytics: AnalyticsConfig,
+    api: Arc<ApiConfig>,
+}
+
+pub fn router(pool: PgPool, table: SnapshotTable, analytics: AnalyticsConfig, api: &ApiConfig) -> Router {
+    let router = Router::new()
+        .route("/snapshots/latest", get(latest_snapshot))
+        .route("/chain/{underlying}", get(chain))
+        .route("/summary/{underlying}", get(summary))
+        .route("/contracts", get(contracts))
+        .with_state(ApiState { pool, table: Arc::new(table), analytics, api: Arc::new(api.clone()) });
+    if api.requests_per_minute == 0 {
+        return router;
+    }
+    let limiter = Arc::new(RateLimiter::new(api.requests_per_minute, api.burst, if api.trust_forwarded_for { api.trusted_hops.max(1) } else { 0 }));
+    router.layer(middleware::from_fn_with_state(limiter, ratelimit::limit))
+}
+
+pub async fn serve(
+    pool: PgPool,
+    table: SnapshotTable,
+    bind: &str,
+    events_config: &EventsConfig,
+    analytics: AnalyticsConfig,
+    api: &ApiConfig,
+    token: CancellationToken,
+) -> Result<()> {
+    let mut app = router(pool.clone(), table, analytics, api);
+    // 开启 [events] 时额外提供 /events WebSocket，转发 run / daemon 发出的加载事件
+    if events_config.enabled {
+        let sender = events::relay(&pool, &events_config.channel).await?;
+        app = app.merge(Router::new().route("/events", get(events::websocket)).with_state(sender));
+    }
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    info!("Serving HTTP API on {}", listener.local_addr()?);
+    // 限流按客户端地址区分，需要连接信息
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(token.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+// 开启变化检测时合约数包括沿用的合约，last_updated_time 是这次快照的时间
+async fn latest_snapshot(State(ApiState { pool, table, .. }): State<ApiState>) -> ApiResult {
+    let Some((snapshot_time, source)) = changes::resolve(&pool, &table, None).await? else {
+        return Ok(Json(json!({ "last_updated_time": null, "contracts": 0, "underlyings": 0 })));
+    };
+    let row = sqlx::query(&format!("SELECT count(*) AS contracts, count(DISTINCT symbol) AS underlyings FROM ({source}) AS snapshot"))
+        .bind(snapshot_time)
+        .fetch_one(&pool)
+        .await?;
+    Ok(Json(json!({
+        "last_updated_time": snapshot_time,
+        "contracts": row.try_get::<i64, _>("contracts")?,
+        "underlyings": row.try_get::<i64, _>("underlyings")?,
+    })))
+}
+
+async fn chain(State(ApiState { pool, table, .. }): State<ApiState>, Path(underlying): Path<String>, Query(params): Query<ChainParams>) -> ApiResult {
+    let output = query::chain(&pool, &table, &underlying, params.expiry.as_deref(), None).await?;
+    Ok(Json(output.to_json()))
+}
+
+async fn summary(State(ApiState { pool, table, analytics, .. }): State<ApiState>, Path(underlying): Path<String>) -> ApiResult {
+    let output = query::symbol_summary(&pool, &table, &analytics, &underlying, None).await?;
+    Ok(Json(output.to_json()))
+}
+
+// 某个快照的合约明细，按 (symbol, call_put, expiration, strike_price, market) 排序分页；
+// 列名与快照表一致，对方可以直接用 json_populate_recordset 之类的函数装回一张表；
+// 开启变化检测时沿用的合约也会返回，它的 last_updated_time 是最近一次变化的时间
+async fn contracts(State(ApiState { pool, table, api, .. }): State<ApiState>, Query(params): Query<ContractParams>) -> ApiResult {
+    let cursor = match params.page.as_deref() {
+        Some(page) => Some(Cursor::decode(page).ok_or_else(|| ApiError::bad_request("Invalid page cursor".to_string()))?),
+        None => None,
+    };
+    let snapshot = match (&cursor, params.snapshot.as_deref()) {
+        (Some(cursor), _) => Some(cursor.snapshot_time),
+        (None, Some(value)) => Some(parse_timestamp(value).map_err(ApiError::bad_request)?),
+        (None, None) => None,
+    };
+    let Some((snapshot_time, source)) = changes::resolve(&pool, &table, snapshot).await? else {
+        return Ok(Json(json!({ "snapshot_time": null, "count": 0, "rows": [], "next_page": null })));
+    };
+    let limit = params.limit.unwrap_or(api.page_size).clamp(1, api.max_page_size.max(1));
+    let underlyings: Option<Vec<String>> = params.underlying.as_deref()
+        .map(|u| u.split(',').map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()).collect());
+
+    let rows = sqlx::query(&format!(r#"
+        SELECT symbol, call_put, expiration, cboe_expiration_date(expiration) AS expiration_date, strike_price,
+               volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price,
+               last_updated_time, days_to_expiration, expiration_class, is_adjusted, market
+        FROM ({source}) AS snapshot
+        WHERE ($2::TEXT[] IS NULL OR symbol = ANY($2))
+          AND ($3::DATE IS NULL OR cboe_expiration_date(expiration) >= $3)
+          AND ($4::DATE IS NULL OR cboe_expiration_date(expiration) <= $4)
+          AND ($5::BIGINT IS NULL OR volume >= $5)
+          AND ($6::TEXT IS NULL OR call_put = $6)
+          AND ($7::TEXT IS NULL OR market = $7)
+          AND ($8::TEXT IS NULL OR (symbol, call_put, expiration, strike_price, market) > ($8, $9, $10, $11, $12))
+        ORDER BY symbol, call_put, expiration, strike_price, market
+        LIMIT $13
+    "#))
+        .bind(snapshot_time)
+        .bind(underlyings)
+        .bind(params.expiry_from)
+        .bind(params.expiry_to)
+        .bind(params.min_volume)
+        .bind(params.call_put.map(|c| c.to_uppercase()))
+        .bind(params.market)
+        .bind(cursor.as_ref().map(|c| c.symbol.clone()))
+        .bind(cursor.as_ref().map(|c| c.call_put.clone()))
+        .bind(cursor.as_ref().map(|c| c.expiration.clone()))
+        .bind(cursor.as_ref().map(|c| c.strike_price))
+        .bind(cursor.as_ref().map(|c| c.market.clone()))
+        .bind(limit + 1)
+        .fetch_all(&pool)
+        .await?;
+
+    let more = rows.len() as i64 > limit;
+    let mut items = Vec::with_capacity(rows.len());
+    for row in rows.iter().take(limit as usize) {
+        items.push(json!({
+            "symbol": row.try_get::<String, _>("symbol")?,
+            "call_put": row.try_get::<String, _>("call_put")?,
+            "expiration": row.try_get::<String, _>("expiration")?,
+            "expiration_date": row.try_get::<Option<NaiveDate>, _>("expiration_date")?,
+            "strike_price": row.try_get::<f64, _>("strike_price")?,
+            "volume": row.try_get::<i64, _>("volume")?,
+            "matched": row.try_get::<i64, _>("matched")?,
+            "routed": row.try_get::<i64, _>("routed")?,
+            "bid_size": row.try_get::<i64, _>("bid_size")?,
+            "bid_price": row.try_get::<f64, _>("bid_price")?,
+            "ask_size": row.try_get::<i64, _>("ask_size")?,
+            "ask_price": row.try_get::<f64, _>("ask_price")?,
+            "last_price": row.try_get::<f64, _>("last_price")?,
+            "last_updated_time": row.try_get::<NaiveDateTime, _>("last_updated_time")?,
+            "days_to_expiration": row.try_get::<Option<i32>, _>("days_to_expiration")?,
+            "expiration_class": row.try_get::<Option<String>, _>("expiration_class")?,
+            "is_adjusted": row.try_get::<bool, _>("is_adjusted")?,
+            "market": row.try_get::<String, _>("market")?,
+        }));
+    }
+    let next_page = match (more, rows.get(limit as usize - 1)) {
+        (true, Some(last)) => Some(Cursor {
+            snapshot_time,
+            symbol: last.try_get("symbol")?,
+            call_put: last.try_get("call_put")?,
+            expiration: last.try_get("expiration")?,
+            strike_price: last.try_get("strike_price")?,
+            market: last.try_get("market")?,
+        }.encode()),
+        _ => None,
+    };
+    Ok(Json(json!({ "snapshot_time": snapshot_time, "count": items.len(), "rows": items, "next_page": next_page })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cboe::OptionRecord;
+    use crate::changes;
+    use crate::config::StorageConfig;
+    use crate::db;
+    use crate::memory::MemoryConfig;
+    use crate::run_log::MarketProgress;
+    use crate::storage::{InsertOptions, Provenance};
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+    const SCHEMA: &str = "cboe_serve_test";
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn record(call_put: &str, volume: i64) -> OptionRecord {
+        OptionRecord {
+            symbol: "SPX".to_string(),
+            call_put: call_put.to_string(),
+            expiration: "2026-10-16".to_string(),
+            strike_price: 5800.0,
+            volume,
+            matched: volume,
+            routed: 0,
+            bid_size: 5,
+            bid_price: 12.5,
+            ask_size: 7,
+            ask_price: 13.0,
+            last_price: 12.8,
+        }
+    }
+
+    // 在 TEST_DATABASE_URL 的库里新建一个 schema，按顺序执行全部 migration
+    async fn test_pool() -> PgPool {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+        let options = PgConnectOptions::from_str(&url).unwrap();
+        let pool = PgPoolOptions::new().connect_with(options.clone()).await.unwrap();
+        sqlx::raw_sql(&format!("DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; CREATE SCHEMA {SCHEMA}")).execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let pool = PgPoolOptions::new().connect_with(options.options([("search_path", SCHEMA)])).await.unwrap();
+        let mut migrations: Vec<_> = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations")).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        migrations.sort();
+        for path in migrations {
+            let sql = std::fs::read_to_string(&path).unwrap();
+            sqlx::raw_sql(&sql).execute(&pool).await.unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        }
+        pool
+    }
+
+    // 按加载的顺序：检测变化、只写入变化的合约、更新状态表
+    async fn load(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime, records: &[OptionRecord]) {
+        let changes = changes::detect(pool, "opt", records).await.unwrap();
+        let options = InsertOptions { batch_size: None, workers: 1, memory: MemoryConfig::default() };
+        let progress = MarketProgress::detached("opt", changes.changed.len() as u64);
+        let provenance = Provenance { market: "opt", source_url: None };
+        db::insert_records(pool, table, &changes.changed, snapshot_time, &provenance, &options, &progress, &CancellationToken::new()).await.unwrap();
+        changes::record(pool, "opt", snapshot_time, &changes).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres TEST_DATABASE_URL"]
+    async fn latest_snapshot_includes_carried_forward_contracts() {
+        let pool = test_pool().await;
+        let table = SnapshotTable::from_config(&StorageConfig { change_detection: true, ..Default::default() }).unwrap();
+        load(&pool, &table, at("2026-10-14 10:00:00"), &[record("C", 10), record("P", 4)]).await;
+        // 第二次快照只有 call 变了，put 沿用第一次写入的行
+        load(&pool, &table, at("2026-10-14 10:15:00"), &[record("C", 25), record("P", 4)]).await;
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT count(*) FROM {table}")).fetch_one(&pool).await.unwrap();
+        assert_eq!(rows, 3);
+
+        let state = ApiState { pool: pool.clone(), table: Arc::new(table.clone()), analytics: AnalyticsConfig::default(), api: Arc::new(ApiConfig::default()) };
+        let Json(latest) = latest_snapshot(State(state.clone())).await.ok().unwrap();
+        assert_eq!(latest["contracts"], 2);
+        assert_eq!(latest["last_updated_time"], json!(at("2026-10-14 10:15:00")));
+
+        let Json(chain_rows) = chain(State(state.clone()), Path("spx".to_string()), Query(ChainParams { expiry: None })).await.ok().unwrap();
+        let volumes: Vec<_> = chain_rows.as_array().unwrap().iter().map(|r| (r["cp"].clone(), r["volume"].clone())).collect();
+        assert_eq!(volumes, vec![(json!("C"), json!(25)), (json!("P"), json!(4))]);
+
+        let Json(summary_rows) = summary(State(state.clone()), Path("SPX".to_string())).await.ok().unwrap();
+        assert_eq!(summary_rows[0]["contracts"], 2);
+
+        let params = serde_json::from_value(json!({})).unwrap();
+        let Json(page) = contracts(State(state), Query(params)).await.ok().unwrap();
+        assert_eq!(page["count"], 2);
+        assert_eq!(page["rows"][1]["call_put"], "P");
+        assert_eq!(page["rows"][1]["last_updated_time"], json!(at("2026-10-14 10:00:00")));
+
+        // 历史快照用 cboe_snapshot_at 还原
+        let first = query::chain(&pool, &table, "SPX", None, Some(at("2026-10-14 10:00:00"))).await.unwrap().to_json();
+        assert_eq!(first[0]["volume"], 10);
+        assert_eq!(first.as_array().unwrap().len(), 2);
+        let top = query::top_volume(&pool, &table, &AnalyticsConfig::default(), 10, None).await.unwrap().to_json();
+        assert_eq!(top.as_array().unwrap().len(), 2);
+
+        sqlx::raw_sql(&format!("DROP SCHEMA {SCHEMA} CASCADE")).execute(&pool).await.unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::cboe::OptionRecord;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SinksConfig {
+    #[cfg(feature = "kafka")]
+    pub kafka: Option<crate::kafka::KafkaConfig>,
+    #[cfg(not(feature = "kafka"))]
+    pub kafka: Option<toml::Value>,
+    #[cfg(feature = "redis")]
+    pub redis: Option<crate::redis_cache::RedisConfig>,
+    #[cfg(not(feature = "redis"))]
+    pub redis: Option<toml::Value>,
+    #[cfg(feature = "grpc")]
+    pub grpc: Option<crate::grpc::GrpcConfig>,
+    #[cfg(not(feature = "grpc"))]
+    pub grpc: Option<toml::Value>,
+}
+
+// 除 Postgres 外的附加输出，写入失败只记录日志，不影响主流程
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn write(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) -> Result<()>;
+}
+
+pub struct Sinks {
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl Sinks {
+    pub async fn from_config(config: &SinksConfig) -> Result<Sinks> {
+        #[cfg_attr(not(any(feature = "kafka", feature = "redis", feature = "grpc")), allow(unused_mut))]
+        let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = &config.kafka {
+            sinks.push(Box::new(crate::kafka::KafkaSink::new(kafka).await?));
+        }
+        #[cfg(not(feature = "kafka"))]
+        if config.kafka.is_some() {
+            anyhow::bail!("[sinks.kafka] requires building with --features kafka");
+        }
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &config.redis {
+            sinks.push(Box::new(crate::redis_cache::RedisSink::new(redis).await?));
+        }
+        #[cfg(not(feature = "redis"))]
+        if config.redis.is_some() {
+            anyhow::bail!("[sinks.redis] requires building with --features redis");
+        }
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = &config.grpc {
+            sinks.push(Box::new(crate::grpc::GrpcSink::new(grpc).await?));
+        }
+        #[cfg(not(feature = "grpc"))]
+        if config.grpc.is_some() {
+            anyhow::bail!("[sinks.grpc] requires building with --features grpc");
+        }
+        Ok(Sinks { sinks })
+    }
+
+    pub async fn write(&self, market: &str, snapshot_time: NaiveDateTime, records: &[OptionRecord]) {
+        for sink in &self.sinks {
+            match sink.write(market, snapshot_time, records).await {
+                Ok(()) => info!("Wrote {} records from {} to {} sink.", records.len(), market, sink.name()),
+                Err(e) => warn!("Failed to write {} records to {} sink: {:#}", market, sink.name(), e),
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::cboe::OptionRecord;
+use crate::filter::SymbolFilter;
+
+// 一个市场一次拉取的记录和来源的元数据，与来源格式（CSV、接口 ...）无关
+pub struct MarketData {
+    pub records: Vec<OptionRecord>,
+    // 来源格式的字段名（CBOE 是 CSV 表头），格式漂移检测按市场和上一次比较
+    pub fields: Vec<String>,
+    // 来源自己检查出的格式问题，试运行时报告
+    pub format_problems: Vec<String>,
+    pub http_bytes: u64,
+    pub wire_bytes: u64,
+    pub parse_errors: u64,
+    pub filtered: u64,
+    // 下载地址，本地文件导入时为空
+    pub source_url: Option<String>,
+}
+
+// 快照来源：先取快照时间，再按市场逐批拉取 OptionRecord；加载流程只依赖这个 trait，解析和格式检查留在各来源里
+#[async_trait]
+pub trait SnapshotSource: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn markets(&self) -> Vec<String>;
+    async fn snapshot_time(&self) -> Result<NaiveDateTime>;
+    async fn fetch(&self, market: &str, filter: &SymbolFilter) -> Result<MarketData>;
+
+    // 最近一次 snapshot_time 读到的元数据的格式签名（取快照时间失败时也有），用于格式漂移检测
+    fn format_signature(&self) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use chrono_tz::America;
+use log::info;
+use regex::Regex;
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool};
+use sqlx::Row;
+use tokio_util::sync::CancellationToken;
+
+use crate::cboe::OptionRecord;
+use crate::config::DatabaseConfig;
+use crate::run_log::MarketProgress;
+use crate::shutdown::Cancelled;
+use crate::storage::{Provenance, DEFAULT_TABLE, InsertOptions, SnapshotStore, SnapshotTable, pool_options, quote_ident};
+
+const SCHEMA: &str = include_str!("../migrations-sqlite/20261014-000001-create-options-snapshot.sql");
+
+// 旧版本创建的文件缺少的列，打开时补上
+const ADDED_COLUMNS: [(&str, &str); 8] = [
+    ("days_to_expiration", "INTEGER"),
+    ("expiration_class", "TEXT"),
+    ("is_adjusted", "INTEGER NOT NULL DEFAULT 0"),
+    ("source", "TEXT NOT NULL DEFAULT 'symbol_data'"),
+    ("is_expired", "INTEGER NOT NULL DEFAULT 0"),
+    ("volume_delta", "INTEGER"),
+    ("matched_delta", "INTEGER"),
+    ("routed_delta", "INTEGER"),
+];
+
+// 表名可配置时索引名跟着表名走，避免同一个文件里的两张表索引重名
+fn schema(table: &str) -> String {
+    if table == DEFAULT_TABLE {
+        return SCHEMA.to_string();
+    }
+    let sql = SCHEMA.replace(DEFAULT_TABLE, &quote_ident(table, '"'));
+    let index = Regex::new(r"idx_options_(\w+)").expect("valid regex");
+    index.replace_all(&sql, |caps: &regex::Captures| quote_ident(&format!("idx_{}_{}", table, &caps[1]), '"')).into_owned()
+}
+
+// 本地 SQLite 文件，适合离线研究；文件和表不存在时自动创建
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    // 每个 job 可以写不同的表，连接时把所有要用到的表都建好
+    pub async fn connect(url: &str, config: &DatabaseConfig, tables: &[SnapshotTable]) -> Result<SqliteStore> {
+        if tables.iter().any(|t| t.schema.is_some()) {
+            bail!("[storage] schema is not supported by the SQLite backend");
+        }
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .statement_cache_capacity(config.statement_cache_capacity);
+        let pool = pool_options::<Sqlite>(config).connect_with(options).await?;
+        for table in tables {
+            prepare(&pool, table).await?;
+        }
+        Ok(SqliteStore { pool })
+    }
+}
+
+async fn prepare(pool: &SqlitePool, table: &SnapshotTable) -> Result<()> {
+    sqlx::raw_sql(&schema(&table.name)).execute(pool).await?;
+    for (column, ty) in ADDED_COLUMNS {
+        if !has_column(pool, &table.name, column).await? {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, ty))
+                .execute(pool)
+                .await?;
+        }
+    }
+    if !has_column(pool, &table.name, "market").await? {
+        rebuild_with_market(pool, table).await?;
+    }
+    Ok(())
+}
+
+async fn has_column(pool: &SqlitePool, table: &str, column: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar("SELECT count(*) > 0 FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_one(pool)
+        .await?;
+    Ok(exists)
+}
+
+// 旧文件的唯一键不含 market，SQLite 不能修改约束，只能改名后按新表结构重建再拷回数据
+async fn rebuild_with_market(pool: &SqlitePool, table: &SnapshotTable) -> Result<()> {
+    let old = quote_ident(&format!("{}_old", table.name), '"');
+    // ADDED_COLUMNS 在这之前已经补到旧表上
+    let base = "id, symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, \
+                last_price, last_updated_time, etl_in_dt";
+    let columns = std::iter::once(base).chain(ADDED_COLUMNS.iter().map(|(c, _)| *c)).collect::<Vec<_>>().join(", ");
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!("ALTER TABLE {} RENAME TO {}", table, old)).execute(&mut *tx).await?;
+    sqlx::raw_sql(&schema(&table.name)).execute(&mut *tx).await?;
+    let copied = sqlx::query(&format!("INSERT INTO {} ({cols}) SELECT {cols} FROM {}", table, old, cols = columns))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    sqlx::query(&format!("DROP TABLE {}", old)).execute(&mut *tx).await?;
+    tx.commit().await?;
+    // 索引名还被旧表占着，删表之后再建一次
+    sqlx::raw_sql(&schema(&table.name)).execute(pool).await?;
+    info!("Rebuilt {} with a market column, copied {} rows.", table, copied);
+    Ok(())
+}
+
+#[async_trait]
+impl SnapshotStore for SqliteStore {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    // 和 Postgres 一样只看完整快照，导入或延迟报价的数据不影响是否需要重新加载
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+        let row = sqlx::query(&format!("SELECT max(last_updated_time) FROM {table} WHERE source = 'symbol_data'"))
+            .fetch_one(&self.pool)
+            .await?;
+        let max_time: Option<NaiveDateTime> = row.try_get(0)?;
+        Ok(max_time)
+    }
+
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64> {
+        let etl_in_dt = chrono::Utc::now().with_timezone(&America::New_York).naive_local();
+        let sizer = options.batch_sizer(records.len());
+        let mut inserted = 0;
+        let mut committed = 0;
+        let mut batches = 0;
+
+        for (batch, chunk) in sizer.chunks(records) {
+            let mut tx = self.pool.begin().await?;
+            let insert = format!(r#"
+                INSERT INTO {table}
+                (symbol, call_put, expiration, strike_price, volume, matched, routed, bid_size, bid_price, ask_size, ask_price, last_price, last_updated_time, etl_in_dt, days_to_expiration, expiration_class, is_adjusted, market, source_url)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (symbol, call_put, expiration, strike_price, last_updated_time, market)
+                DO UPDATE SET
+                    volume = excluded.volume,
+                    matched = excluded.matched,
+                    routed = excluded.routed,
+                    bid_size = excluded.bid_size,
+                    bid_price = excluded.bid_price,
+                    ask_size = excluded.ask_size,
+                    ask_price = excluded.ask_price,
+                    last_price = excluded.last_price,
+                    etl_in_dt = excluded.etl_in_dt,
+                    days_to_expiration = excluded.days_to_expiration,
+                    expiration_class = excluded.expiration_class,
+                    is_adjusted = excluded.is_adjusted,
+                    source_url = excluded.source_url
+            "#);
+            for rec in chunk {
+                if token.is_cancelled() {
+                    tx.rollback().await?;
+                    info!("Rolled back batch {} after shutdown signal, {} rows committed.", batches + 1, committed);
+                    return Err(Cancelled.into());
+                }
+                let class = rec.classify(snapshot_time.date());
+                let result = sqlx::query(&insert)
+                    .bind(&rec.symbol)
+                    .bind(&rec.call_put)
+                    .bind(&rec.expiration)
+                    .bind(rec.strike_price)
+                    .bind(rec.volume)
+                    .bind(rec.matched)
+                    .bind(rec.routed)
+                    .bind(rec.bid_size)
+                    .bind(rec.bid_price)
+                    .bind(rec.ask_size)
+                    .bind(rec.ask_price)
+                    .bind(rec.last_price)
+                    .bind(snapshot_time)
+                    .bind(etl_in_dt)
+                    .bind(class.as_ref().map(|c| c.days_to_expiration))
+                    .bind(class.as_ref().map(|c| c.expiration_class))
+                    .bind(rec.is_adjusted())
+                    .bind(provenance.market)
+                    .bind(provenance.source_url)
+                    .execute(&mut *tx)
+                    .await?;
+                inserted += result.rows_affected();
+                progress.tick();
+            }
+            tx.commit().await?;
+
+            committed += chunk.len() as u64;
+            batches += 1;
+            progress.record_committed(committed, batches, batch).await?;
+        }
+
+        info!("Inserted {} records in {} batches.", records.len(), batches);
+        Ok(inserted)
+    }
+
+    async fn clean_duplicate_data(&self, table: &SnapshotTable) -> Result<u64> {
+        let result = sqlx::query(&format!(r#"
+            DELETE FROM {table}
+            WHERE id IN (
+                SELECT id FROM (
+                    SELECT id,
+                           row_number() OVER (
+                               PARTITION BY symbol, call_put, expiration, strike_price, last_updated_time, market
+                               ORDER BY etl_in_dt DESC, id DESC
+                           ) AS rn
+                    FROM {table}
+                )
+                WHERE rn > 1
+            )
+        "#))
+            .execute(&self.pool)
+            .await?;
+        info!("Removed {} duplicate rows.", result.rows_affected());
+        Ok(result.rows_affected())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+}
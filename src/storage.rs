@@ -0,0 +1,376 @@
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::info;
+use sqlx::{Connection, PgConnection, PgPool};
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::{PgConnectOptions, Postgres};
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(test)]
+use crate::cboe::ContractKey;
+use crate::cboe::OptionRecord;
+use crate::config::{DatabaseConfig, StorageConfig};
+use crate::db::PgStore;
+use crate::memory::{BatchSizer, MemoryConfig};
+use crate::run_log::MarketProgress;
+
+pub const DEFAULT_TABLE: &str = "t_options_cboe_snapshot";
+
+// 快照表名按 [storage] 配置设置，所有 SQL 用 Display 拼接带引号的全名；每个 job 有自己的 SnapshotTable
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotTable {
+    pub schema: Option<String>,
+    pub name: String,
+    // storage.change_detection：快照表只有变化的行，读最新快照要走 changes::snapshot_sql
+    pub change_detection: bool,
+    sql: String,
+}
+
+impl Default for SnapshotTable {
+    fn default() -> Self {
+        SnapshotTable::new(None, DEFAULT_TABLE.to_string())
+    }
+}
+
+impl fmt::Display for SnapshotTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.sql)
+    }
+}
+
+impl SnapshotTable {
+    fn new(schema: Option<String>, name: String) -> SnapshotTable {
+        let mut table = SnapshotTable { schema, name, change_detection: false, sql: String::new() };
+        table.sql = table.quoted('"');
+        table
+    }
+
+    // latest_table、变化检测用的 t_options_cboe_latest / t_cboe_contract_state / cboe_snapshot_at
+    // 是 migration 建好的固定对象，只对应默认的快照表，自定义表名时不能开启
+    pub fn from_config(config: &StorageConfig) -> Result<SnapshotTable> {
+        let schema = config.schema.clone().filter(|s| !s.is_empty());
+        let name = config.table.clone().unwrap_or_else(|| DEFAULT_TABLE.to_string());
+        if name.is_empty() {
+            bail!("[storage] table must not be empty");
+        }
+        let mut table = SnapshotTable::new(schema, name);
+        if config.latest_table {
+            table.require_default("storage.latest_table")?;
+        }
+        if config.change_detection {
+            table.require_default("storage.change_detection")?;
+            table.change_detection = true;
+        }
+        Ok(table)
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.schema.is_none() && self.name == DEFAULT_TABLE
+    }
+
+    // 用到固定的配套表（t_options_cboe_snapshot_archive 等）的功能在自定义表名时报错，不去读写默认表
+    pub fn require_default(&self, feature: &str) -> Result<()> {
+        if !self.is_default() {
+            bail!("{} only works with the default snapshot table {}, not {}", feature, DEFAULT_TABLE, self);
+        }
+        Ok(())
+    }
+
+    pub fn quoted(&self, quote: char) -> String {
+        self.qualify(&self.name, quote)
+    }
+
+    // 同一 schema 下的其它对象（分区等），带引号的全名
+    pub fn qualify(&self, name: &str, quote: char) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", quote_ident(schema, quote), quote_ident(name, quote)),
+            None => quote_ident(name, quote),
+        }
+    }
+}
+
+// Postgres / SQLite 用双引号，MySQL 用反引号，标识符里的引号写两次
+pub fn quote_ident(name: &str, quote: char) -> String {
+    let escaped = name.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+pub struct InsertOptions {
+    // 每 N 行提交一次，None 表示每个市场一个事务
+    pub batch_size: Option<usize>,
+    // 并行写入的连接数，目前只有 Postgres 支持，其它后端顺序写入
+    pub workers: usize,
+    pub memory: MemoryConfig,
+}
+
+impl InsertOptions {
+    // 同时写入的批次数，受 [memory] max_in_flight_batches 限制
+    pub fn in_flight(&self) -> usize {
+        match self.memory.max_in_flight_batches {
+            0 => self.workers.max(1),
+            max => self.workers.clamp(1, max),
+        }
+    }
+
+    // default 是没有指定批次大小时各后端原来的行为（整个市场一批，或按连接数平分）
+    pub fn batch_sizer(&self, default: usize) -> BatchSizer {
+        let initial = self.batch_size.unwrap_or(if self.memory.max_rss_mb > 0 { self.memory.batch_size } else { default });
+        BatchSizer::new(&self.memory, initial)
+    }
+}
+
+// 写入时记录的来源：CBOE 市场代码（导入时未指定为空）和请求地址（storage.source_url 开启时）
+pub struct Provenance<'a> {
+    pub market: &'a str,
+    pub source_url: Option<&'a str>,
+}
+
+// 快照表的读写；Postgres 之外的后端只支持加载，运行日志、查询等子命令仍需要 Postgres
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    fn backend(&self) -> &'static str;
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>>;
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64>;
+    async fn clean_duplicate_data(&self, table: &SnapshotTable) -> Result<u64>;
+    async fn close(&self);
+}
+
+// 交给 Database 之后还要查看内容（比如测试里的 MemoryStore）时传 Arc
+#[async_trait]
+impl<S: SnapshotStore + ?Sized> SnapshotStore for Arc<S> {
+    fn backend(&self) -> &'static str {
+        self.as_ref().backend()
+    }
+
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+        self.as_ref().max_updated_time(table).await
+    }
+
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64> {
+        self.as_ref().insert_records(table, records, snapshot_time, provenance, options, progress, token).await
+    }
+
+    async fn clean_duplicate_data(&self, table: &SnapshotTable) -> Result<u64> {
+        self.as_ref().clean_duplicate_data(table).await
+    }
+
+    async fn close(&self) {
+        self.as_ref().close().await
+    }
+}
+
+// 各后端共用的连接池参数
+pub fn pool_options<DB: sqlx::Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    let limit = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    PoolOptions::new()
+        .max_connections(config.max_connections.max(1))
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs.max(1)))
+        .idle_timeout(limit(config.idle_timeout_secs))
+        .max_lifetime(limit(config.max_lifetime_secs))
+}
+
+// 会话参数用 set_config 而不是启动参数 options，pgbouncer 默认拒绝不认识的启动参数
+async fn connect_postgres(url: &str, config: &DatabaseConfig) -> Result<PgPool> {
+    let mut options = PgConnectOptions::from_str(url)?.statement_cache_capacity(config.statement_cache_capacity);
+    if let Some(name) = &config.application_name {
+        options = options.application_name(name);
+    }
+    let session: Vec<(String, String)> = config.session.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    if !session.is_empty() {
+        info!("Session settings: {}", session.iter().map(|(k, v)| format!("{} = {}", k, v)).collect::<Vec<_>>().join(", "));
+        // 连接池里 after_connect 失败只会一直重试到超时，先用单独的连接检查一遍，报出具体的参数错误
+        let mut conn = PgConnection::connect_with(&options).await?;
+        for (name, value) in &session {
+            sqlx::query("SELECT set_config($1, $2, false)").bind(name).bind(value).execute(&mut conn).await
+                .with_context(|| format!("Invalid [database.session] setting {} = {:?}", name, value))?;
+        }
+        conn.close().await?;
+    }
+    let pool = pool_options::<Postgres>(config)
+        .after_connect(move |conn, _| {
+            let session = session.clone();
+            Box::pin(async move {
+                for (name, value) in &session {
+                    sqlx::query("SELECT set_config($1, $2, false)").bind(name).bind(value).execute(&mut *conn).await?;
+                }
+                Ok(())
+            })
+        })
+        .connect_with(options)
+        .await?;
+    Ok(pool)
+}
+
+pub struct Database {
+    postgres: Option<PgPool>,
+    pub store: Box<dyn SnapshotStore>,
+}
+
+impl Database {
+    // SQLite 在连接时创建 tables 里的快照表，其它后端需要先建好
+    pub async fn connect(url: &str, config: &DatabaseConfig, tables: &[SnapshotTable]) -> Result<Database> {
+        let scheme = url.split_once(':').map(|(s, _)| s).unwrap_or_default();
+        match scheme {
+            "postgres" | "postgresql" => {
+                let pool = connect_postgres(url, config).await?;
+                Ok(Database { postgres: Some(pool.clone()), store: Box::new(PgStore::new(pool)) })
+            }
+            "sqlite" => {
+                let store = crate::sqlite::SqliteStore::connect(url, config, tables).await?;
+                info!("Using SQLite backend; run log and Postgres-only features are disabled.");
+                Ok(Database::with_store(store))
+            }
+            #[cfg(feature = "mysql")]
+            "mysql" | "mariadb" => {
+                let store = crate::mysql::MySqlStore::connect(url, config).await?;
+                info!("Using MySQL backend; run log and Postgres-only features are disabled.");
+                Ok(Database::with_store(store))
+            }
+            #[cfg(not(feature = "mysql"))]
+            "mysql" | "mariadb" => bail!("MySQL support requires building with --features mysql"),
+            other => bail!("Unsupported database URL scheme {:?}", other),
+        }
+    }
+
+    // 不连接数据库，直接使用给定的存储；和 SQLite / MySQL 一样没有 Postgres 的附加功能
+    pub fn with_store(store: impl SnapshotStore + 'static) -> Database {
+        Database { postgres: None, store: Box::new(store) }
+    }
+
+    pub fn postgres(&self) -> Result<&PgPool> {
+        self.postgres.as_ref()
+            .ok_or_else(|| anyhow!("This command requires a Postgres DATABASE_URL, current backend is {}", self.store.backend()))
+    }
+
+    pub fn postgres_opt(&self) -> Option<&PgPool> {
+        self.postgres.as_ref()
+    }
+
+    pub async fn close(&self) {
+        self.store.close().await;
+        info!("Closed {} connections.", self.store.backend());
+    }
+}
+
+// 内存里的快照表，按 (表, 快照时间, 市场) 保存，同一合约再次写入时覆盖，和各后端的 upsert 一致
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+    snapshots: std::sync::Mutex<BTreeMap<SnapshotKey, BTreeMap<ContractKey, OptionRecord>>>,
+}
+
+// 表名、快照时间、市场
+#[cfg(test)]
+type SnapshotKey = (String, NaiveDateTime, String);
+
+#[cfg(test)]
+impl MemoryStore {
+    pub fn snapshot_times(&self) -> Vec<NaiveDateTime> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let mut times: Vec<NaiveDateTime> = snapshots.keys().map(|(_, t, _)| *t).collect();
+        times.dedup();
+        times
+    }
+
+    pub fn records(&self, table: &SnapshotTable, snapshot_time: NaiveDateTime, market: &str) -> Vec<OptionRecord> {
+        let snapshots = self.snapshots.lock().unwrap();
+        let key = (table.to_string(), snapshot_time, market.to_string());
+        snapshots.get(&key).map(|rows| rows.values().cloned().collect()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl SnapshotStore for MemoryStore {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn max_updated_time(&self, table: &SnapshotTable) -> Result<Option<NaiveDateTime>> {
+        let table = table.to_string();
+        Ok(self.snapshots.lock().unwrap().keys().filter(|(t, _, _)| *t == table).map(|(_, time, _)| *time).max())
+    }
+
+    async fn insert_records(
+        &self,
+        table: &SnapshotTable,
+        records: &[OptionRecord],
+        snapshot_time: NaiveDateTime,
+        provenance: &Provenance,
+        _options: &InsertOptions,
+        progress: &MarketProgress,
+        token: &CancellationToken,
+    ) -> Result<u64> {
+        if token.is_cancelled() {
+            return Err(crate::shutdown::Cancelled.into());
+        }
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let rows = snapshots.entry((table.to_string(), snapshot_time, provenance.market.to_string())).or_default();
+        for rec in records {
+            rows.insert(rec.key(), rec.clone());
+            progress.tick();
+        }
+        progress.committed(0..records.len() as u64);
+        Ok(records.len() as u64)
+    }
+
+    async fn clean_duplicate_data(&self, _table: &SnapshotTable) -> Result<u64> {
+        Ok(0)
+    }
+
+    async fn close(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage(schema: Option<&str>, table: Option<&str>) -> StorageConfig {
+        StorageConfig { schema: schema.map(str::to_string), table: table.map(str::to_string), ..Default::default() }
+    }
+
+    #[test]
+    fn quotes_schema_and_table() {
+        let table = SnapshotTable::from_config(&storage(Some("staging"), Some("cboe \"snap\""))).unwrap();
+        assert_eq!(table.to_string(), r#""staging"."cboe ""snap""""#);
+        assert_eq!(table.quoted('`'), "`staging`.`cboe \"snap\"`");
+        assert!(SnapshotTable::from_config(&storage(None, None)).unwrap().is_default());
+    }
+
+    #[test]
+    fn custom_table_rejects_companion_tables() {
+        let config = StorageConfig { latest_table: true, ..storage(None, Some("cboe_snapshot")) };
+        assert!(SnapshotTable::from_config(&config).is_err());
+        let config = StorageConfig { change_detection: true, ..storage(Some("staging"), None) };
+        assert!(SnapshotTable::from_config(&config).is_err());
+        let config = StorageConfig { latest_table: true, change_detection: true, ..storage(None, None) };
+        assert!(SnapshotTable::from_config(&config).unwrap().change_detection);
+    }
+}
@@ -0,0 +1,319 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+
+use crate::cboe::OptionRecord;
+use crate::chain::{Expiry, OptionChain};
+
+// 快照里没有标的价格和隐含波动率：远期价格由看涨看跌平价推出，隐含波动率用 Black-76 从买卖中间价反解
+const DAYS_PER_YEAR: f64 = 365.0;
+const MIN_VOL: f64 = 1e-4;
+const MAX_VOL: f64 = 5.0;
+
+// 标准正态分布函数，Abramowitz-Stegun 26.2.17 近似，误差小于 7.5e-8
+fn norm_cdf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.231_641_9 * x.abs());
+    let poly = t * (0.319_381_530 + t * (-0.356_563_782 + t * (1.781_477_937 + t * (-1.821_255_978 + t * 1.330_274_429))));
+    let tail = (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt() * poly;
+    if x >= 0.0 { 1.0 - tail } else { tail }
+}
+
+// Black-76 期权价格，discount 是到期日的贴现因子
+pub fn black76(forward: f64, strike: f64, years: f64, vol: f64, discount: f64, is_call: bool) -> f64 {
+    let stdev = vol * years.sqrt();
+    let d1 = ((forward / strike).ln() + 0.5 * stdev * stdev) / stdev;
+    let d2 = d1 - stdev;
+    if is_call {
+        discount * (forward * norm_cdf(d1) - strike * norm_cdf(d2))
+    } else {
+        discount * (strike * norm_cdf(-d2) - forward * norm_cdf(-d1))
+    }
+}
+
+// 二分法反解（价格对波动率单调），价格低于内在价值或高于上限时为 None
+pub fn implied_vol(price: f64, forward: f64, strike: f64, years: f64, discount: f64, is_call: bool) -> Option<f64> {
+    if !(price > 0.0 && forward > 0.0 && strike > 0.0 && years > 0.0) {
+        return None;
+    }
+    let (mut low, mut high) = (MIN_VOL, MAX_VOL);
+    if price <= black76(forward, strike, years, low, discount, is_call) || price >= black76(forward, strike, years, high, discount, is_call) {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        if black76(forward, strike, years, mid, discount, is_call) < price {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-7 {
+            break;
+        }
+    }
+    Some(0.5 * (low + high))
+}
+
+// 有双边报价时的中间价
+fn mid(rec: &OptionRecord) -> Option<f64> {
+    (rec.bid_price > 0.0 && rec.ask_price >= rec.bid_price).then_some(0.5 * (rec.bid_price + rec.ask_price))
+}
+
+// 从快照时间到到期日纽约 16:00 的年数
+pub fn years_to_expiry(snapshot_time: NaiveDateTime, expiration: NaiveDate) -> f64 {
+    let close = expiration.and_time(NaiveTime::from_hms_opt(16, 0, 0).unwrap_or(NaiveTime::MIN));
+    (close - snapshot_time).num_seconds() as f64 / 86_400.0 / DAYS_PER_YEAR
+}
+
+// 看涨看跌中间价差最小的行权价上 F = K + (C - P) / D
+fn implied_forward(expiry: &Expiry, discount: f64) -> Option<f64> {
+    expiry.strikes.iter()
+        .filter_map(|row| {
+            let call = mid(row.call.as_ref()?)?;
+            let put = mid(row.put.as_ref()?)?;
+            Some((row.strike, call - put))
+        })
+        .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .map(|(strike, diff)| strike + diff / discount)
+        .filter(|f| *f > 0.0)
+}
+
+// 一个到期日的波动率微笑：对数价值度 ln(K/F) 上的自然三次样条，节点之外取端点的值
+#[derive(Serialize, Clone)]
+pub struct Smile {
+    pub expiration: String,
+    pub expiration_date: NaiveDate,
+    pub years: f64,
+    pub forward: f64,
+    pub log_moneyness: Vec<f64>,
+    pub iv: Vec<f64>,
+    // 各节点的二阶导数，由 log_moneyness / iv 算出
+    #[serde(skip)]
+    second: Vec<f64>,
+}
+
+impl Smile {
+    // 由保存的节点重建，节点需按 log_moneyness 升序
+    pub fn from_knots(expiration: &str, expiration_date: NaiveDate, years: f64, forward: f64, log_moneyness: Vec<f64>, iv: Vec<f64>) -> Smile {
+        let second = spline_second_derivatives(&log_moneyness, &iv);
+        Smile { expiration: expiration.to_string(), expiration_date, years, forward, log_moneyness, iv, second }
+    }
+
+    // 远期价格两侧各取虚值合约（行权价低于远期用看跌，否则用看涨）
+    pub fn fit(expiry: &Expiry, snapshot_time: NaiveDateTime, rate: f64) -> Option<Smile> {
+        let expiration_date = expiry.expiration_date?;
+        let years = years_to_expiry(snapshot_time, expiration_date);
+        if years <= 0.0 {
+            return None;
+        }
+        let discount = (-rate * years).exp();
+        let forward = implied_forward(expiry, discount)?;
+        let mut log_moneyness = Vec::new();
+        let mut iv = Vec::new();
+        for row in &expiry.strikes {
+            let is_call = row.strike >= forward;
+            let Some(price) = (if is_call { row.call.as_ref() } else { row.put.as_ref() }).and_then(mid) else {
+                continue;
+            };
+            if let Some(vol) = implied_vol(price, forward, row.strike, years, discount, is_call) {
+                log_moneyness.push((row.strike / forward).ln());
+                iv.push(vol);
+            }
+        }
+        (log_moneyness.len() >= 2).then(|| Smile::from_knots(&expiry.expiration, expiration_date, years, forward, log_moneyness, iv))
+    }
+
+    pub fn iv_at_moneyness(&self, k: f64) -> f64 {
+        let xs = &self.log_moneyness;
+        let n = xs.len();
+        if n == 0 {
+            return f64::NAN;
+        }
+        if k <= xs[0] {
+            return self.iv[0];
+        }
+        if k >= xs[n - 1] {
+            return self.iv[n - 1];
+        }
+        let i = xs.partition_point(|x| *x <= k).clamp(1, n - 1) - 1;
+        let h = xs[i + 1] - xs[i];
+        let a = (xs[i + 1] - k) / h;
+        let b = (k - xs[i]) / h;
+        a * self.iv[i] + b * self.iv[i + 1] + ((a * a * a - a) * self.second[i] + (b * b * b - b) * self.second[i + 1]) * h * h / 6.0
+    }
+
+    pub fn iv_at(&self, strike: f64) -> f64 {
+        self.iv_at_moneyness((strike / self.forward).ln())
+    }
+}
+
+// 自然样条（两端二阶导数为 0）的三对角方程，x 不是严格递增的节点按直线处理
+fn spline_second_derivatives(xs: &[f64], ys: &[f64]) -> Vec<f64> {
+    let n = xs.len();
+    let mut second = vec![0.0; n];
+    if n < 3 {
+        return second;
+    }
+    let mut u = vec![0.0; n];
+    for i in 1..n - 1 {
+        let span = xs[i + 1] - xs[i - 1];
+        if xs[i + 1] - xs[i] <= 0.0 || xs[i] - xs[i - 1] <= 0.0 || span <= 0.0 {
+            continue;
+        }
+        let sig = (xs[i] - xs[i - 1]) / span;
+        let p = sig * second[i - 1] + 2.0;
+        second[i] = (sig - 1.0) / p;
+        let slope = (ys[i + 1] - ys[i]) / (xs[i + 1] - xs[i]) - (ys[i] - ys[i - 1]) / (xs[i] - xs[i - 1]);
+        u[i] = (6.0 * slope / span - sig * u[i - 1]) / p;
+    }
+    for i in (1..n - 1).rev() {
+        second[i] = second[i] * second[i + 1] + u[i];
+    }
+    second
+}
+
+// 一个标的一次快照的波动率曲面，到期日升序
+#[derive(Serialize, Clone)]
+pub struct Surface {
+    pub underlying: String,
+    pub snapshot_time: NaiveDateTime,
+    pub rate: f64,
+    pub smiles: Vec<Smile>,
+}
+
+impl Surface {
+    // rate 是连续复利的无风险利率，只影响贴现，远期价格始终由平价推出
+    pub fn fit(chain: &OptionChain, rate: f64) -> Surface {
+        let smiles = chain.expirations.iter().filter_map(|e| Smile::fit(e, chain.snapshot_time, rate)).collect();
+        Surface { underlying: chain.underlying.clone(), snapshot_time: chain.snapshot_time, rate, smiles }
+    }
+
+    // 到期日之间按总方差 σ²t 线性插值（同一行权价在两侧各自的价值度上取值），早于第一个 / 晚于最后一个到期日时取最近的微笑
+    pub fn iv_at(&self, strike: f64, expiry: NaiveDate) -> Option<f64> {
+        let (first, last) = (self.smiles.first()?, self.smiles.last()?);
+        if let Some(smile) = self.smiles.iter().find(|s| s.expiration_date == expiry) {
+            return Some(smile.iv_at(strike));
+        }
+        if expiry <= first.expiration_date {
+            return Some(first.iv_at(strike));
+        }
+        if expiry >= last.expiration_date {
+            return Some(last.iv_at(strike));
+        }
+        let after = self.smiles.partition_point(|s| s.expiration_date < expiry);
+        let (before, after) = (&self.smiles[after - 1], &self.smiles[after]);
+        let years = years_to_expiry(self.snapshot_time, expiry);
+        let weight = (years - before.years) / (after.years - before.years);
+        let variance = |s: &Smile| s.iv_at(strike).powi(2) * s.years;
+        let total = variance(before) + weight * (variance(after) - variance(before));
+        (years > 0.0).then(|| (total / years).sqrt())
+    }
+}
+
+impl OptionChain {
+    pub fn surface(&self, rate: f64) -> Surface {
+        Surface::fit(self, rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::StrikeRow;
+
+    const FORWARD: f64 = 100.0;
+    const YEARS: f64 = 0.25;
+
+    fn discount(rate: f64, years: f64) -> f64 {
+        (-rate * years).exp()
+    }
+
+    fn quote(call_put: &str, strike: f64, price: f64) -> OptionRecord {
+        OptionRecord {
+            symbol: "SPX".to_string(),
+            call_put: call_put.to_string(),
+            expiration: "2026-11-20".to_string(),
+            strike_price: strike,
+            volume: 1,
+            matched: 0,
+            routed: 0,
+            bid_size: 1,
+            bid_price: price,
+            ask_size: 1,
+            ask_price: price,
+            last_price: price,
+        }
+    }
+
+    #[test]
+    fn implied_vol_round_trips_black76() {
+        let d = discount(0.05, YEARS);
+        for strike in [80.0, 95.0, 100.0, 105.0, 120.0] {
+            for vol in [0.15, 0.3, 0.8] {
+                for is_call in [true, false] {
+                    let price = black76(FORWARD, strike, YEARS, vol, d, is_call);
+                    let iv = implied_vol(price, FORWARD, strike, YEARS, d, is_call).unwrap();
+                    assert!((iv - vol).abs() < 1e-5, "K={strike} vol={vol} call={is_call}: {iv}");
+                    assert!((black76(FORWARD, strike, YEARS, iv, d, is_call) - price).abs() < 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn prices_outside_the_no_arbitrage_bounds_have_no_vol() {
+        let d = discount(0.05, YEARS);
+        // 低于贴现后的内在价值
+        assert_eq!(implied_vol(0.9 * d * 20.0, FORWARD, 80.0, YEARS, d, true), None);
+        assert_eq!(implied_vol(0.9 * d * 20.0, FORWARD, 120.0, YEARS, d, false), None);
+        // 看涨不能高于贴现后的远期价格，看跌不能高于贴现后的行权价
+        assert_eq!(implied_vol(d * FORWARD + 0.01, FORWARD, 100.0, YEARS, d, true), None);
+        assert_eq!(implied_vol(d * 100.0 + 0.01, FORWARD, 100.0, YEARS, d, false), None);
+        assert_eq!(implied_vol(0.0, FORWARD, 100.0, YEARS, d, true), None);
+        assert_eq!(implied_vol(5.0, FORWARD, 100.0, 0.0, d, true), None);
+    }
+
+    #[test]
+    fn spline_matches_knots_and_interpolates_between() {
+        let date = NaiveDate::from_ymd_opt(2026, 11, 20).unwrap();
+        let smile = Smile::from_knots("2026-11-20", date, YEARS, FORWARD, vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 0.0]);
+        for (k, iv) in [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)] {
+            assert!((smile.iv_at_moneyness(k) - iv).abs() < 1e-12);
+        }
+        // 自然样条：中间节点的二阶导数 M = 6 * (-1 - 1) / 4 = -3，x = 0.5 处为 0.5 + 3 * 0.375 / 6
+        assert!((smile.iv_at_moneyness(0.5) - 0.6875).abs() < 1e-12);
+        assert!((smile.iv_at_moneyness(1.5) - 0.6875).abs() < 1e-12);
+        // 节点之外取端点的值
+        assert_eq!(smile.iv_at_moneyness(-1.0), 0.0);
+        assert_eq!(smile.iv_at_moneyness(3.0), 0.0);
+
+        // 两个节点时是直线
+        let line = Smile::from_knots("2026-11-20", date, YEARS, FORWARD, vec![-0.1, 0.1], vec![0.3, 0.2]);
+        assert!((line.iv_at_moneyness(0.0) - 0.25).abs() < 1e-12);
+        assert!((line.iv_at(FORWARD) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fits_a_flat_smile_from_parity_priced_quotes() {
+        let snapshot_time = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        let expiration_date = NaiveDate::from_ymd_opt(2026, 11, 20).unwrap();
+        let years = years_to_expiry(snapshot_time, expiration_date);
+        let d = discount(0.05, years);
+        let strikes = (16..=24)
+            .map(|i| {
+                let strike = i as f64 * 5.0;
+                let call = black76(FORWARD, strike, years, 0.2, d, true);
+                let put = black76(FORWARD, strike, years, 0.2, d, false);
+                StrikeRow { strike, call: Some(quote("C", strike, call)), put: Some(quote("P", strike, put)) }
+            })
+            .collect();
+        let expiry = Expiry { expiration: "2026-11-20".to_string(), expiration_date: Some(expiration_date), strikes };
+
+        let smile = Smile::fit(&expiry, snapshot_time, 0.05).unwrap();
+        assert!((smile.forward - FORWARD).abs() < 1e-6);
+        assert_eq!(smile.iv.len(), 9);
+        assert!(smile.iv.iter().all(|iv| (iv - 0.2).abs() < 1e-5));
+        assert!((smile.iv_at(97.5) - 0.2).abs() < 1e-5);
+
+        // 到期之后没有微笑
+        assert!(Smile::fit(&expiry, expiration_date.and_hms_opt(16, 0, 0).unwrap(), 0.05).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Result, anyhow};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+
+use crate::cboe::OptionRecord;
+use crate::chain::OptionChain;
+use crate::config::AnalyticsConfig;
+use crate::output::Table;
+use crate::surface::{Smile, Surface};
+
+// 每次加载后为这些标的拟合波动率曲面，写入 t_cboe_iv_surface；rate 是连续复利的无风险利率
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SurfaceConfig {
+    pub symbols: Vec<String>,
+    pub rate: f64,
+}
+
+impl SurfaceConfig {
+    pub fn wants(&self, rec: &OptionRecord) -> bool {
+        self.symbols.iter().any(|s| s.trim().eq_ignore_ascii_case(rec.symbol.trim()))
+    }
+}
+
+async fn store(pool: &PgPool, surface: &Surface, market: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM t_cboe_iv_surface WHERE symbol = $1 AND snapshot_time = $2")
+        .bind(&surface.underlying)
+        .bind(surface.snapshot_time)
+        .execute(&mut *tx)
+        .await?;
+    for smile in &surface.smiles {
+        sqlx::query(r#"
+            INSERT INTO t_cboe_iv_surface (symbol, snapshot_time, expiration, expiration_date, years, forward, rate, log_moneyness, iv, market)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#)
+            .bind(&surface.underlying)
+            .bind(surface.snapshot_time)
+            .bind(&smile.expiration)
+            .bind(smile.expiration_date)
+            .bind(smile.years)
+            .bind(smile.forward)
+            .bind(surface.rate)
+            .bind(&smile.log_moneyness)
+            .bind(&smile.iv)
+            .bind(market)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// 同一合约在各市场的报价不同，合在一条链里会按行权价互相覆盖；每个标的只用成交量最大的市场（相同时取靠前的）
+fn busiest_market<'a>(underlying: &str, snapshot_time: NaiveDateTime, markets: &'a [(String, Vec<OptionRecord>)]) -> Option<(&'a str, OptionChain)> {
+    let mut best: Option<(&str, OptionChain)> = None;
+    for (market, records) in markets {
+        let chain = OptionChain::from_records(underlying, snapshot_time, records);
+        if chain.expirations.is_empty() {
+            continue;
+        }
+        if best.as_ref().is_none_or(|(_, b)| chain.total_volume() > b.total_volume()) {
+            best = Some((market, chain));
+        }
+    }
+    best
+}
+
+// markets 是本次快照每个市场里 config.symbols 的合约
+pub async fn fit(pool: &PgPool, config: &SurfaceConfig, analytics: &AnalyticsConfig, snapshot_time: NaiveDateTime, markets: &[(String, Vec<OptionRecord>)]) -> Result<()> {
+    let kept: Vec<(String, Vec<OptionRecord>)>;
+    let markets = if analytics.exclude_adjusted {
+        kept = markets.iter()
+            .map(|(market, records)| (market.clone(), records.iter().filter(|r| !r.is_adjusted()).cloned().collect()))
+            .collect();
+        &kept[..]
+    } else {
+        markets
+    };
+    for symbol in &config.symbols {
+        let Some((market, chain)) = busiest_market(&symbol.trim().to_ascii_uppercase(), snapshot_time, markets) else {
+            warn!("No contracts to fit an IV surface for {}.", symbol);
+            continue;
+        };
+        let surface = Surface::fit(&chain, config.rate);
+        if surface.smiles.is_empty() {
+            warn!("No usable two-sided quotes to fit an IV surface for {} in {}.", symbol, market);
+            continue;
+        }
+        store(pool, &surface, market).await?;
+        info!("Fitted IV smiles for {} of {} {} expirations from {}.", surface.smiles.len(), chain.expirations.len(), chain.underlying, market);
+    }
+    Ok(())
+}
+
+// 读取保存的曲面，不指定快照时取该标的最近一次
+pub async fn load(pool: &PgPool, symbol: &str, snapshot: Option<NaiveDateTime>) -> Result<Surface> {
+    let rows = sqlx::query(r#"
+        SELECT snapshot_time, expiration, expiration_date, years, forward, rate, log_moneyness, iv
+        FROM t_cboe_iv_surface
+        WHERE symbol = $1
+          AND snapshot_time = COALESCE($2, (SELECT max(snapshot_time) FROM t_cboe_iv_surface WHERE symbol = $1))
+        ORDER BY expiration_date
+    "#)
+        .bind(symbol)
+        .bind(snapshot)
+        .fetch_all(pool)
+        .await?;
+    let first = rows.first().ok_or_else(|| anyhow!("No IV surface stored for {}", symbol))?;
+    let mut surface = Surface {
+        underlying: symbol.to_string(),
+        snapshot_time: first.try_get("snapshot_time")?,
+        rate: first.try_get("rate")?,
+        smiles: Vec::with_capacity(rows.len()),
+    };
+    for row in &rows {
+        surface.smiles.push(Smile::from_knots(
+            row.try_get::<&str, _>("expiration")?,
+            row.try_get("expiration_date")?,
+            row.try_get("years")?,
+            row.try_get("forward")?,
+            row.try_get("log_moneyness")?,
+            row.try_get("iv")?,
+        ));
+    }
+    Ok(surface)
+}
+
+// 指定行权价和到期日时插值出一个波动率，否则列出每个到期日的远期价格和平值波动率
+pub async fn query(pool: &PgPool, symbol: &str, strike: Option<f64>, expiry: Option<NaiveDate>, snapshot: Option<NaiveDateTime>) -> Result<Table> {
+    let surface = load(pool, &symbol.trim().to_ascii_uppercase(), snapshot).await?;
+    let snapshot_time = surface.snapshot_time.format("%Y-%m-%d %H:%M:%S").to_string();
+    if let (Some(strike), Some(expiry)) = (strike, expiry) {
+        let mut table = Table::new(vec!["snapshot_time", "symbol", "expiration", "strike", "iv"]);
+        table.push(vec![json!(snapshot_time), json!(surface.underlying), json!(expiry), json!(strike), json!(surface.iv_at(strike, expiry))]);
+        return Ok(table);
+    }
+    let mut table = Table::new(vec!["snapshot_time", "symbol", "expiration", "years", "forward", "atm_iv", "points"]);
+    for smile in &surface.smiles {
+        table.push(vec![
+            json!(snapshot_time),
+            json!(surface.underlying),
+            json!(smile.expiration),
+            json!((smile.years * 1e4).round() / 1e4),
+            json!((smile.forward * 100.0).round() / 100.0),
+            json!((smile.iv_at(smile.forward) * 1e4).round() / 1e4),
+            json!(smile.iv.len()),
+        ]);
+    }
+    Ok(table)
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime};
+use log::info;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+
+use crate::output::Table;
+use crate::storage::SnapshotTable;
+
+// 用一次快照更新标的目录；按快照时间取 LEAST / GREATEST，补导入历史文件时顺序无关
+// 开启变化检测时快照表只有变化的合约，改从状态表统计本次出现的合约
+pub async fn update(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime) -> Result<u64> {
+    let source = if table.change_detection {
+        "t_cboe_contract_state WHERE seen_at = $1".to_string()
+    } else {
+        format!("{table} WHERE last_updated_time = $1 AND source = 'symbol_data'")
+    };
+    let rows = sqlx::query(&format!(r#"
+        INSERT INTO t_cboe_symbols AS s (symbol, first_seen, last_seen, markets, active_contracts, updated_at)
+        SELECT symbol, $1, $1,
+               array_remove(array_agg(DISTINCT market ORDER BY market), ''),
+               count(DISTINCT (call_put, expiration, strike_price)),
+               now()
+        FROM {source}
+        GROUP BY symbol
+        ON CONFLICT (symbol) DO UPDATE SET
+            first_seen = LEAST(s.first_seen, EXCLUDED.first_seen),
+            last_seen = GREATEST(s.last_seen, EXCLUDED.last_seen),
+            markets = ARRAY(SELECT DISTINCT m FROM unnest(s.markets || EXCLUDED.markets) AS m ORDER BY m),
+            active_contracts = CASE WHEN EXCLUDED.last_seen >= s.last_seen THEN EXCLUDED.active_contracts ELSE s.active_contracts END,
+            updated_at = EXCLUDED.updated_at
+        RETURNING symbol, (xmax = 0) AS inserted
+    "#))
+        .bind(snapshot_time)
+        .fetch_all(pool)
+        .await?;
+    let mut listed = Vec::new();
+    for row in &rows {
+        if row.try_get::<bool, _>("inserted")? {
+            listed.push(row.try_get::<String, _>("symbol")?);
+        }
+    }
+    if !listed.is_empty() {
+        info!("{} new symbols: {}", listed.len(), listed.join(", "));
+    }
+    info!("Updated {} symbols in t_cboe_symbols.", rows.len());
+    Ok(listed.len() as u64)
+}
+
+// 最近 N 天新出现的标的，以及最近 N 天内最后一次出现、之后的快照里不再出现的标的
+pub async fn listings(pool: &PgPool, days: i64) -> Result<Table> {
+    let latest: Option<NaiveDateTime> = sqlx::query_scalar("SELECT max(last_seen) FROM t_cboe_symbols")
+        .fetch_one(pool)
+        .await?;
+    let mut table = Table::new(vec!["change", "symbol", "first_seen", "last_seen", "markets", "contracts"]);
+    let Some(latest) = latest else {
+        return Ok(table);
+    };
+    let rows = sqlx::query(r#"
+        SELECT CASE WHEN last_seen < $1 THEN 'delisted' ELSE 'listed' END AS change,
+               symbol, first_seen, last_seen, markets, active_contracts
+        FROM t_cboe_symbols
+        WHERE first_seen >= $2 OR (last_seen < $1 AND last_seen >= $2)
+        ORDER BY change DESC, symbol
+    "#)
+        .bind(latest)
+        .bind(latest - Duration::days(days))
+        .fetch_all(pool)
+        .await?;
+    for row in rows {
+        table.push(vec![
+            json!(row.try_get::<String, _>("change")?),
+            json!(row.try_get::<String, _>("symbol")?),
+            json!(row.try_get::<NaiveDateTime, _>("first_seen")?.format("%Y-%m-%d %H:%M:%S").to_string()),
+            json!(row.try_get::<NaiveDateTime, _>("last_seen")?.format("%Y-%m-%d %H:%M:%S").to_string()),
+            json!(row.try_get::<Vec<String>, _>("markets")?.join(",")),
+            json!(row.try_get::<i64, _>("active_contracts")?),
+        ]);
+    }
+    Ok(table)
+}
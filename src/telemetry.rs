@@ -0,0 +1,68 @@
+// 设置了标准 OTEL_EXPORTER_OTLP_* 环境变量时把 tracing span 通过 OTLP/HTTP 导出；未启用 otel feature 时为空操作
+#[cfg(feature = "otel")]
+mod otlp {
+    use anyhow::Result;
+    use log::{info, warn};
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    pub struct Telemetry {
+        provider: SdkTracerProvider,
+    }
+
+    fn configured() -> bool {
+        ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+            .iter()
+            .any(|name| std::env::var(name).is_ok_and(|v| !v.is_empty()))
+    }
+
+    pub fn init() -> Result<Option<Telemetry>> {
+        if !configured() {
+            return Ok(None);
+        }
+        let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+        let mut resource = Resource::builder();
+        if std::env::var("OTEL_SERVICE_NAME").is_err() {
+            resource = resource.with_service_name(env!("CARGO_PKG_NAME"));
+        }
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .try_init()?;
+        info!("OpenTelemetry trace export enabled.");
+        Ok(Some(Telemetry { provider }))
+    }
+
+    impl Telemetry {
+        // 退出前把缓冲的 span 发出去
+        pub fn shutdown(self) {
+            if let Err(e) = self.provider.shutdown() {
+                warn!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+mod otlp {
+    use anyhow::Result;
+
+    pub struct Telemetry;
+
+    pub fn init() -> Result<Option<Telemetry>> {
+        Ok(None)
+    }
+
+    impl Telemetry {
+        pub fn shutdown(self) {}
+    }
+}
+
+pub use otlp::init;
@@ -0,0 +1,88 @@
+//! 不访问 cboe.com 的替身，用于单元测试：`fetch_stream(&fetcher, ...)` 读预先设置的页面和 CSV，
+//! `TradingCalendar` 的开收盘判断用 `FixedClock` 的时间；命令行程序的测试也用它们跑完整的加载流程
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::America;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::cboe;
+use crate::clock::Clock;
+use crate::http::{Download, HttpFetcher};
+
+// 固定的当前时间，可以手动推进
+pub struct FixedClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> FixedClock {
+        FixedClock { now: Mutex::new(now) }
+    }
+
+    // 纽约本地时间；夏令时切换时不存在的时间取最早的合法时间
+    pub fn new_york(now: NaiveDateTime) -> FixedClock {
+        let now = America::New_York.from_local_datetime(&now).earliest().map(|t| t.with_timezone(&Utc)).unwrap_or_else(|| now.and_utc());
+        FixedClock::new(now)
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// 按 URL 返回预先设置的响应，没有设置的 URL 返回错误（相当于 404）；记录所有请求过的 URL
+#[derive(Default)]
+pub struct MemoryFetcher {
+    responses: Mutex<HashMap<String, Bytes>>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl MemoryFetcher {
+    pub fn new() -> MemoryFetcher {
+        MemoryFetcher::default()
+    }
+
+    pub fn respond(self, url: &str, body: impl Into<Bytes>) -> MemoryFetcher {
+        self.responses.lock().unwrap_or_else(PoisonError::into_inner).insert(url.to_string(), body.into());
+        self
+    }
+
+    // cboe.com 页面上显示的快照时间
+    pub fn with_snapshot_time(self, snapshot_time: NaiveDateTime) -> MemoryFetcher {
+        let page = format!("<p>Data last updated {}</p>", snapshot_time.format("%Y-%m-%d %H:%M:%S"));
+        self.respond(cboe::PAGE_URL, page)
+    }
+
+    // 一个市场的 CSV，格式同 cboe.com 的下载
+    pub fn with_market_csv(self, market: &str, csv: impl Into<Bytes>) -> MemoryFetcher {
+        let url = cboe::csv_url(market);
+        self.respond(&url, csv)
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+#[async_trait]
+impl HttpFetcher for MemoryFetcher {
+    async fn get_download(&self, url: &str) -> Result<Download> {
+        self.requests.lock().unwrap_or_else(PoisonError::into_inner).push(url.to_string());
+        let body = self.responses.lock().unwrap_or_else(PoisonError::into_inner).get(url).cloned()
+            .ok_or_else(|| anyhow!("No response registered for {}", url))?;
+        Ok(Download { wire_bytes: body.len() as u64, body })
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::storage::SnapshotTable;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TimescaleConfig {
+    pub enabled: bool,
+    pub chunk_interval: String,
+    pub compress_after: Option<String>,
+}
+
+impl Default for TimescaleConfig {
+    fn default() -> Self {
+        TimescaleConfig {
+            enabled: false,
+            chunk_interval: "1 day".to_string(),
+            compress_after: None,
+        }
+    }
+}
+
+// 只由 timescale-setup 子命令执行，可以重复运行；已经开启压缩的表不再修改压缩设置，存在压缩 chunk 时 ALTER TABLE 会失败
+#[cfg(feature = "timescale")]
+pub async fn setup(pool: &PgPool, table: &SnapshotTable, config: &TimescaleConfig) -> Result<()> {
+    use log::info;
+
+    sqlx::query("CREATE EXTENSION IF NOT EXISTS timescaledb").execute(pool).await?;
+    sqlx::query(r#"
+        SELECT create_hypertable($2::regclass, 'last_updated_time',
+                                 chunk_time_interval => $1::interval,
+                                 if_not_exists => TRUE, migrate_data => TRUE)
+    "#)
+        .bind(&config.chunk_interval)
+        .bind(table.to_string())
+        .execute(pool)
+        .await?;
+    // create_hypertable 在表已存在时不会修改分块间隔，这里显式设置，只影响之后新建的 chunk
+    sqlx::query("SELECT set_chunk_time_interval($2::regclass, $1::interval)")
+        .bind(&config.chunk_interval)
+        .bind(table.to_string())
+        .execute(pool)
+        .await?;
+    info!("Hypertable ready with chunk interval {}.", config.chunk_interval);
+
+    if let Some(compress_after) = &config.compress_after {
+        let compressed: bool = sqlx::query_scalar(r#"
+            SELECT EXISTS (
+                SELECT 1 FROM timescaledb_information.compression_settings
+                WHERE hypertable_name = $1 AND hypertable_schema = COALESCE($2, current_schema())
+            )
+        "#)
+            .bind(&table.name)
+            .bind(&table.schema)
+            .fetch_one(pool)
+            .await?;
+        if compressed {
+            info!("Compression is already enabled, keeping the existing settings.");
+        } else {
+            sqlx::query(&format!(r#"
+                ALTER TABLE {table} SET (
+                    timescaledb.compress,
+                    timescaledb.compress_segmentby = 'symbol',
+                    timescaledb.compress_orderby = 'last_updated_time DESC'
+                )
+            "#))
+                .execute(pool)
+                .await?;
+        }
+        sqlx::query("SELECT add_compression_policy($2::regclass, $1::interval, if_not_exists => TRUE)")
+            .bind(compress_after)
+            .bind(table.to_string())
+            .execute(pool)
+            .await?;
+        info!("Compression policy set for chunks older than {}.", compress_after);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "timescale"))]
+pub async fn setup(_pool: &PgPool, _table: &SnapshotTable, _config: &TimescaleConfig) -> Result<()> {
+    anyhow::bail!("timescale-setup requires building with --features timescale")
+}
+
+// 按 chunk 删除过期数据，比逐行 DELETE 快得多；只删整个 chunk 都早于 cutoff 的部分，返回的是 chunk 数而不是行数
+#[cfg(feature = "timescale")]
+pub async fn drop_chunks(pool: &PgPool, table: &SnapshotTable, cutoff: NaiveDateTime, dry_run: bool) -> Result<u64> {
+    let function = if dry_run { "show_chunks" } else { "drop_chunks" };
+    let chunks = sqlx::query(&format!("SELECT {}($2::regclass, older_than => $1)", function))
+        .bind(cutoff)
+        .bind(table.to_string())
+        .fetch_all(pool)
+        .await?
+        .len() as u64;
+    Ok(chunks)
+}
+
+#[cfg(not(feature = "timescale"))]
+pub async fn drop_chunks(_pool: &PgPool, _table: &SnapshotTable, _cutoff: NaiveDateTime, _dry_run: bool) -> Result<u64> {
+    anyhow::bail!("[timescale] enabled requires building with --features timescale")
+}
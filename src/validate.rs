@@ -0,0 +1,200 @@
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use regex::Regex;
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cboe::{ContractKey, OptionRecord};
+use crate::changes;
+use crate::storage::SnapshotTable;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    pub enabled: bool,
+    pub crossed_market: bool,
+    pub negative_price: bool,
+    pub zero_strike: bool,
+    pub expired: bool,
+    pub symbol_pattern: Option<String>,
+    // 成交量超过上一快照的 N 倍且增量不小于 volume_jump_min 时报警
+    pub volume_jump_factor: Option<f64>,
+    pub volume_jump_min: i64,
+    pub store_issues: bool,
+    pub max_issues: Option<u64>,
+    pub max_issue_ratio: Option<f64>,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            enabled: false,
+            crossed_market: true,
+            negative_price: true,
+            zero_strike: true,
+            expired: true,
+            symbol_pattern: None,
+            volume_jump_factor: None,
+            volume_jump_min: 1000,
+            store_issues: false,
+            max_issues: None,
+            max_issue_ratio: None,
+        }
+    }
+}
+
+pub struct Issue {
+    pub rule: &'static str,
+    pub key: ContractKey,
+    pub strike_price: f64,
+    pub detail: String,
+}
+
+pub struct Validator<'a> {
+    config: &'a ValidationConfig,
+    symbol_pattern: Option<Regex>,
+    prior_volume: HashMap<ContractKey, i64>,
+    snapshot_time: NaiveDateTime,
+}
+
+impl<'a> Validator<'a> {
+    pub async fn new(pool: Option<&PgPool>, table: &SnapshotTable, config: &'a ValidationConfig, snapshot_time: NaiveDateTime) -> Result<Validator<'a>> {
+        let symbol_pattern = config.symbol_pattern.as_deref()
+            .map(|p| Regex::new(p).with_context(|| format!("Invalid symbol_pattern {}", p)))
+            .transpose()?;
+        // 成交量突变检查需要从 Postgres 读取上一快照
+        let prior_volume = match (config.volume_jump_factor, pool) {
+            (Some(_), Some(pool)) => load_prior_volume(pool, table, snapshot_time).await?,
+            _ => HashMap::new(),
+        };
+        Ok(Validator { config, symbol_pattern, prior_volume, snapshot_time })
+    }
+
+    pub fn check(&self, records: &[OptionRecord]) -> Vec<Issue> {
+        let today = self.snapshot_time.date();
+        let mut issues = Vec::new();
+        for rec in records {
+            let mut flag = |rule: &'static str, detail: String| {
+                issues.push(Issue { rule, key: rec.key(), strike_price: rec.strike_price, detail });
+            };
+            if self.config.crossed_market && rec.bid_price > 0.0 && rec.ask_price > 0.0 && rec.bid_price > rec.ask_price {
+                flag("crossed_market", format!("bid {} > ask {}", rec.bid_price, rec.ask_price));
+            }
+            if self.config.negative_price && (rec.bid_price < 0.0 || rec.ask_price < 0.0 || rec.last_price < 0.0) {
+                flag("negative_price", format!("bid {} ask {} last {}", rec.bid_price, rec.ask_price, rec.last_price));
+            }
+            if self.config.zero_strike && rec.strike_price <= 0.0 {
+                flag("zero_strike", format!("strike {}", rec.strike_price));
+            }
+            if self.config.expired {
+                match rec.expiration_date() {
+                    Some(d) if d < today => flag("expired", format!("expired {}", d)),
+                    None => flag("expired", format!("unparseable expiration {}", rec.expiration)),
+                    _ => {}
+                }
+            }
+            if let Some(re) = &self.symbol_pattern
+                && !re.is_match(&rec.symbol)
+            {
+                flag("symbol_pattern", format!("symbol {} does not match {}", rec.symbol, re.as_str()));
+            }
+            if let Some(factor) = self.config.volume_jump_factor
+                && let Some(prior) = self.prior_volume.get(&rec.key())
+                && *prior > 0
+                && rec.volume as f64 > *prior as f64 * factor
+                && rec.volume - prior >= self.config.volume_jump_min
+            {
+                flag("volume_jump", format!("volume {} vs prior {}", rec.volume, prior));
+            }
+        }
+        issues
+    }
+
+    // 超过阈值时返回错误，调用方不再写入该市场的数据
+    pub fn enforce(&self, market: &str, issues: &[Issue], rows: usize) -> Result<()> {
+        let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+        for issue in issues {
+            *counts.entry(issue.rule).or_default() += 1;
+        }
+        if issues.is_empty() {
+            info!("Validation passed for {}.", market);
+        } else {
+            let summary: Vec<String> = counts.iter().map(|(rule, n)| format!("{}={}", rule, n)).collect();
+            warn!("Validation found {} issues in {}: {}", issues.len(), market, summary.join(", "));
+        }
+
+        let count = issues.len() as u64;
+        if let Some(max) = self.config.max_issues
+            && count > max
+        {
+            bail!("{} has {} data-quality issues (max {})", market, count, max);
+        }
+        if let Some(ratio) = self.config.max_issue_ratio
+            && rows > 0
+            && count as f64 / rows as f64 > ratio
+        {
+            bail!("{} has {} data-quality issues in {} rows (max ratio {})", market, count, rows, ratio);
+        }
+        Ok(())
+    }
+}
+
+// 开启变化检测时本次快照还没写入，状态表里最近一次出现的时间就是上一快照，沿用的合约也要比较
+async fn load_prior_volume(pool: &PgPool, table: &SnapshotTable, snapshot_time: NaiveDateTime) -> Result<HashMap<ContractKey, i64>> {
+    let last_seen = match table.change_detection {
+        true => changes::last_seen(pool).await?.filter(|t| *t < snapshot_time),
+        false => None,
+    };
+    let prior = match last_seen {
+        Some(t) => Some(t),
+        None => sqlx::query_scalar(&format!("SELECT max(last_updated_time) FROM {table} WHERE last_updated_time < $1 AND source = 'symbol_data'"))
+            .bind(snapshot_time)
+            .fetch_one(pool)
+            .await?,
+    };
+    let Some(prior_time) = prior else {
+        return Ok(HashMap::new());
+    };
+    let source = changes::snapshot_sql(table, last_seen.is_some());
+    let rows = sqlx::query(&format!("SELECT symbol, call_put, expiration, strike_price, volume FROM ({source}) AS snapshot"))
+        .bind(prior_time)
+        .fetch_all(pool)
+        .await?;
+    let mut prior = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let key = ContractKey::new(
+            row.try_get("symbol")?,
+            row.try_get("call_put")?,
+            row.try_get("expiration")?,
+            row.try_get("strike_price")?,
+        );
+        prior.insert(key, row.try_get("volume")?);
+    }
+    Ok(prior)
+}
+
+pub async fn store_issues(pool: &PgPool, run_id: i64, market: &str, snapshot_time: NaiveDateTime, issues: &[Issue]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for issue in issues {
+        sqlx::query(r#"
+            INSERT INTO t_cboe_snapshot_dq_issues
+            (run_id, market, snapshot_time, rule, symbol, call_put, expiration, strike_price, detail)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#)
+            .bind(run_id)
+            .bind(market)
+            .bind(snapshot_time)
+            .bind(issue.rule)
+            .bind(&issue.key.symbol)
+            .bind(&issue.key.call_put)
+            .bind(&issue.key.expiration)
+            .bind(issue.strike_price)
+            .bind(&issue.detail)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
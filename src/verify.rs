@@ -0,0 +1,114 @@
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+
+use crate::cboe::OptionRecord;
+use crate::cli::VerifyArgs;
+use crate::output::Table;
+use crate::storage::SnapshotTable;
+
+// 一个市场一次快照的校验和：按冲突键去重（后出现的覆盖前面的，与写入时 DO UPDATE 一致）后按键排序，
+// 浮点数按位编码，同样的数据无论 CSV 行序如何都得到同样的结果
+pub struct Checksum {
+    pub digest: String,
+    pub rows: u64,
+}
+
+pub fn checksum(records: &[OptionRecord]) -> Checksum {
+    let mut rows = BTreeMap::new();
+    for rec in records {
+        let key = (rec.symbol.as_str(), rec.call_put.as_str(), rec.expiration.as_str(), rec.strike_price.to_bits());
+        rows.insert(key, rec);
+    }
+    let mut hasher = Sha256::new();
+    for ((symbol, call_put, expiration, strike), rec) in &rows {
+        hasher.update(format!(
+            "{}\x1f{}\x1f{}\x1f{:016x}\x1f{}\x1f{}\x1f{}\x1f{}\x1f{:016x}\x1f{}\x1f{:016x}\x1f{:016x}\n",
+            symbol, call_put, expiration, strike, rec.volume, rec.matched, rec.routed,
+            rec.bid_size, rec.bid_price.to_bits(), rec.ask_size, rec.ask_price.to_bits(), rec.last_price.to_bits(),
+        ));
+    }
+    Checksum { digest: format!("{:x}", hasher.finalize()), rows: rows.len() as u64 }
+}
+
+async fn stored_records(pool: &PgPool, table: &SnapshotTable, market: &str, snapshot_time: NaiveDateTime) -> Result<Vec<OptionRecord>> {
+    let rows = sqlx::query(&format!(r#"
+        SELECT symbol, call_put, expiration, strike_price, volume, matched, routed,
+               bid_size, bid_price, ask_size, ask_price, last_price
+        FROM {table}
+        WHERE last_updated_time = $1 AND market = $2 AND source = 'symbol_data'
+    "#))
+        .bind(snapshot_time)
+        .bind(market)
+        .fetch_all(pool)
+        .await?;
+    rows.iter()
+        .map(|row| Ok(OptionRecord {
+            symbol: row.try_get("symbol")?,
+            call_put: row.try_get("call_put")?,
+            expiration: row.try_get("expiration")?,
+            strike_price: row.try_get("strike_price")?,
+            volume: row.try_get("volume")?,
+            matched: row.try_get("matched")?,
+            routed: row.try_get("routed")?,
+            bid_size: row.try_get("bid_size")?,
+            bid_price: row.try_get("bid_price")?,
+            ask_size: row.try_get("ask_size")?,
+            ask_price: row.try_get("ask_price")?,
+            last_price: row.try_get("last_price")?,
+        }))
+        .collect()
+}
+
+// 从数据库重新计算运行日志里记录过校验和的快照；整份快照已被 prune 删除的记为 missing，不算不一致
+pub async fn run(pool: &PgPool, snapshot_table: &SnapshotTable, args: &VerifyArgs) -> Result<()> {
+    let rows = sqlx::query(r#"
+        SELECT run_id, market, snapshot_time, checksum, checksum_rows
+        FROM t_cboe_snapshot_etl_log_market
+        WHERE checksum IS NOT NULL AND status = 'done'
+          AND ($1::BIGINT IS NULL OR run_id = $1)
+          AND ($2::TIMESTAMP IS NULL OR snapshot_time >= $2)
+        ORDER BY snapshot_time DESC, market
+        LIMIT $3
+    "#)
+        .bind(args.run)
+        .bind(args.since)
+        .bind(args.limit)
+        .fetch_all(pool)
+        .await?;
+
+    let mut table = Table::new(vec!["run_id", "market", "snapshot_time", "rows_loaded", "rows_stored", "status"]);
+    let mut diverged = 0;
+    for row in &rows {
+        let market: String = row.try_get("market")?;
+        let snapshot_time: NaiveDateTime = row.try_get("snapshot_time")?;
+        let expected: String = row.try_get("checksum")?;
+        let expected_rows: i64 = row.try_get("checksum_rows")?;
+        let records = stored_records(pool, snapshot_table, &market, snapshot_time).await?;
+        let actual = checksum(&records);
+        let status = if actual.rows == 0 && expected_rows > 0 {
+            "missing"
+        } else if actual.digest == expected {
+            "ok"
+        } else {
+            diverged += 1;
+            "MISMATCH"
+        };
+        table.push(vec![
+            json!(row.try_get::<i64, _>("run_id")?),
+            json!(market),
+            json!(snapshot_time.format("%Y-%m-%d %H:%M:%S").to_string()),
+            json!(expected_rows),
+            json!(actual.rows),
+            json!(status),
+        ]);
+    }
+    table.print(args.format)?;
+    if diverged > 0 {
+        bail!("{} of {} snapshots no longer match the checksum recorded at load time", diverged, rows.len());
+    }
+    Ok(())
+}